@@ -2,11 +2,13 @@
 
 use proc_macro2::{Ident, Literal, Span, TokenStream, TokenTree};
 use quote::{quote, quote_spanned};
-use syn::Error;
+use syn::{spanned::Spanned, Error};
 
+use super::generics::DeriveGenerics;
 use super::shared::FieldIdent;
 
 /// The fields for a struct or an enum variant.
+#[derive(Clone)]
 pub struct Fields {
     pub kind: FieldKind,
     fields: Vec<Field>,
@@ -20,11 +22,61 @@ pub enum FieldKind {
     Unnamed,
 }
 
+/// A single variant of an enum.
+#[derive(Clone)]
+pub struct Variant {
+    pub ident: Ident,
+    pub fields: Fields,
+}
+
+#[derive(Clone)]
 pub struct Field {
+    /// The name of the field in keypaths, which may have been renamed.
     pub ident: FieldIdent,
+    /// The field itself, for accessing it on the type.
+    member: syn::Member,
     pub ty: syn::Type,
+    /// The visibility of the field, which is also used for the mirror's field.
+    pub vis: syn::Visibility,
     span: Span,
-    //pub attrs: Attrs,
+    pub attrs: Attrs,
+}
+
+/// The options set by `#[keypath(...)]` on a field.
+#[derive(Clone, Default)]
+pub struct Attrs {
+    /// The field is not part of any keypath.
+    pub skip: bool,
+    /// The name used for the field in keypaths.
+    pub rename: Option<String>,
+    /// The field is a leaf, and its type need not be `Keyable`.
+    pub opaque: bool,
+    /// The fields of the field's type are accessed as if they were fields
+    /// of the containing type.
+    pub flatten: bool,
+}
+
+/// The options set by `#[keypath(...)]` on a struct or enum.
+#[derive(Clone, Default)]
+pub struct ContainerAttrs {
+    /// The struct's single field is flattened.
+    pub transparent: bool,
+    /// The type is `Default`, so missing values of it can be inserted.
+    pub default: bool,
+    /// The path to the keypath crate, if it is not `::keypath`.
+    pub crate_path: Option<syn::Path>,
+    /// The type is a collection, which is indexed through the corresponding
+    /// trait rather than by its fields.
+    pub collection: Option<Collection>,
+}
+
+/// The kinds of collection that a derived type can be.
+#[derive(Clone, Copy)]
+pub enum Collection {
+    /// `#[keypath(sequence)]`, for types that implement `KeyableSequence`.
+    Sequence,
+    /// `#[keypath(map)]`, for types that implement `KeyableMap`.
+    Map,
 }
 
 impl Fields {
@@ -40,49 +92,218 @@ impl Fields {
             .map(|(i, field)| Field::parse_ast(field, i))
             .collect::<Result<Vec<_>, _>>()?;
 
+        // flattened fields are reached through the mirror's `Deref` impl,
+        // and there can only be one of those.
+        if let Some(second) = fields.iter().filter(|f| f.attrs.flatten).nth(1) {
+            return Err(Error::new(
+                second.ty.span(),
+                "only one field can be flattened; the flattened type can flatten a field of its own",
+            ));
+        }
+
         Ok(Fields { kind, fields })
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &Field> {
-        self.fields.iter()
+    /// Flatten the only field, for `#[keypath(transparent)]`.
+    pub fn make_transparent(&mut self, span: Span) -> Result<(), Error> {
+        match self.fields.as_mut_slice() {
+            [field] if !field.attrs.skip && !field.attrs.opaque => {
+                field.attrs.flatten = true;
+                Ok(())
+            }
+            [_] => Err(Error::new(
+                span,
+                "the field of a `transparent` struct cannot be `skip` or `opaque`",
+            )),
+            _ => Err(Error::new(
+                span,
+                "`transparent` structs must have exactly one field",
+            )),
+        }
+    }
+
+    /// The fields that are part of keypaths; that is, those not skipped.
+    ///
+    /// Flattened fields come last, so that in match arms the type's own
+    /// fields take precedence over those of a flattened field.
+    pub fn iter_keyable(&self) -> impl Iterator<Item = &Field> {
+        let (flattened, fields): (Vec<_>, Vec<_>) = self
+            .fields
+            .iter()
+            .filter(|f| !f.attrs.skip)
+            .partition(|f| f.attrs.flatten);
+        fields.into_iter().chain(flattened)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Visit the types of all fields, such as to rewrite them.
+    pub fn visit_types(&mut self, visitor: &mut impl syn::visit_mut::VisitMut) {
+        for field in &mut self.fields {
+            visitor.visit_type_mut(&mut field.ty);
+        }
     }
 
     pub fn generate_mirror_decls(&self) -> TokenStream {
         match self.kind {
             _ if self.fields.is_empty() => TokenStream::new(),
             FieldKind::Unnamed => {
-                let types = self.fields.iter().map(|f| &f.ty);
-                quote!( #( <#types as ::keypath::Keyable>::Mirror ),* )
+                let vis = self.fields.iter().map(|f| &f.vis);
+                let types = self.fields.iter().map(Field::mirror_type_tokens);
+                quote!( #( #vis #types ),* )
             }
             FieldKind::Named => {
-                let names = self.fields.iter().map(Field::field_tokens);
-                let types = self.fields.iter().map(|f| &f.ty);
-                quote!( #( #names:  <#types as ::keypath::Keyable>::Mirror ),* )
+                let fields = self.iter_keyable().collect::<Vec<_>>();
+                let vis = fields.iter().map(|f| &f.vis);
+                let names = fields.iter().map(|f| f.mirror_field_tokens());
+                let types = fields.iter().map(|f| f.mirror_type_tokens());
+                quote!( #( #vis #names: #types ),* )
             }
         }
     }
 
-    pub fn generate_mirror_inits(&self, generics: &[Ident]) -> TokenStream {
-        let inits = self
-            .fields
-            .iter()
-            .map(|field| field.init_mirror_tokens(generics));
+    pub fn generate_mirror_inits(&self) -> TokenStream {
         match self.kind {
             _ if self.fields.is_empty() => TokenStream::new(),
-            FieldKind::Unnamed => quote!( #( #inits ),* ),
+            FieldKind::Unnamed => {
+                let inits = self.fields.iter().map(Field::init_mirror_tokens);
+                quote!( #( #inits ),* )
+            }
             FieldKind::Named => {
-                let names = self.fields.iter().map(Field::field_tokens);
+                let names = self.iter_keyable().map(Field::mirror_field_tokens);
+                let inits = self.iter_keyable().map(Field::init_mirror_tokens);
                 quote!( #( #names: #inits ),* )
             }
         }
     }
+
+    /// The fields of the typed accessor struct, given the name of the type
+    /// param for the root of its keypaths.
+    ///
+    /// The last field is the accessor's own keypath, which is private.
+    /// Skipped and flattened fields have no accessors; in tuple structs, they
+    /// are replaced by a placeholder so that other fields keep their positions.
+    pub fn generate_accessor_decls(&self, root: &Ident, value: &TokenStream) -> TokenStream {
+        let path = quote!(__keypath::KeyPath<#root, #value>);
+        match self.kind {
+            FieldKind::Unnamed => {
+                let vis = self.fields.iter().map(|f| &f.vis);
+                let types = self.fields.iter().map(|f| f.accessor_type_tokens(root));
+                quote!( #( #vis #types, )* #path )
+            }
+            FieldKind::Named => {
+                let fields = self.iter_accessors().collect::<Vec<_>>();
+                let vis = fields.iter().map(|f| &f.vis);
+                let names = fields.iter().map(|f| f.mirror_field_tokens());
+                let types = fields.iter().map(|f| f.accessor_type_tokens(root));
+                quote!( #( #vis #names: #types, )* __path: #path )
+            }
+        }
+    }
+
+    /// The expression that creates the typed accessor struct `ident`, given
+    /// `path`, the keypath to the value it describes.
+    pub fn generate_accessor_init(&self, ident: &Ident) -> TokenStream {
+        match self.kind {
+            FieldKind::Unnamed => {
+                let inits = self.fields.iter().map(Field::init_accessor_tokens);
+                quote!(#ident( #( #inits, )* path ))
+            }
+            FieldKind::Named => {
+                let names = self.iter_accessors().map(Field::mirror_field_tokens);
+                let inits = self.iter_accessors().map(Field::init_accessor_tokens);
+                quote!(#ident { #( #names: #inits, )* __path: path })
+            }
+        }
+    }
+
+    /// The fields that have accessors.
+    fn iter_accessors(&self) -> impl Iterator<Item = &Field> {
+        self.fields
+            .iter()
+            .filter(|f| !f.attrs.skip && !f.attrs.flatten)
+    }
+
+    /// The member of the typed accessor that holds its own keypath.
+    pub fn accessor_path_member(&self) -> TokenStream {
+        match self.kind {
+            FieldKind::Unnamed => {
+                let idx = Literal::usize_unsuffixed(self.fields.len());
+                quote!(#idx)
+            }
+            FieldKind::Named => quote!(__path),
+        }
+    }
+
+    /// If a field is flattened, the `Deref` impl for the mirror, which makes
+    /// the flattened field's mirror available as if it were part of this one.
+    pub fn generate_mirror_deref(
+        &self,
+        mirror_ident: &Ident,
+        generics: &DeriveGenerics,
+    ) -> Option<TokenStream> {
+        let field = self.fields.iter().find(|f| f.attrs.flatten)?;
+        let ty = &field.ty;
+        let member = field.mirror_field_tokens();
+        let impl_params = generics.impl_params();
+        let mirror_args = generics.root_args();
+        let where_clause = generics.where_clause();
+        Some(quote!(
+            impl<#impl_params> ::std::ops::Deref for #mirror_ident #mirror_args #where_clause {
+                type Target = <#ty as __keypath::Keyable>::Mirror;
+
+                fn deref(&self) -> &Self::Target {
+                    self.#member.inner()
+                }
+            }
+        ))
+    }
+}
+
+impl Variant {
+    /// Variant fields have no visibility of their own; they are visible
+    /// wherever the enum is, so we use the enum's visibility, `vis`.
+    pub fn parse_ast(variant: &syn::Variant, vis: &syn::Visibility) -> Result<Self, Error> {
+        let mut fields = Fields::parse_ast(&variant.fields)?;
+        for field in &mut fields.fields {
+            field.vis = vis.clone();
+        }
+        Ok(Variant {
+            ident: variant.ident.clone(),
+            fields,
+        })
+    }
+
+    /// The head of a match arm that matches this variant, as the first item of
+    /// `ident.split_first()`.
+    pub fn match_arm_head(&self) -> TokenStream {
+        let name = self.ident.to_string();
+        quote!(Some((__keypath::internals::PathComponent::Variant(name), rest)) if name == #name)
+    }
 }
 
 impl Field {
     pub fn parse_ast(field: &syn::Field, index: usize) -> Result<Self, Error> {
-        let ident = match field.ident.as_ref() {
-            Some(ident) => FieldIdent::Named(ident.to_string().trim_start_matches("r#").to_owned()),
-            None => FieldIdent::Unnamed(index),
+        let attrs = Attrs::parse_ast(&field.attrs)?;
+        let ident = match (field.ident.as_ref(), &attrs.rename) {
+            (Some(_), Some(rename)) => FieldIdent::Named(rename.clone()),
+            (Some(ident), None) => {
+                FieldIdent::Named(ident.to_string().trim_start_matches("r#").to_owned())
+            }
+            (None, None) => FieldIdent::Unnamed(index),
+            (None, Some(_)) => {
+                return Err(Error::new(
+                    field.ty.span(),
+                    "`rename` can only be used on named fields",
+                ))
+            }
+        };
+
+        let member = match field.ident.as_ref() {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
         };
 
         let ty = field.ty.clone();
@@ -92,56 +313,328 @@ impl Field {
             .map(|id| id.span())
             .unwrap_or_else(Span::call_site);
 
-        Ok(Field { ident, ty, span })
+        let vis = field.vis.clone();
+        Ok(Field {
+            ident,
+            member,
+            ty,
+            vis,
+            span,
+            attrs,
+        })
+    }
+
+    /// The type used for this field in the mirror and in traversal; opaque
+    /// fields are wrapped so that their type need not be `Keyable`.
+    fn keyable_type(&self) -> TokenStream {
+        let ty = &self.ty;
+        match self.attrs.opaque {
+            true => quote!(__keypath::internals::Opaque<#ty>),
+            false => quote!(#ty),
+        }
+    }
+
+    /// The type of this field in the mirror.
+    fn mirror_type_tokens(&self) -> TokenStream {
+        let ty = self.keyable_type();
+        if self.attrs.skip {
+            quote!(__keypath::internals::Skipped)
+        } else if self.attrs.flatten {
+            quote!(__keypath::internals::Flattened<<#ty as __keypath::Keyable>::Mirror>)
+        } else {
+            quote!(<#ty as __keypath::Keyable>::Mirror)
+        }
     }
 
-    fn init_mirror_tokens(&self, generics: &[Ident]) -> TokenStream {
+    fn init_mirror_tokens(&self) -> TokenStream {
         let span = self.span;
-        let typ = &self.ty;
-        if includes_generic_type(typ, generics) {
-            quote_spanned!(span=> <#typ as ::keypath::Keyable>::mirror() )
+        let typ = self.keyable_type();
+        if self.attrs.skip {
+            quote_spanned!(span=> __keypath::internals::Skipped)
+        } else if self.attrs.flatten {
+            quote_spanned!(span=>
+                __keypath::internals::Flattened::new(<#typ as __keypath::Keyable>::MIRROR)
+            )
         } else {
-            quote_spanned!(span=> <#typ as ::keypath::Keyable>::Mirror::new() )
+            quote_spanned!(span=> <#typ as __keypath::Keyable>::MIRROR )
+        }
+    }
+
+    /// The type of this field in the typed accessor struct: the accessors of
+    /// its type, or just its keypath if the field is opaque.
+    fn accessor_type_tokens(&self, root: &Ident) -> TokenStream {
+        let ty = &self.ty;
+        if self.attrs.skip || self.attrs.flatten {
+            quote!(__keypath::internals::Skipped)
+        } else if self.attrs.opaque {
+            quote!(__keypath::KeyPath<#root, #ty>)
+        } else {
+            quote!(<#ty as __keypath::Keyable>::KeyPaths<#root>)
+        }
+    }
+
+    /// The expression that creates this field's accessors, given `path`, the
+    /// keypath to the containing value.
+    fn init_accessor_tokens(&self) -> TokenStream {
+        let ty = &self.ty;
+        if self.attrs.skip || self.attrs.flatten {
+            return quote!(__keypath::internals::Skipped);
+        }
+        let component = self.ident.path_component_tokens();
+        // SAFETY: the value type is the field's type, with the lifetimes of `Self`
+        let field_path = quote!(path.append(&unsafe {
+            __keypath::KeyPath::<Self, #ty>::__conjure_from_abyss(
+                |_| ::std::marker::PhantomData,
+                {
+                    const FIELDS: &[__keypath::internals::PathComponent] = &[#component];
+                    FIELDS
+                },
+            )
+        }));
+        match self.attrs.opaque {
+            true => field_path,
+            false => quote!(<#ty as __keypath::Keyable>::keypaths_from(#field_path)),
         }
     }
 
-    fn field_tokens(&self) -> TokenTree {
+    /// The name of the field in the mirror, which is the name used in keypaths.
+    fn mirror_field_tokens(&self) -> TokenTree {
         match self.ident {
-            FieldIdent::Named(ref s) => Ident::new(&s, self.span).into(),
+            FieldIdent::Named(ref s) => Ident::new(s, self.span).into(),
             FieldIdent::Unnamed(num) => Literal::usize_unsuffixed(num).into(),
         }
     }
 
-    pub fn match_arms(&self, method_tokens: TokenStream) -> TokenStream {
-        let field = self.field_tokens();
-        let variant = self.ident.path_component_tokens();
-        quote!(Some((#variant, rest)) => self.#field.#method_tokens(rest),)
+    /// The head of a match arm that matches this field.
+    ///
+    /// A flattened field matches any component that its type has; it is
+    /// then given the whole path, rather than the `rest` after its head.
+    fn match_arm_head(&self) -> TokenStream {
+        match self.attrs.flatten {
+            true => {
+                let ty = &self.ty;
+                quote!(Some((field, _)) if <#ty as __keypath::Keyable>::type_at_path(
+                    ::std::slice::from_ref(field)
+                ).is_ok())
+            }
+            false => self.ident.match_arm_head(),
+        }
+    }
+
+    /// The path that remains after this field's match arm head: `path` if the
+    /// field is flattened, otherwise the `rest` bound by the head.
+    fn remaining_path(&self, path: TokenStream) -> TokenStream {
+        match self.attrs.flatten {
+            true => path,
+            false => quote!(rest),
+        }
+    }
+
+    /// The expression that traverses the `rest` of the path from the field,
+    /// given a reference to it.
+    fn traverse_tokens(&self, value: TokenStream, rest: TokenStream, mutable: bool) -> TokenStream {
+        let (method, wrap) = match mutable {
+            true => (quote!(get_field_mut), quote!(from_mut)),
+            false => (quote!(get_field), quote!(from_ref)),
+        };
+        match self.attrs.opaque {
+            true => quote!(__keypath::internals::RawKeyable::#method(
+                __keypath::internals::Opaque::#wrap(#value),
+                #rest,
+            )),
+            false => quote!((#value).#method(#rest)),
+        }
+    }
+
+    /// The match arm for `RawKeyable::get_field`, or `get_field_mut` if
+    /// `mutable` is true.
+    pub fn match_arms(&self, mutable: bool) -> TokenStream {
+        let member = &self.member;
+        let head = self.match_arm_head();
+        let value = match mutable {
+            true => quote!(&mut self.#member),
+            false => quote!(&self.#member),
+        };
+        let traverse = self.traverse_tokens(value, self.remaining_path(quote!(ident)), mutable);
+        quote!(#head => #traverse,)
+    }
+
+    /// The match arm for `Keyable::type_at_path`, which checks the rest of the
+    /// path against the field's type.
+    ///
+    /// `path` is the path being matched, including the field's component.
+    pub fn type_match_arms(&self, path: TokenStream) -> TokenStream {
+        let ty = self.keyable_type();
+        let head = self.match_arm_head();
+        let rest = self.remaining_path(path);
+        quote_spanned!(self.span=> #head => <#ty as __keypath::Keyable>::type_at_path(#rest),)
+    }
+
+    /// Match arms for a field of an enum variant.
+    ///
+    /// The field is only bound if `self` is the expected variant; otherwise
+    /// we return the provided `mismatch` error.
+    pub fn variant_match_arms(
+        &self,
+        variant: &Ident,
+        mutable: bool,
+        mismatch: &TokenStream,
+    ) -> TokenStream {
+        let member = &self.member;
+        let head = self.match_arm_head();
+        let traverse =
+            self.traverse_tokens(quote!(value), self.remaining_path(quote!(rest)), mutable);
+        quote!(#head => match self {
+            Self::#variant { #member: value, .. } => #traverse,
+            _ => #mismatch,
+        },)
     }
 }
 
-/// check if a struct field's type includes one of the generic paramaters
-/// declared by that struct.
-///
-/// If it does, we can't generate const code.
-fn includes_generic_type(ty: &syn::Type, generics: &[Ident]) -> bool {
-    let path = match ty {
-        syn::Type::Path(syn::TypePath { path, .. }) => path,
-        _ => return false,
-    };
+impl Attrs {
+    /// Parse the `#[keypath(...)]` attributes on a field.
+    pub fn parse_ast(attrs: &[syn::Attribute]) -> Result<Self, Error> {
+        use syn::{Meta, NestedMeta};
 
-    let last_seg = path.segments.last().unwrap();
-    if generics.contains(&last_seg.ident) {
-        return true;
+        let mut result = Attrs::default();
+        for_each_option(attrs, |nested| {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => result.skip = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("opaque") => {
+                    result.opaque = true
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+                    result.flatten = true
+                }
+                NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("rename") => {
+                    let name = match &meta.lit {
+                        syn::Lit::Str(name) => name,
+                        other => {
+                            return Err(Error::new(
+                                other.span(),
+                                "`rename` expects a string, such as `rename = \"name\"`",
+                            ))
+                        }
+                    };
+                    if syn::parse_str::<Ident>(&name.value()).is_err() {
+                        return Err(Error::new(
+                            name.span(),
+                            "`rename` must be a valid identifier",
+                        ));
+                    }
+                    result.rename = Some(name.value());
+                }
+                other => return Err(Error::new(
+                    other.span(),
+                    "unknown keypath attribute; expected `skip`, `rename`, `opaque` or `flatten`",
+                )),
+            }
+            Ok(())
+        })?;
+
+        // `skip` and `flatten` replace the field's usual handling entirely
+        let exclusive = match (result.skip, result.flatten) {
+            (true, _) => Some("skip"),
+            (false, true) => Some("flatten"),
+            (false, false) => None,
+        };
+        let combined = result.opaque || result.rename.is_some() || (result.skip && result.flatten);
+        if let (Some(name), true) = (exclusive, combined) {
+            let attr = attrs.iter().find(|attr| attr.path.is_ident("keypath"));
+            return Err(Error::new_spanned(
+                attr,
+                format!(
+                    "`{}` cannot be combined with other keypath attributes",
+                    name
+                ),
+            ));
+        }
+        Ok(result)
     }
+}
 
-    match &last_seg.arguments {
-        syn::PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| {
-            if let syn::GenericArgument::Type(ty) = arg {
-                includes_generic_type(ty, generics)
-            } else {
-                false
+impl ContainerAttrs {
+    /// Parse the `#[keypath(...)]` attributes on a struct or enum.
+    pub fn parse_ast(attrs: &[syn::Attribute]) -> Result<Self, Error> {
+        use syn::{Meta, NestedMeta};
+
+        let mut result = ContainerAttrs::default();
+        for_each_option(attrs, |nested| {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("transparent") => {
+                    if result.collection.is_some() {
+                        return Err(collection_conflict(path));
+                    }
+                    result.transparent = true
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                    result.default = true
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("sequence") => {
+                    result.set_collection(Collection::Sequence, path)?
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("map") => {
+                    result.set_collection(Collection::Map, path)?
+                }
+                NestedMeta::Meta(Meta::NameValue(meta)) if meta.path.is_ident("crate") => {
+                    let path =
+                        match &meta.lit {
+                            syn::Lit::Str(path) => path,
+                            other => return Err(Error::new(
+                                other.span(),
+                                "`crate` expects a string, such as `crate = \"path::to::keypath\"`",
+                            )),
+                        };
+                    let parsed = path.parse().map_err(|_| {
+                        Error::new(path.span(), "`crate` must be a path, such as `::keypath`")
+                    })?;
+                    result.crate_path = Some(parsed);
+                }
+                other => {
+                    return Err(Error::new(
+                        other.span(),
+                        "unknown keypath attribute; expected `transparent`, `sequence`, `map`, `default` or `crate`",
+                    ))
+                }
             }
-        }),
-        _ => false,
+            Ok(())
+        })?;
+        Ok(result)
+    }
+
+    fn set_collection(&mut self, collection: Collection, path: &syn::Path) -> Result<(), Error> {
+        if self.collection.is_some() || self.transparent {
+            return Err(collection_conflict(path));
+        }
+        self.collection = Some(collection);
+        Ok(())
+    }
+}
+
+/// The error for an option that conflicts with an earlier `sequence`, `map`
+/// or `transparent`.
+fn collection_conflict(path: &syn::Path) -> Error {
+    Error::new(
+        path.span(),
+        "`sequence`, `map` and `transparent` cannot be combined",
+    )
+}
+
+/// Call `f` with each of the options in the `#[keypath(...)]` attributes.
+fn for_each_option(
+    attrs: &[syn::Attribute],
+    mut f: impl FnMut(&syn::NestedMeta) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("keypath")) {
+        match attr.parse_meta()? {
+            syn::Meta::List(list) => list.nested.iter().try_for_each(&mut f)?,
+            other => {
+                return Err(Error::new(
+                    other.span(),
+                    "expected a list of options, such as `#[keypath(skip)]`",
+                ))
+            }
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,217 @@
+//! The generics of derived impls.
+
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::visit::{self, Visit};
+use syn::visit_mut::VisitMut;
+
+use crate::attr::Field;
+
+/// The generic parameters of a derived type, as used by the derived impls and
+/// by its mirror.
+///
+/// Type parameters used by fields must be `Keyable`, as must any associated
+/// types used by fields, such as `T::Item`. Lifetime parameters are kept, so
+/// a type that borrows its data is keyable for any lifetime.
+#[derive(Clone)]
+pub struct DeriveGenerics {
+    /// The params, with their bounds, and the where clause.
+    generics: syn::Generics,
+    /// Params that are not mentioned by any (unskipped) field.
+    unused: Vec<syn::GenericParam>,
+}
+
+impl DeriveGenerics {
+    /// `fields` are all the fields of the type.
+    pub fn new<'a>(input: &syn::Generics, fields: impl IntoIterator<Item = &'a Field>) -> Self {
+        let type_params = input
+            .type_params()
+            .map(|param| param.ident.clone())
+            .collect::<Vec<_>>();
+
+        let mut keyable = ParamUsage::new(&type_params);
+        let mut mentioned = ParamUsage::new(&type_params);
+        for field in fields.into_iter().filter(|f| !f.attrs.skip) {
+            mentioned.visit_type(&field.ty);
+            if !field.attrs.opaque {
+                keyable.visit_type(&field.ty);
+            }
+        }
+
+        let mut generics = input.clone();
+        for param in generics.params.iter_mut() {
+            match param {
+                syn::GenericParam::Type(ty) => {
+                    ty.eq_token = None;
+                    ty.default = None;
+                    if keyable.used.contains(&ty.ident) {
+                        ty.bounds.push(syn::parse_quote!(__keypath::Keyable));
+                    }
+                }
+                syn::GenericParam::Const(cst) => {
+                    cst.eq_token = None;
+                    cst.default = None;
+                }
+                syn::GenericParam::Lifetime(_) => (),
+            }
+        }
+
+        let where_clause = generics.make_where_clause();
+        for projection in &keyable.projections {
+            where_clause
+                .predicates
+                .push(syn::parse_quote!(#projection: __keypath::Keyable));
+        }
+
+        let unused = generics
+            .params
+            .iter()
+            .filter(|param| match param {
+                syn::GenericParam::Type(ty) => !mentioned.mentioned.contains(&ty.ident),
+                syn::GenericParam::Lifetime(def) => !mentioned.lifetimes.contains(&def.lifetime),
+                syn::GenericParam::Const(_) => false,
+            })
+            .cloned()
+            .collect();
+
+        DeriveGenerics { generics, unused }
+    }
+
+    /// Visit the types in the bounds and where clause, such as to rewrite them.
+    pub fn visit(&mut self, visitor: &mut impl VisitMut) {
+        visitor.visit_generics_mut(&mut self.generics);
+    }
+
+    /// The params of the impls, with their bounds, without the angle brackets.
+    pub fn impl_params(&self) -> TokenStream {
+        let params = self.generics.params.iter();
+        quote!( #( #params, )* )
+    }
+
+    pub fn where_clause(&self) -> Option<&syn::WhereClause> {
+        self.generics.where_clause.as_ref()
+    }
+
+    /// The generic arguments of the derived type, such as `<'a, T>`; the
+    /// mirror has the same arguments.
+    pub fn root_args(&self) -> syn::TypeGenerics<'_> {
+        self.generics.split_for_impl().1
+    }
+
+    /// The generic arguments of the derived type followed by `extra`, such as
+    /// `<'a, T, Extra>`.
+    pub fn root_args_with(&self, extra: &Ident) -> TokenStream {
+        let args = self.generics.params.iter().map(|param| match param {
+            syn::GenericParam::Type(ty) => ty.ident.to_token_stream(),
+            syn::GenericParam::Lifetime(def) => def.lifetime.to_token_stream(),
+            syn::GenericParam::Const(cst) => cst.ident.to_token_stream(),
+        });
+        quote!(< #( #args, )* #extra >)
+    }
+
+    /// The `PhantomData` that a mirror needs in order to use all of the
+    /// lifetime and type params, if it needs one.
+    ///
+    /// If `all` is true, this always uses every param; otherwise it only
+    /// includes those that are not used by any field.
+    pub fn phantom(&self, all: bool) -> Option<TokenStream> {
+        let params = match all {
+            true => self.generics.params.iter().collect::<Vec<_>>(),
+            false => self.unused.iter().collect(),
+        };
+        let params = params
+            .into_iter()
+            .filter_map(|param| match param {
+                syn::GenericParam::Type(ty) => Some(ty.ident.to_token_stream()),
+                syn::GenericParam::Lifetime(def) => {
+                    let lifetime = &def.lifetime;
+                    Some(quote!(&#lifetime ()))
+                }
+                syn::GenericParam::Const(_) => None,
+            })
+            .collect::<Vec<_>>();
+        match params.as_slice() {
+            [] => None,
+            params => Some(quote!(::std::marker::PhantomData<(#( #params, )*)>)),
+        }
+    }
+}
+
+/// Finds the type params used by a type, and the associated types of those
+/// params, such as `T::Item` or `<T as Trait>::Output`.
+struct ParamUsage<'a> {
+    params: &'a [Ident],
+    /// Params used other than in associated types.
+    used: Vec<Ident>,
+    /// Params used anywhere.
+    mentioned: Vec<Ident>,
+    /// Lifetimes used anywhere.
+    lifetimes: Vec<syn::Lifetime>,
+    projections: Vec<syn::Type>,
+}
+
+impl<'a> ParamUsage<'a> {
+    fn new(params: &'a [Ident]) -> Self {
+        ParamUsage {
+            params,
+            used: Vec::new(),
+            mentioned: Vec::new(),
+            lifetimes: Vec::new(),
+            projections: Vec::new(),
+        }
+    }
+
+    fn is_projection(&self, ty: &syn::TypePath) -> bool {
+        match ty.path.segments.first() {
+            _ if ty.qself.is_some() => true,
+            Some(first) if ty.path.leading_colon.is_none() && ty.path.segments.len() > 1 => {
+                self.params.contains(&first.ident)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn push_unique<T: PartialEq>(items: &mut Vec<T>, item: T) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+impl<'ast> Visit<'ast> for ParamUsage<'_> {
+    fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+        if !self.is_projection(ty) {
+            return visit::visit_type_path(self, ty);
+        }
+        let mut inner = ParamUsage::new(self.params);
+        visit::visit_type_path(&mut inner, ty);
+        let tokens = ty.to_token_stream().to_string();
+        let seen = self
+            .projections
+            .iter()
+            .any(|proj| proj.to_token_stream().to_string() == tokens);
+        if !inner.mentioned.is_empty() && !seen {
+            self.projections.push(syn::Type::Path(ty.clone()));
+        }
+        for param in inner.mentioned {
+            push_unique(&mut self.mentioned, param);
+        }
+        for lifetime in inner.lifetimes {
+            push_unique(&mut self.lifetimes, lifetime);
+        }
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &'ast syn::Lifetime) {
+        push_unique(&mut self.lifetimes, lifetime.clone());
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if let (None, Some(first)) = (path.leading_colon, path.segments.first()) {
+            if self.params.contains(&first.ident) {
+                push_unique(&mut self.used, first.ident.clone());
+                push_unique(&mut self.mentioned, first.ident.clone());
+            }
+        }
+        visit::visit_path(self, path);
+    }
+}
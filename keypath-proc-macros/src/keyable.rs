@@ -1,196 +1,833 @@
 //! The implementation for #[derive(Keyable)]
 
-use crate::attr::{FieldKind, Fields};
+use crate::attr::{Collection, ContainerAttrs, FieldKind, Fields, Variant};
+use crate::generics::DeriveGenerics;
+use crate::shared;
 
-use proc_macro2::Ident;
-use quote::{quote, quote_spanned};
-use syn::{spanned::Spanned, Data, DataStruct};
-
-static DERIVED_MIRROR_STRUCT_PREFIX: &str = "KeyableDerivedMirrorOf_";
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::visit_mut::{self, VisitMut};
+use syn::{spanned::Spanned, Data, DataEnum, DataStruct};
 
 pub(crate) fn derive_keyable_impl(
     input: syn::DeriveInput,
 ) -> Result<proc_macro2::TokenStream, syn::Error> {
-    match &input.data {
-        Data::Struct(s) => derive_struct(&input, s),
-        Data::Enum(e) => Err(syn::Error::new(
-            e.enum_token.span(),
-            "Keyable cannot currently be derived for enums",
-        )),
-        Data::Union(u) => Err(syn::Error::new(
+    let attrs = ContainerAttrs::parse_ast(&input.attrs)?;
+    let derived = match (&input.data, attrs.collection) {
+        (Data::Union(u), _) => Err(syn::Error::new(
             u.union_token.span(),
             "Data implementations cannot be derived from unions",
         )),
-    }
+        (_, Some(collection)) => Ok(derive_collection(&input, collection, &attrs)),
+        (Data::Struct(s), None) => derive_struct(&input, s, &attrs),
+        (Data::Enum(_), None) if attrs.transparent => Err(syn::Error::new(
+            input.ident.span(),
+            "`transparent` can only be used on structs",
+        )),
+        (Data::Enum(e), None) => derive_enum(&input, e, &attrs),
+    }?;
+
+    // everything is in its own scope, so that it can import the crate, and
+    // the aliases used by the mirror
+    let crate_import = shared::crate_import(attrs.crate_path.as_ref());
+    Ok(quote! {
+        #[allow(unused_imports, non_camel_case_types)]
+        const _: () = {
+            #crate_import
+
+            #derived
+        };
+    })
 }
 
 fn derive_struct(
     input: &syn::DeriveInput,
     s: &DataStruct,
+    attrs: &ContainerAttrs,
 ) -> Result<proc_macro2::TokenStream, syn::Error> {
     let ident = &input.ident;
-    let impl_generics = add_generic_bounds(&input.generics, quote!(::keypath::Keyable));
-    let (_, ty_generics, where_clause) = &input.generics.split_for_impl();
+    let mut fields = Fields::parse_ast(&s.fields)?;
+    if attrs.transparent {
+        fields.make_transparent(ident.span())?;
+    }
+
+    let generics = DeriveGenerics::new(&input_generics(input, attrs), fields.iter_keyable());
+    let impl_params = generics.impl_params();
+    let root_args = generics.root_args();
+    let where_clause = generics.where_clause();
+    let get_field_arms = fields.iter_keyable().map(|fld| fld.match_arms(false));
+    let get_mut_field_arms = fields.iter_keyable().map(|fld| fld.match_arms(true));
+
+    let type_arms = fields
+        .iter_keyable()
+        .map(|fld| fld.type_match_arms(quote!(path)));
+    let type_at_path_fn = type_at_path_fn(quote!(#( #type_arms )*));
+
+    let mut scope = MirrorScope::new(ident, &[], &generics);
+    let mut mirror_fields = fields.clone();
+    mirror_fields.visit_types(&mut scope);
+    let mut mirror_generics = generics.clone();
+    mirror_generics.visit(&mut scope);
+    let mirror_decl = scope.wrap(mirror_struct(
+        ident,
+        &input.vis,
+        &mirror_generics,
+        &mirror_fields,
+    ));
+    let mirror_trait_items = mirror_trait_items(attrs);
+    let raw_keyable_impl = raw_keyable_impl(
+        input,
+        &generics,
+        quote!(#( #get_field_arms )*),
+        quote!(#( #get_mut_field_arms )*),
+    );
+    let index_impls = index_impls(input, &generics);
+
+    // the accessor's field types may refer to the type, so it is declared
+    // alongside the mirror's aliases, and uses the mirror's rewritten fields
+    let accessor_ident = Ident::new(&format!("{}KeyPaths", ident), ident.span());
+    let accessor_decl = accessor_struct(
+        &accessor_ident,
+        &input.vis,
+        &mirror_generics,
+        &mirror_fields,
+    );
+    let keypaths_items = accessor_trait_items(&accessor_ident, &generics, &fields);
+
+    Ok(quote! {
+        #raw_keyable_impl
+
+        #mirror_decl
+
+        #accessor_decl
+
+        impl<#impl_params> __keypath::Keyable for #ident #root_args #where_clause {
+            #mirror_trait_items
+
+            #keypaths_items
+
+            #type_at_path_fn
+        }
+
+        #index_impls
+    })
+}
+
+fn derive_enum(
+    input: &syn::DeriveInput,
+    e: &DataEnum,
+    attrs: &ContainerAttrs,
+) -> Result<TokenStream, syn::Error> {
+    let ident = &input.ident;
+    let variants = e
+        .variants
+        .iter()
+        .map(|v| Variant::parse_ast(v, &input.vis))
+        .collect::<Result<Vec<_>, _>>()?;
+    let generics = DeriveGenerics::new(
+        &input_generics(input, attrs),
+        variants.iter().flat_map(|v| v.fields.iter_keyable()),
+    );
+    let impl_params = generics.impl_params();
+    let root_args = generics.root_args();
+    let where_clause = generics.where_clause();
+
+    let index_impls = index_impls(input, &generics);
+    let default_value_fn = default_value_fn(attrs.default);
+    // paths into enums are fallible, so they have no typed accessors
+    let keypaths_items = leaf_accessor_trait_items();
+
+    // an enum without any fields has nothing to traverse, so it is a leaf.
+    if variants.iter().all(|v| v.fields.is_empty()) {
+        let raw_keyable_impl =
+            raw_keyable_impl(input, &generics, TokenStream::new(), TokenStream::new());
+        return Ok(quote! {
+            #raw_keyable_impl
+
+            impl<#impl_params> __keypath::Keyable for #ident #root_args #where_clause {
+                type Mirror = __keypath::internals::Leaf<Self>;
+                const MIRROR: Self::Mirror = __keypath::internals::Leaf::new();
+
+                #default_value_fn
+
+                #keypaths_items
+            }
+
+            #index_impls
+        });
+    }
+
+    let get_field_arms = variants
+        .iter()
+        .map(|v| variant_match_arm(v, &variants, false));
+    let get_mut_field_arms = variants
+        .iter()
+        .map(|v| variant_match_arm(v, &variants, true));
+    let raw_keyable_impl = raw_keyable_impl(
+        input,
+        &generics,
+        quote!(#( #get_field_arms )*),
+        quote!(#( #get_mut_field_arms )*),
+    );
 
-    let fields = Fields::parse_ast(&s.fields)?;
-    let get_field_arms = fields.iter().map(|fld| fld.match_arms(quote!(get_field)));
-    let get_mut_field_arms = fields
+    let type_arms = variants.iter().map(variant_type_match_arm);
+    let type_at_path_fn = type_at_path_fn(quote!(#( #type_arms )*));
+
+    let variant_mirrors = variants
         .iter()
-        .map(|fld| fld.match_arms(quote!(get_field_mut)));
+        .filter(|v| !v.fields.is_empty())
+        .map(|v| variant_mirror_ident(ident, &v.ident))
+        .collect::<Vec<_>>();
+    let mut scope = MirrorScope::new(ident, &variant_mirrors, &generics);
+    let mut mirror_variants = variants.clone();
+    for variant in &mut mirror_variants {
+        variant.fields.visit_types(&mut scope);
+    }
+    let mut mirror_generics = generics.clone();
+    mirror_generics.visit(&mut scope);
+    let mirror_decl = scope.wrap(mirror_enum(
+        ident,
+        &input.vis,
+        &mirror_generics,
+        &mirror_variants,
+    ));
+    let mirror_trait_items = mirror_trait_items(attrs);
+
+    Ok(quote! {
+        #raw_keyable_impl
+
+        #mirror_decl
 
-    let (fragment_decl, typed_trait_decl) =
-        mirror_struct(ident, &input.vis, &input.generics, &fields)?;
-    let res = quote! {
-        impl<#impl_generics> ::keypath::internals::RawKeyable for #ident #ty_generics #where_clause {
-            fn as_any(&self) -> &dyn ::std::any::Any {
+        impl<#impl_params> __keypath::Keyable for #ident #root_args #where_clause {
+            #mirror_trait_items
+
+            #keypaths_items
+
+            #type_at_path_fn
+        }
+
+        #index_impls
+    })
+}
+
+/// Derive `Keyable` for a `#[keypath(sequence)]` or `#[keypath(map)]` type,
+/// which is indexed through its `KeyableSequence` or `KeyableMap` impl; its
+/// fields are not part of any keypath.
+fn derive_collection(
+    input: &syn::DeriveInput,
+    collection: Collection,
+    attrs: &ContainerAttrs,
+) -> TokenStream {
+    let ident = &input.ident;
+    let (collection_trait, mirror, prefix) = match collection {
+        Collection::Sequence => (
+            quote!(__keypath::KeyableSequence),
+            quote!(__keypath::internals::SequenceMirror),
+            "sequence",
+        ),
+        Collection::Map => (
+            quote!(__keypath::KeyableMap),
+            quote!(__keypath::internals::MapMirror),
+            "map",
+        ),
+    };
+    let glue = |name: &str| {
+        let name = Ident::new(&format!("{}_{}", prefix, name), Span::call_site());
+        quote!(__keypath::internals::#name)
+    };
+    let (get_field, get_field_mut) = (glue("get_field"), glue("get_field_mut"));
+    let (for_each_item, for_each_item_mut) = (glue("for_each_item"), glue("for_each_item_mut"));
+    let type_at_path = glue("type_at_path");
+
+    // the collection trait's impl has whatever bounds the type needs
+    let mut input_generics = input_generics(input, attrs);
+    input_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(Self: #collection_trait));
+    let generics = DeriveGenerics::new(&input_generics, None);
+    let impl_params = generics.impl_params();
+    let root_args = generics.root_args();
+    let where_clause = generics.where_clause();
+
+    let index_impls = index_impls(input, &generics);
+    let default_value_fn = default_value_fn(attrs.default);
+    let keypaths_items = leaf_accessor_trait_items();
+
+    // SAFETY: the items are returned by their own impls, and have the types
+    // that the mirror gives them
+    quote! {
+        unsafe impl<#impl_params> __keypath::internals::RawKeyable for #ident #root_args #where_clause {
+            fn as_any(&self) -> &dyn ::std::any::Any where Self: 'static {
                 self
             }
 
-            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any {
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any where Self: 'static {
                 self
             }
 
-            fn get_field(&self, ident: &[::keypath::internals::PathComponent]) -> Result<&dyn ::keypath::internals::RawKeyable, ::keypath::FieldError> {
+            fn get_field(&self, ident: &[__keypath::internals::PathComponent]) -> Result<&dyn __keypath::internals::RawKeyable, __keypath::FieldError> {
+                #get_field(self, ident)
+            }
+
+            fn get_field_mut(&mut self, ident: &[__keypath::internals::PathComponent]) -> Result<&mut dyn __keypath::internals::RawKeyable, __keypath::FieldError> {
+                #get_field_mut(self, ident)
+            }
+
+            fn for_each_item<'__keypath>(
+                &'__keypath self,
+                f: &mut dyn FnMut(__keypath::internals::PathComponent, &'__keypath dyn __keypath::internals::RawKeyable),
+            ) {
+                #for_each_item(self, f)
+            }
+
+            fn for_each_item_mut<'__keypath>(
+                &'__keypath mut self,
+                f: &mut dyn FnMut(__keypath::internals::PathComponent, &'__keypath mut dyn __keypath::internals::RawKeyable),
+            ) -> Result<(), __keypath::FieldError> {
+                #for_each_item_mut(self, f)
+            }
+        }
+
+        impl<#impl_params> __keypath::Keyable for #ident #root_args #where_clause {
+            type Mirror = #mirror<Self>;
+            const MIRROR: Self::Mirror = #mirror::new();
+
+            #default_value_fn
+
+            #keypaths_items
+
+            fn type_at_path(path: &[__keypath::internals::PathComponent]) -> Result<__keypath::internals::TypeInfo, __keypath::FieldError> {
+                #type_at_path::<Self>(path)
+            }
+        }
+
+        #index_impls
+    }
+}
+
+/// The match arm for a `PathComponent::Variant` that refers to this variant.
+///
+/// If `self` is a different variant, traversal fails. If `mutable` is true,
+/// this is for `get_field_mut`.
+fn variant_match_arm(variant: &Variant, all: &[Variant], mutable: bool) -> TokenStream {
+    let ident = &variant.ident;
+    let head = variant.match_arm_head();
+    let names = all.iter().map(|v| {
+        let ident = &v.ident;
+        let name = ident.to_string();
+        quote!(Self::#ident { .. } => #name)
+    });
+    let expected = ident.to_string();
+    let mismatch = quote!(Err(__keypath::FieldErrorKind::VariantMismatch {
+        expected: #expected,
+        found: match self { #( #names, )* },
+    }
+    .into_error(self, ident.len() - 1)));
+
+    let field_arms = variant
+        .fields
+        .iter_keyable()
+        .map(|fld| fld.variant_match_arms(ident, mutable, &mismatch));
+
+    quote!(#head => match rest.split_first() {
+        None if matches!(self, Self::#ident { .. }) => Ok(self),
+        None => #mismatch,
+        #( #field_arms )*
+        Some((field, rest)) => Err(
+            __keypath::FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len())
+        ),
+    },)
+}
+
+/// The match arm for `Keyable::type_at_path` for this variant.
+fn variant_type_match_arm(variant: &Variant) -> TokenStream {
+    let head = variant.match_arm_head();
+    let field_arms = variant
+        .fields
+        .iter_keyable()
+        .map(|fld| fld.type_match_arms(quote!(rest)));
+
+    quote!(#head => match rest.split_first() {
+        None => Ok(__keypath::internals::TypeInfo::of::<Self>()),
+        #( #field_arms )*
+        Some((field, rest)) => Err(
+            __keypath::FieldErrorKind::InvalidField(field.clone()).into_error_for::<Self>(rest.len())
+        ),
+    },)
+}
+
+/// `Keyable::type_at_path`, given the match arms for the fields.
+fn type_at_path_fn(arms: TokenStream) -> TokenStream {
+    quote! {
+        fn type_at_path(path: &[__keypath::internals::PathComponent]) -> Result<__keypath::internals::TypeInfo, __keypath::FieldError> {
+            match path.split_first() {
+                None => Ok(__keypath::internals::TypeInfo::of::<Self>()),
+                #arms
+                Some((field, rest)) => Err(
+                    __keypath::FieldErrorKind::InvalidField(field.clone()).into_error_for::<Self>(rest.len())
+                ),
+            }
+        }
+    }
+}
+
+/// Generate the `RawKeyable` impl, given the match arms for the fields.
+fn raw_keyable_impl(
+    input: &syn::DeriveInput,
+    generics: &DeriveGenerics,
+    get_field_arms: TokenStream,
+    get_mut_field_arms: TokenStream,
+) -> TokenStream {
+    let ident = &input.ident;
+    let impl_params = generics.impl_params();
+    let root_args = generics.root_args();
+    let where_clause = generics.where_clause();
+
+    // SAFETY: each field is returned by its own impl, and has the type that
+    // the mirror gives it
+    quote! {
+        unsafe impl<#impl_params> __keypath::internals::RawKeyable for #ident #root_args #where_clause {
+            fn as_any(&self) -> &dyn ::std::any::Any where Self: 'static {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any where Self: 'static {
+                self
+            }
+
+            fn get_field(&self, ident: &[__keypath::internals::PathComponent]) -> Result<&dyn __keypath::internals::RawKeyable, __keypath::FieldError> {
                 match ident.split_first() {
                 None => Ok(self),
-                 #( #get_field_arms )*
+                 #get_field_arms
                     Some((field, rest)) => Err(
-                        ::keypath::FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len())
+                        __keypath::FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len())
                     ),
 
                 }
             }
 
-            fn get_field_mut(&mut self, ident: &[::keypath::internals::PathComponent]) -> Result<&mut dyn ::keypath::internals::RawKeyable, ::keypath::FieldError> {
+            fn get_field_mut(&mut self, ident: &[__keypath::internals::PathComponent]) -> Result<&mut dyn __keypath::internals::RawKeyable, __keypath::FieldError> {
                 match ident.split_first() {
                 None => Ok(self),
-                #( #get_mut_field_arms )*
+                #get_mut_field_arms
                     Some((field, rest)) => Err(
-                        ::keypath::FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len())
+                        __keypath::FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len())
                     ),
 
                 }
             }
         }
+    }
+}
 
-        #fragment_decl
+/// The generics of the derived impls: those of the type, which must also be
+/// `Default` if it is marked `#[keypath(default)]`.
+fn input_generics(input: &syn::DeriveInput, attrs: &ContainerAttrs) -> syn::Generics {
+    let mut generics = input.generics.clone();
+    if attrs.default {
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(Self: ::std::default::Default));
+    }
+    generics
+}
 
-        impl<#impl_generics> ::keypath::Keyable for #ident #ty_generics #where_clause {
-            #typed_trait_decl
+/// `Keyable::default_value`, which returns `Some` if the type is `Default`.
+///
+/// Unless the type is marked `#[keypath(default)]`, we can't know this from
+/// the derive input, so we let method resolution figure it out. This only
+/// works for types without type params, since in a generic impl it can't
+/// know whether they will be `Default`.
+fn default_value_fn(explicit: bool) -> TokenStream {
+    if explicit {
+        return quote! {
+            fn default_value() -> Option<Self> {
+                Some(::std::default::Default::default())
+            }
+        };
+    }
+    quote! {
+        fn default_value() -> Option<Self> {
+            #[allow(unused_imports)]
+            use __keypath::internals::{ProbeDefault as _, ProbeNoDefault as _};
+            (&&__keypath::internals::DefaultProbe::<Self>::new()).probe_default()
         }
+    }
+}
 
-        impl <Value: 'static, #impl_generics> std::ops::Index<&::keypath::KeyPath<#ident #ty_generics, Value>> for #ident #ty_generics #where_clause {
+fn index_impls(input: &syn::DeriveInput, generics: &DeriveGenerics) -> TokenStream {
+    let ident = &input.ident;
+    let impl_params = generics.impl_params();
+    let ty_generics = generics.root_args();
+    let where_clause = generics.where_clause();
+
+    quote! {
+        impl <#impl_params Value> std::ops::Index<&__keypath::KeyPath<#ident #ty_generics, Value>> for #ident #ty_generics #where_clause {
             type Output = Value;
-            fn index(&self, index: &::keypath::KeyPath<#ident #ty_generics, Value>) -> &Self::Output {
+            fn index(&self, index: &__keypath::KeyPath<#ident #ty_generics, Value>) -> &Self::Output {
                 self.item_at_path(index)
             }
         }
 
-        impl <Value: 'static, #impl_generics> std::ops::IndexMut<&::keypath::KeyPath<#ident #ty_generics, Value>> for #ident #ty_generics #where_clause {
-            fn index_mut(&mut self, index: &::keypath::KeyPath<#ident #ty_generics, Value>) -> &mut Self::Output {
+        impl <#impl_params Value> std::ops::IndexMut<&__keypath::KeyPath<#ident #ty_generics, Value>> for #ident #ty_generics #where_clause {
+            fn index_mut(&mut self, index: &__keypath::KeyPath<#ident #ty_generics, Value>) -> &mut Self::Output {
                 self.item_at_path_mut(index)
             }
         }
-    };
-    Ok(res)
+    }
 }
 
-fn mirror_struct(
-    base_ident: &Ident,
+/// The scope in which a derived mirror is declared.
+///
+/// Mirrors are declared in an anonymous scope with the same names as the
+/// types they mirror, so that the compiler's errors for invalid fields, and
+/// its suggestions, name the user's type rather than something generated.
+///
+/// Within that scope, the mirror shadows the type itself, and the mirrors of
+/// enum variants shadow any types named after those variants. Field types
+/// that refer to these are rewritten to use aliases declared in an enclosing
+/// scope.
+struct MirrorScope {
+    root: Ident,
+    /// The generic args of the root type, which replace `Self`.
+    root_args: syn::PathArguments,
+    /// The names of variant mirrors.
+    shadowed: Vec<Ident>,
+    /// The shadowed names that field types actually refer to.
+    aliased: Vec<Ident>,
+}
+
+impl MirrorScope {
+    fn new(root: &Ident, shadowed: &[Ident], generics: &DeriveGenerics) -> Self {
+        let root_args = generics.root_args().into_token_stream();
+        let root_args = match root_args.is_empty() {
+            true => syn::PathArguments::None,
+            false => syn::PathArguments::AngleBracketed(syn::parse_quote!(#root_args)),
+        };
+        MirrorScope {
+            root: root.clone(),
+            root_args,
+            shadowed: shadowed.to_vec(),
+            aliased: Vec::new(),
+        }
+    }
+
+    /// Declare the mirror, given the tokens that make it up.
+    ///
+    /// This must be expanded in the scope that the derive wraps its output in,
+    /// rather than at the top level, since it imports the aliases.
+    fn wrap(&self, mirror_decl: TokenStream) -> TokenStream {
+        let root = &self.root;
+        let root_alias = root_alias();
+        let aliases = self.aliased.iter().map(|ident| {
+            let alias = shadowed_alias(ident);
+            quote!(use #ident as #alias;)
+        });
+
+        quote! {
+            use #root as #root_alias;
+            #( #aliases )*
+
+            const _: () = {
+                #mirror_decl
+            };
+        }
+    }
+}
+
+impl VisitMut for MirrorScope {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if let (None, Some(first)) = (path.leading_colon, path.segments.first_mut()) {
+            if first.ident == "Self" {
+                first.ident = root_alias();
+                first.arguments = self.root_args.clone();
+            } else if first.ident == self.root {
+                first.ident = root_alias();
+            } else if self.shadowed.contains(&first.ident) {
+                if !self.aliased.contains(&first.ident) {
+                    self.aliased.push(first.ident.clone());
+                }
+                first.ident = shadowed_alias(&first.ident);
+            }
+        }
+        visit_mut::visit_path_mut(self, path);
+    }
+}
+
+fn root_alias() -> Ident {
+    Ident::new("__KeypathSelf", Span::call_site())
+}
+
+fn shadowed_alias(ident: &Ident) -> Ident {
+    Ident::new(&format!("__KeypathShadowed_{}", ident), ident.span())
+}
+
+/// The items of the `Keyable` impl that provide the mirror.
+fn mirror_trait_items(attrs: &ContainerAttrs) -> TokenStream {
+    let default_value_fn = default_value_fn(attrs.default);
+    quote!(
+        type Mirror = <Self as __keypath::internals::DerivedMirror>::Mirror;
+        const MIRROR: Self::Mirror = <Self as __keypath::internals::DerivedMirror>::MIRROR;
+
+        #default_value_fn
+    )
+}
+
+fn accessor_root() -> Ident {
+    Ident::new("__KeypathRoot", Span::call_site())
+}
+
+/// The declaration of a struct's typed accessor, whose fields hold the
+/// accessors of the struct's fields, and which derefs to its own keypath.
+fn accessor_struct(
+    ident: &Ident,
     base_vis: &syn::Visibility,
-    generics: &syn::Generics,
+    generics: &DeriveGenerics,
     fields: &Fields,
-) -> Result<(proc_macro2::TokenStream, proc_macro2::TokenStream), syn::Error> {
-    let (_, ty_generics, _) = generics.split_for_impl();
-    let impl_generics = add_generic_bounds(generics, quote!(::keypath::Keyable));
-    let mirror_ident = mirror_ident_for_base_ident(base_ident);
+) -> TokenStream {
+    let root = accessor_root();
+    let impl_params = generics.impl_params();
+    let args = generics.root_args_with(&root);
+    let where_clause = generics.where_clause();
+    let root_alias = root_alias();
+    let root_args = generics.root_args();
+    let value = quote!(#root_alias #root_args);
+    let decls = fields.generate_accessor_decls(&root, &value);
+    let member = fields.accessor_path_member();
 
-    let field_decls = fields.generate_mirror_decls();
-    let struct_decl = match fields.kind {
+    let decl = match fields.kind {
         FieldKind::Named => {
-            quote!(pub struct #mirror_ident <#impl_generics>{#field_decls})
+            quote!(#base_vis struct #ident <#impl_params #root> #where_clause { #decls })
         }
-
         FieldKind::Unnamed => {
-            quote!(pub struct #mirror_ident <#impl_generics>(#field_decls);)
+            quote!(#base_vis struct #ident <#impl_params #root>(#decls) #where_clause;)
         }
     };
-    let struct_decl = quote!(#[allow(non_camel_case_types)]
-        #struct_decl);
+    quote!(
+        #decl
 
-    let generic_idents = get_generic_idents(generics);
-    let optional_const_token = if generic_idents.is_empty() {
-        quote!(const)
-    } else {
-        proc_macro2::TokenStream::new()
-    };
-    let struct_field_init = fields.generate_mirror_inits(&generic_idents);
-    let struct_init = match fields.kind {
-        FieldKind::Named => quote!(Self {#struct_field_init}),
-        FieldKind::Unnamed => quote!(Self (#struct_field_init)),
-    };
+        impl<#impl_params #root> ::std::ops::Deref for #ident #args #where_clause {
+            type Target = __keypath::KeyPath<#root, #value>;
 
-    let tokens = quote!(
-        #struct_decl
+            fn deref(&self) -> &Self::Target {
+                &self.#member
+            }
+        }
+    )
+}
+
+/// The items of a struct's `Keyable` impl that provide its typed accessor.
+fn accessor_trait_items(ident: &Ident, generics: &DeriveGenerics, fields: &Fields) -> TokenStream {
+    let root = accessor_root();
+    let args = generics.root_args_with(&root);
+    let init = fields.generate_accessor_init(ident);
+    quote!(
+        type KeyPaths<#root> = #ident #args;
+
+        fn keypaths_from<#root>(path: __keypath::KeyPath<#root, Self>) -> Self::KeyPaths<#root> {
+            #init
+        }
+    )
+}
+
+/// The items of the `Keyable` impl for a type without typed accessors, whose
+/// accessor is just its keypath.
+fn leaf_accessor_trait_items() -> TokenStream {
+    let root = accessor_root();
+    quote!(
+        type KeyPaths<#root> = __keypath::KeyPath<#root, Self>;
 
-        impl< #impl_generics> #mirror_ident #ty_generics {
-            #optional_const_token fn new() -> Self {
+        fn keypaths_from<#root>(path: __keypath::KeyPath<#root, Self>) -> Self::KeyPaths<#root> {
+            path
+        }
+    )
+}
+
+/// The impls shared by struct and enum mirrors, which connect the mirror to
+/// the type it mirrors.
+fn mirror_impls(
+    mirror_ident: &Ident,
+    base_vis: &syn::Visibility,
+    generics: &DeriveGenerics,
+    struct_init: TokenStream,
+) -> TokenStream {
+    let impl_params = generics.impl_params();
+    let root_args = generics.root_args();
+    let where_clause = generics.where_clause();
+    let root = root_alias();
+
+    quote!(
+        impl<#impl_params> #mirror_ident #root_args #where_clause {
+            const fn new() -> Self {
                 #struct_init
             }
 
-        #base_vis #optional_const_token fn to_key_path_with_root<Root>(self, fields: &'static [::keypath::internals::PathComponent]) -> ::keypath::KeyPath<Root, #base_ident #ty_generics> {
-            ::keypath::KeyPath::__conjure_from_abyss(fields)
+            #base_vis const fn value_type(&self) -> ::std::marker::PhantomData<#root #root_args> {
+                ::std::marker::PhantomData
+            }
         }
+
+        impl<#impl_params> __keypath::internals::Mirror for #mirror_ident #root_args #where_clause {
+            type Value = #root #root_args;
         }
-    );
 
-    let trait_impl = quote!(
-        type Mirror = #mirror_ident #ty_generics;
-        fn mirror() -> #mirror_ident #ty_generics{
-            #mirror_ident::new()
+        impl<#impl_params> __keypath::internals::DerivedMirror for #root #root_args #where_clause {
+            type Mirror = #mirror_ident #root_args;
+            const MIRROR: Self::Mirror = #mirror_ident::new();
         }
-    );
-    Ok((tokens, trait_impl))
+    )
+}
+
+/// The declaration of a mirror struct, and the expression that creates it,
+/// given its fields.
+///
+/// If `phantom` is provided, it is added as a final field, so that the mirror
+/// uses all of its generic params.
+fn mirror_struct_decl(
+    ident: &Ident,
+    base_vis: &syn::Visibility,
+    generics: &DeriveGenerics,
+    fields: &Fields,
+    phantom: Option<TokenStream>,
+) -> (TokenStream, TokenStream) {
+    let impl_params = generics.impl_params();
+    let where_clause = generics.where_clause();
+
+    let mut field_decls = fields.generate_mirror_decls();
+    let mut field_inits = fields.generate_mirror_inits();
+    if let Some(phantom) = phantom {
+        let (decl, init) = match fields.kind {
+            FieldKind::Named => (
+                quote!(__phantom: #phantom),
+                quote!(__phantom: ::std::marker::PhantomData),
+            ),
+            FieldKind::Unnamed => (quote!(#phantom), quote!(::std::marker::PhantomData)),
+        };
+        if field_decls.is_empty() {
+            field_decls = decl;
+            field_inits = init;
+        } else {
+            field_decls = quote!(#field_decls, #decl);
+            field_inits = quote!(#field_inits, #init);
+        }
+    }
+
+    match fields.kind {
+        FieldKind::Named => (
+            quote!(#base_vis struct #ident <#impl_params> #where_clause {#field_decls}),
+            quote!(Self {#field_inits}),
+        ),
+        FieldKind::Unnamed => (
+            quote!(#base_vis struct #ident <#impl_params>(#field_decls) #where_clause;),
+            quote!(Self (#field_inits)),
+        ),
+    }
 }
 
-fn mirror_ident_for_base_ident(ident: &Ident) -> Ident {
-    Ident::new(
-        &format!("{}{}", DERIVED_MIRROR_STRUCT_PREFIX, ident),
-        ident.span(),
+fn mirror_struct(
+    base_ident: &Ident,
+    base_vis: &syn::Visibility,
+    generics: &DeriveGenerics,
+    fields: &Fields,
+) -> TokenStream {
+    let (struct_decl, struct_init) = mirror_struct_decl(
+        base_ident,
+        base_vis,
+        generics,
+        fields,
+        generics.phantom(false),
+    );
+    let impls = mirror_impls(base_ident, base_vis, generics, struct_init);
+    let deref_impl = fields.generate_mirror_deref(base_ident, generics);
+
+    quote!(
+        #struct_decl
+
+        #impls
+
+        #deref_impl
     )
 }
 
-fn get_generic_idents(generics: &syn::Generics) -> Vec<Ident> {
-    generics
-        .params
+fn mirror_enum(
+    base_ident: &Ident,
+    base_vis: &syn::Visibility,
+    generics: &DeriveGenerics,
+    variants: &[Variant],
+) -> TokenStream {
+    let impl_params = generics.impl_params();
+    let root_args = generics.root_args();
+    let where_clause = generics.where_clause();
+    let root = root_alias();
+
+    // unit variants have nothing to mirror
+    let variants = variants
         .iter()
-        .filter_map(|gp| match gp {
-            syn::GenericParam::Type(ty) => Some(ty.ident.clone()),
-            _ => None,
-        })
-        .collect()
-}
-
-fn add_generic_bounds(
-    generics: &syn::Generics,
-    with_bounds: proc_macro2::TokenStream,
-) -> proc_macro2::TokenStream {
-    let res = generics.params.iter().map(|gp| {
-        use syn::GenericParam::*;
-        match gp {
-            Type(ty) => {
-                let ident = &ty.ident;
-                let bounds = &ty.bounds;
-                if bounds.is_empty() {
-                    quote_spanned!(ty.span()=> #ident : #with_bounds)
-                } else {
-                    quote_spanned!(ty.span()=> #ident : #bounds + #with_bounds)
+        .filter(|v| !v.fields.is_empty())
+        .collect::<Vec<_>>();
+
+    let variant_decls = variants.iter().map(|variant| {
+        let ident = variant_mirror_ident(base_ident, &variant.ident);
+        // a variant may not use all of the enum's generic params, so variant
+        // mirrors carry them in a PhantomData.
+        let (struct_decl, struct_init) = mirror_struct_decl(
+            &ident,
+            base_vis,
+            generics,
+            &variant.fields,
+            generics.phantom(true),
+        );
+        let deref_impl = variant.fields.generate_mirror_deref(&ident, generics);
+
+        quote!(
+            #struct_decl
+
+            #deref_impl
+
+            impl<#impl_params> #ident #root_args #where_clause {
+                const fn new() -> Self {
+                    #struct_init
                 }
             }
-            Lifetime(lf) => quote!(#lf),
-            Const(cst) => quote!(#cst),
-        }
+
+            impl<#impl_params> __keypath::internals::Mirror for #ident #root_args #where_clause {
+                type Value = #root #root_args;
+            }
+        )
     });
 
-    quote!( #( #res, )* )
+    let variant_names = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+    let variant_types = variants
+        .iter()
+        .map(|v| variant_mirror_ident(base_ident, &v.ident))
+        .collect::<Vec<_>>();
+    let impls = mirror_impls(
+        base_ident,
+        base_vis,
+        generics,
+        quote!(Self { #( #variant_names: #variant_types::new() ),* }),
+    );
+
+    quote!(
+        #( #variant_decls )*
+
+        #[allow(non_snake_case, dead_code)]
+        #base_vis struct #base_ident <#impl_params> #where_clause {
+            #( #base_vis #variant_names: #variant_types #root_args ),*
+        }
+
+        #impls
+    )
+}
+
+/// Variant mirrors are named after the variant, unless that would clash
+/// with the enum's own mirror.
+fn variant_mirror_ident(base_ident: &Ident, variant: &Ident) -> Ident {
+    if variant == base_ident {
+        Ident::new(&format!("{}__{}", base_ident, variant), variant.span())
+    } else {
+        variant.clone()
+    }
 }
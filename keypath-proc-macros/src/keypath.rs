@@ -1,20 +1,82 @@
 use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
 use quote::quote;
 
 use super::keypath_parse::{KeyPathMacroInput, SyntaxError};
+use super::shared;
 
 pub(crate) fn keypath_impl(input: TokenStream) -> Result<TokenStream, SyntaxError> {
     //eprintln!("{:#?}", input);
-    let KeyPathMacroInput { root, components } = KeyPathMacroInput::parse(input)?;
+    let KeyPathMacroInput {
+        crate_path,
+        root,
+        components,
+    } = KeyPathMacroInput::parse(input)?;
 
-    let element_validators = components.iter().map(|comp| comp.traverse_type());
+    let path_type = if components.iter().any(|comp| comp.is_wildcard()) {
+        quote!(__keypath::WildcardKeyPath)
+    } else if components.iter().any(|comp| comp.is_fallible()) {
+        quote!(__keypath::FallibleKeyPath)
+    } else {
+        quote!(__keypath::KeyPath)
+    };
+    // the root's mirror is the argument of a closure, so that the value type
+    // shares the root's lifetimes; the closure is only type-checked, and is
+    // never called. The argument is hygienic, so index expressions can't see it.
+    let root_mirror = Ident::new("mirror", Span::mixed_site());
+    let mirror = components
+        .iter()
+        .fold(quote!(#root_mirror), |mirror, comp| {
+            comp.traverse_type(mirror)
+        });
+    let value_type = quote!(|#root_mirror| #mirror.value_type());
     let element_fields = components.iter().map(|comp| comp.to_tokens());
-    let tokens = quote!(
-        <#root as ::keypath::Keyable>::Mirror::new()
-        #( #element_validators )*
+    let key_bindings = components
+        .iter()
+        .filter_map(|comp| comp.key_binding())
+        .collect::<Vec<_>>();
 
-        .to_key_path_with_root::<#root>(&[#( #element_fields ),*])
+    // errors for components that a type doesn't support come from here
+    let crate_import = shared::crate_import(crate_path.as_ref());
+    let fallbacks = quote!(
+        #crate_import
+        #[allow(unused_imports)]
+        use __keypath::internals::UnsupportedComponent as _;
     );
+
+    let tokens = if key_bindings.is_empty() {
+        quote!({
+            #fallbacks
+            // SAFETY: the value type is inferred by traversing the root's mirror
+            unsafe {
+                #path_type::<#root, _>::__conjure_from_abyss(
+                    #value_type,
+                    {
+                        const FIELDS: &[__keypath::internals::PathComponent] = &[#( #element_fields ),*];
+                        FIELDS
+                    },
+                )
+            }
+        })
+    } else {
+        // paths with runtime indices are always fallible, or wildcards
+        let path_type = if components.iter().any(|comp| comp.is_wildcard()) {
+            quote!(__keypath::WildcardKeyPath)
+        } else {
+            quote!(__keypath::FallibleKeyPath)
+        };
+        quote!({
+            #fallbacks
+            #( #key_bindings )*
+            // SAFETY: as above
+            unsafe {
+                #path_type::<#root, _>::__conjure_from_abyss_owned(
+                    #value_type,
+                    vec![#( #element_fields ),*],
+                )
+            }
+        })
+    };
     //eprintln!("{}", tokens);
     Ok(tokens.into())
 }
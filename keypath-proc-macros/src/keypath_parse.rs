@@ -2,11 +2,14 @@ use std::iter::{FromIterator, Peekable};
 
 use proc_macro::token_stream::IntoIter as StreamIter;
 use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
-use quote::quote_spanned;
+use quote::{quote_spanned, ToTokens};
+use syn::parse::Parser;
 
 use super::shared::PathComponent;
 
 pub(crate) struct KeyPathMacroInput {
+    /// The path to the keypath crate, from a leading `crate = path;`.
+    pub(crate) crate_path: Option<syn::Path>,
     pub(crate) root: proc_macro2::TokenStream,
     pub(crate) components: Vec<SpannedComponent>,
 }
@@ -30,8 +33,30 @@ enum FieldLiteral {
 type TokenIter = Peekable<StreamIter>;
 
 impl SpannedComponent {
-    pub(crate) fn traverse_type(&self) -> proc_macro2::TokenStream {
-        self.element.mirror_item_access(self.span.into())
+    /// Apply this component to the mirror of the previous component.
+    ///
+    /// The previous mirror is wrapped in parentheses with this component's
+    /// span, so that errors for this component point at it, rather than at
+    /// the whole path.
+    pub(crate) fn traverse_type(
+        &self,
+        mirror: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let span = self.span.into();
+        let access = self.element.mirror_item_access(span);
+        quote_spanned!(span=> (#mirror) #access)
+    }
+
+    pub(crate) fn is_fallible(&self) -> bool {
+        self.element.is_fallible()
+    }
+
+    pub(crate) fn is_wildcard(&self) -> bool {
+        self.element.is_wildcard()
+    }
+
+    pub(crate) fn key_binding(&self) -> Option<proc_macro2::TokenStream> {
+        self.element.key_binding_tokens()
     }
 
     pub(crate) fn to_tokens(&self) -> proc_macro2::TokenStream {
@@ -43,60 +68,91 @@ impl SpannedComponent {
 
 impl KeyPathMacroInput {
     pub(crate) fn parse(input: TokenStream) -> Result<Self, SyntaxError> {
-        let mut iter = input.into_iter().peekable();
-        //next_token(&mut iter);
-
-        let root = expect_root(&mut iter)?.into();
-        let components = collect_path_components(&mut iter)?;
-        Ok(KeyPathMacroInput { root, components })
+        let (crate_path, input) = split_crate_path(input)?;
+        let (mut root, rest) = expect_root(input)?;
+        strip_parens(&mut root);
+        let mut components = Vec::new();
+        let mut iter = rest.into_iter().peekable();
+        collect_path_components(&mut iter, &mut components)?;
+        Ok(KeyPathMacroInput {
+            crate_path,
+            root: root.into_token_stream(),
+            components,
+        })
     }
 }
 
-fn expect_root(iter: &mut TokenIter) -> Result<TokenStream, SyntaxError> {
-    let mut result = Vec::new();
-    let root = next_token(iter)?;
-    match &root {
-        TokenTree::Ident(_) => result.push(root),
-        _other => {
-            return Err(SyntaxError::new(
-                root.span(),
-                "Keypath should start with Type",
-            ))
+/// Parse the optional `crate = path::to::keypath;` that precedes the path,
+/// returning the crate path and the remaining input.
+fn split_crate_path(input: TokenStream) -> Result<(Option<syn::Path>, TokenStream), SyntaxError> {
+    let mut iter = input.clone().into_iter();
+    let start = match (iter.next(), iter.next()) {
+        (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(eq)))
+            if ident.to_string() == "crate" && eq.as_char() == '=' =>
+        {
+            eq.span()
         }
-    }
+        _ => return Ok((None, input)),
+    };
 
-    if matches!(iter.peek(), Some(&TokenTree::Punct(ref p)) if p.as_char() == '<') {
-        result.extend(expect_root_generics(iter)?);
+    let mut path = TokenStream::new();
+    loop {
+        match iter.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ';' => break,
+            Some(token) => path.extend(Some(token)),
+            None => {
+                return Err(SyntaxError::new(
+                    start,
+                    "expected ';' after the crate path, as in `crate = path::to::keypath;`",
+                ))
+            }
+        }
     }
-    Ok(result.into_iter().collect())
+    let path = syn::parse::<syn::Path>(path).map_err(|e| {
+        SyntaxError::new(
+            e.span().unwrap(),
+            format!("expected the path to the keypath crate: {}", e),
+        )
+    })?;
+    Ok((Some(path), iter.collect()))
 }
 
-fn expect_root_generics(iter: &mut TokenIter) -> Result<Vec<TokenTree>, SyntaxError> {
-    let mut result = Vec::new();
-    result.push(TokenTree::Punct(expect_punct(iter, '<')?));
-    let mut done = false;
-    for token in iter {
-        done = matches!(token, TokenTree::Punct(ref p) if p.as_char() == '>');
-        result.push(token);
-        if done {
-            break;
-        }
-    }
-    if !done {
-        Err(SyntaxError::new(
-            result.first().unwrap().span(),
-            "Missing closing '>'",
-        ))
-    } else {
-        Ok(result)
+/// Parse the root type; this is everything up to the first path component.
+fn expect_root(input: TokenStream) -> Result<(syn::Type, TokenStream), SyntaxError> {
+    let parser = |stream: syn::parse::ParseStream| {
+        let root: syn::Type = stream.parse()?;
+        let rest: proc_macro2::TokenStream = stream.parse()?;
+        Ok((root, rest))
+    };
+    parser
+        .parse(input)
+        .map(|(root, rest)| (root, rest.into()))
+        .map_err(|e| {
+            SyntaxError::new(
+                e.span().unwrap(),
+                format!("Keypath should start with Type: {}", e),
+            )
+        })
+}
+
+/// Remove the parentheses from a root such as `(Shape)`.
+///
+/// The root is always a type, so a variant of the root has to follow it as a
+/// component, and `Shape::Circle` would be read as a type; the parentheses
+/// end the type, so that `(Shape)::Circle` is a path through the variant.
+fn strip_parens(root: &mut syn::Type) {
+    if let syn::Type::Paren(paren) = root {
+        *root = (*paren.elem).clone();
     }
 }
 
-fn collect_path_components(iter: &mut TokenIter) -> Result<Vec<SpannedComponent>, SyntaxError> {
-    let mut result = Vec::new();
+fn collect_path_components(
+    iter: &mut TokenIter,
+    result: &mut Vec<SpannedComponent>,
+) -> Result<(), SyntaxError> {
     loop {
         match iter.next() {
-            None => return Ok(result),
+            None => return Ok(()),
             Some(TokenTree::Punct(p)) if p.as_char() == '.' => match iter
                 .next()
                 .ok_or_else(|| SyntaxError::new(p.span(), "'.' must be followed by a field"))?
@@ -105,7 +161,7 @@ fn collect_path_components(iter: &mut TokenIter) -> Result<Vec<SpannedComponent>
                     span: ident.span(),
                     element: PathComponent::named(ident.to_string()),
                 }),
-                TokenTree::Literal(lit) => append_fields_from_lit(&lit, &mut result)?,
+                TokenTree::Literal(lit) => append_fields_from_lit(&lit, result)?,
                 other => {
                     return Err(SyntaxError::new(
                         other.span(),
@@ -113,17 +169,45 @@ fn collect_path_components(iter: &mut TokenIter) -> Result<Vec<SpannedComponent>
                     ))
                 }
             },
+            Some(TokenTree::Punct(p)) if p.as_char() == '?' => result.push(SpannedComponent {
+                span: p.span(),
+                element: PathComponent::Optional,
+            }),
+            Some(TokenTree::Punct(p)) if p.as_char() == ':' => {
+                result.push(expect_variant(&p, iter)?);
+            }
             Some(TokenTree::Group(g)) if matches!(g.delimiter(), Delimiter::Bracket) => {
-                result.push(expect_index(&g)?);
+                let component = expect_index(&g, result.len())?;
+                result.push(component);
             }
             Some(other) => {
-                eprintln!("BAD TOKEN {:?}", other);
-                return Err(SyntaxError::new(other.span(), "expected '.' or '['"));
+                return Err(SyntaxError::new(
+                    other.span(),
+                    "expected '.', '::', '?' or '['",
+                ));
             }
         }
     }
 }
 
+/// Parse an enum variant, such as `::Circle`; the first ':' has already been consumed.
+fn expect_variant(first: &Punct, iter: &mut TokenIter) -> Result<SpannedComponent, SyntaxError> {
+    if first.spacing() != Spacing::Joint {
+        return Err(SyntaxError::new(first.span(), "expected '::'"));
+    }
+    expect_punct(iter, ':')?;
+    match next_token(iter)? {
+        TokenTree::Ident(ident) => Ok(SpannedComponent {
+            span: ident.span(),
+            element: PathComponent::Variant(ident.to_string()),
+        }),
+        other => Err(SyntaxError::new(
+            other.span(),
+            format!("expected variant identifier, found '{}'", other),
+        )),
+    }
+}
+
 fn append_fields_from_lit(
     lit: &Literal,
     result: &mut Vec<SpannedComponent>,
@@ -152,17 +236,51 @@ fn append_fields_from_lit(
     Ok(())
 }
 
-fn expect_index(g: &Group) -> Result<SpannedComponent, SyntaxError> {
+fn expect_index(g: &Group, position: usize) -> Result<SpannedComponent, SyntaxError> {
     let mut tokens = g.stream().into_iter();
-    let lit = match tokens.next() {
-        Some(TokenTree::Literal(lit)) => lit,
-        _ => {
+    let lit = match (tokens.next(), tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(lit)), None, _) => lit,
+        (None, ..) => {
             return Err(SyntaxError::new(
                 g.span(),
-                "Brackets must contain a string or integer literal",
+                "Brackets must contain a literal or an expression",
             ))
         }
+        (Some(TokenTree::Punct(p)), None, _) if p.as_char() == '*' => {
+            return Ok(SpannedComponent {
+                element: PathComponent::Wildcard,
+                span: p.span(),
+            })
+        }
+        // negative integers can only be keys into maps
+        (Some(TokenTree::Punct(p)), Some(TokenTree::Literal(lit)), None)
+            if p.as_char() == '-'
+                && matches!(
+                    litrs::Literal::parse(lit.to_string()),
+                    Ok(litrs::Literal::Integer(_))
+                ) =>
+        {
+            return Ok(index_expr(g, position, true))
+        }
+        // anything else is an expression, to be evaluated at runtime
+        _ => return Ok(index_expr(g, position, false)),
     };
+    match litrs::Literal::parse(lit.to_string()) {
+        // floats that aren't valid field pairs, such as `5_f64`, would
+        // otherwise be reported as invalid fields
+        Ok(litrs::Literal::Float(_)) => return Err(float_index_error(&lit)),
+        // integers with a suffix, such as `5_u8`, and characters are keys of
+        // that type
+        Ok(litrs::Literal::Integer(int))
+            if !matches!(int.type_suffix(), None | Some(litrs::IntegerType::Usize)) =>
+        {
+            return Ok(index_expr(g, position, true))
+        }
+        Ok(litrs::Literal::Char(_)) | Ok(litrs::Literal::Byte(_)) => {
+            return Ok(index_expr(g, position, true))
+        }
+        _ => (),
+    }
     match parse_literal(&lit)? {
         FieldLiteral::Named(name) => Ok(SpannedComponent {
             element: PathComponent::IndexStr(name),
@@ -172,32 +290,85 @@ fn expect_index(g: &Group) -> Result<SpannedComponent, SyntaxError> {
             element: PathComponent::IndexInt(idx),
             span: lit.span(),
         }),
-        FieldLiteral::UnnamedPair(..) => Err(SyntaxError::new(
-            lit.span(),
-            "collection indices must be string or unsigned integer literals",
-        )),
+        FieldLiteral::UnnamedPair(..) => Err(float_index_error(&lit)),
     }
 }
 
-fn parse_literal(lit: &Literal) -> Result<FieldLiteral, SyntaxError> {
-    let raw_lit = lit.to_string();
-    if raw_lit.starts_with('"') {
-        return Ok(FieldLiteral::Named(raw_lit.trim_matches('"').to_string()));
+/// An index expression, such as `[i]`, which is evaluated at runtime and
+/// type-checked against the collection's keys.
+///
+/// `literal` is true for literals that can only be map keys, such as `[-1]`
+/// or `['a']`, so that using them on sequences gets a more specific error.
+fn index_expr(g: &Group, position: usize, literal: bool) -> SpannedComponent {
+    let binding = proc_macro2::Ident::new(
+        &format!("__keypath_key_{}", position),
+        proc_macro2::Span::call_site(),
+    );
+    SpannedComponent {
+        element: PathComponent::IndexExpr {
+            expr: g.stream().into(),
+            binding,
+            literal,
+        },
+        span: g.span(),
     }
+}
 
-    if let Ok(idx) = raw_lit.parse::<usize>() {
-        return Ok(FieldLiteral::Unnamed(idx));
-    }
+fn float_index_error(lit: &Literal) -> SyntaxError {
+    SyntaxError::new(
+        lit.span(),
+        "collection indices must be string or unsigned integer literals",
+    )
+}
 
-    // see if it's a float, where both sides of the decimal place are valid usizes
-    raw_lit
-        .split_once('.')
-        .and_then(|(front, back)| {
-            let front = front.parse::<usize>().ok()?;
-            let back = back.parse::<usize>().ok()?;
-            Some(FieldLiteral::UnnamedPair(front, back))
-        })
-        .ok_or_else(|| SyntaxError::new(lit.span(), "identifiers must be strings or integers"))
+/// Decode a literal used as a field or an index.
+///
+/// String keys may be written in any form that Rust accepts, including
+/// escapes and raw strings; byte strings are accepted if they are ASCII.
+fn parse_literal(lit: &Literal) -> Result<FieldLiteral, SyntaxError> {
+    let raw_lit = lit.to_string();
+    let error = |message: &str| SyntaxError::new(lit.span(), message);
+    match litrs::Literal::parse(raw_lit.as_str()) {
+        Ok(litrs::Literal::String(string)) => Ok(FieldLiteral::Named(string.value().to_owned())),
+        // byte strings cannot contain unicode, so without the 'b' prefix they are
+        // ordinary strings, unless they have non-ASCII `\x` escapes.
+        Ok(litrs::Literal::ByteString(_)) => litrs::StringLit::parse(&raw_lit[1..])
+            .map(|string| FieldLiteral::Named(string.value().to_owned()))
+            .map_err(|_| error("byte string keys may only contain ASCII")),
+        Ok(litrs::Literal::Integer(int)) => {
+            match int.type_suffix() {
+                None | Some(litrs::IntegerType::Usize) => (),
+                Some(_) => {
+                    // integer suffixes always start with 'u' or 'i', which are not hex digits
+                    let suffix = &raw_lit[raw_lit.rfind(&['u', 'i'][..]).unwrap_or(0)..];
+                    return Err(SyntaxError::new(
+                        lit.span(),
+                        format!(
+                            "field indices must be usize; remove the '{}' suffix",
+                            suffix
+                        ),
+                    ));
+                }
+            }
+            int.value::<usize>()
+                .map(FieldLiteral::Unnamed)
+                .ok_or_else(|| error("integer is too large"))
+        }
+        // `.0.1` is lexed as the float `0.1`
+        Ok(litrs::Literal::Float(float)) => float
+            .fractional_part()
+            .filter(|_| float.exponent_part().is_empty() && float.type_suffix().is_none())
+            .and_then(|back| {
+                let front = float.integer_part().parse::<usize>().ok()?;
+                let back = back.parse::<usize>().ok()?;
+                Some(FieldLiteral::UnnamedPair(front, back))
+            })
+            .ok_or_else(|| error("identifiers must be strings or integers")),
+        Ok(litrs::Literal::Char(_)) | Ok(litrs::Literal::Byte(_)) => Err(error(
+            "character literals cannot be used as keys; use a string literal",
+        )),
+        _ => Err(error("identifiers must be strings or integers")),
+    }
 }
 
 fn next_token(iter: &mut TokenIter) -> Result<TokenTree, SyntaxError> {
@@ -5,6 +5,7 @@
 extern crate proc_macro;
 
 mod attr;
+mod generics;
 mod keyable;
 mod keypath;
 mod keypath_parse;
@@ -13,7 +14,7 @@ mod shared;
 use proc_macro::TokenStream;
 use syn::parse_macro_input;
 
-#[proc_macro_derive(Keyable)]
+#[proc_macro_derive(Keyable, attributes(keypath))]
 pub fn derive_keyable(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as syn::DeriveInput);
     keyable::derive_keyable_impl(input)
@@ -25,16 +26,52 @@ pub fn derive_keyable(input: TokenStream) -> TokenStream {
 ///
 /// This verifies at compile-time that the path is valid.
 ///
-/// This macro expects a *type name*, followed by one or more *path components*.
-/// Path components may be either *fields* or *indices*.
+/// This macro expects a *type*, followed by one or more *path components*.
+/// Path components may be *fields*, *indices*, *variants*, or *optionals*.
+///
+/// The type may be any Rust type that implements `Keyable`, including
+/// qualified paths (`crate::model::Person`), generic types
+/// (`Wrapper<Vec<u8>>`), associated types (`Self::Item`), `Self`, and tuples
+/// (`(u8, String)`).
+///
+/// The type is always read as a whole path, since a macro can't tell a type
+/// in a module, such as `shapes::Circle`, from a variant of an enum, such as
+/// `Shape::Circle`. To start a path with a variant, put the enum in
+/// parentheses: `keypath!((Shape)::Circle.radius)` is a path through the
+/// `Circle` variant of `Shape`.
 ///
 /// - field: a single '`.`' character, followed by either a valid identifier or
 ///   a single unsized integer.
-/// - indicies: a pair of brackets (`[]`) containing either a string literal or
-///   an unsized integer.
+/// - indicies: a pair of brackets (`[]`) containing either a string literal,
+///   an unsized integer, or an expression that evaluates to an index or a key
+///   of the collection, such as `[i]`, `[{ key }]` or `[Suit::Hearts]`.
+/// - variants: a '`::`' followed by the name of an enum variant.
+/// - optionals: a single '`?`' character, following a component of type `Option<T>`.
 ///
 /// Fields should correspond to named or unnamed fields on the base type.
-/// Indicies refer to members of collections.
+/// Indicies refer to members of collections. Variants refer to a variant of
+/// an enum; the fields of that variant can then be accessed. Accessing a
+/// variant that is not the active variant is an error.
+///
+/// Integer literals can index maps with integer keys, as long as they fit in
+/// the key type; negative or suffixed integers, such as `[-1]` or `[5_u8]`,
+/// are expressions.
+///
+/// Optionals refer to the value inside an `Option`, in the manner of Swift's
+/// optional chaining.
+///
+/// If a path contains only fields, this produces a `KeyPath`, which cannot
+/// fail. If it contains any indices, variants or optionals, it produces a
+/// `FallibleKeyPath`, which returns an error if (for instance) an index is
+/// out of range or an `Option` is `None`.
+///
+/// Index expressions are evaluated once, when the keypath is created. A path
+/// that contains an index expression is built at runtime, and so cannot be
+/// used in a `const` context.
+///
+/// The path may be preceded by `crate = path::to::keypath;`, for when the
+/// keypath crate is not available as `::keypath`, such as when it is
+/// re-exported by another crate.
 ///
 /// # Examples
 ///
@@ -48,6 +85,16 @@ pub fn derive_keyable(input: TokenStream) -> TokenStream {
 /// keypath!(Person.friends["常羽辰"].address);
 /// keypath!(Thing.field.0["friends"].count);
 /// keypath!(Thing.1[2][3].size.width);
+/// keypath!((Shape)::Circle.radius);
+/// keypath!(crate::model::Person.name);
+/// keypath!(Wrapper<Vec<u8>>.inner[0]);
+/// keypath!((u8, String).1);
+/// keypath!(Self.size);
+/// keypath!(Drawing.shapes[0]::Rect.0);
+/// keypath!(Person.spouse?.name);
+/// keypath!(Person.friends[i].name);
+/// keypath!(Ledger.accounts[-1].balance);
+/// keypath!(crate = platform::keypath; Person.name);
 /// ```
 #[proc_macro]
 pub fn keypath(input: TokenStream) -> TokenStream {
@@ -1,12 +1,41 @@
-use proc_macro2::{Ident, Literal, Span};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
 use quote::{quote, quote_spanned};
 
+/// Import the keypath crate as `__keypath`, which is how all generated code
+/// refers to it.
+///
+/// The crate is at `::keypath` unless another path is given, such as when it
+/// is re-exported by another crate, or renamed in `Cargo.toml`.
+pub fn crate_import(path: Option<&syn::Path>) -> TokenStream {
+    match path {
+        Some(path) => quote!(use #path as __keypath;),
+        None => quote!(
+            use ::keypath as __keypath;
+        ),
+    }
+}
+
 pub enum PathComponent {
     Field(FieldIdent),
     IndexInt(usize),
     IndexStr(String),
+    Variant(String),
+    Optional,
+    /// A wildcard, `[*]`, which matches every item of a collection.
+    Wildcard,
+    /// An index that is evaluated at runtime, such as `[i]`. The key is
+    /// evaluated once, and stored in a local variable named `binding`.
+    ///
+    /// Literals that can only be map keys, such as `[-1]`, `[5_u8]` or
+    /// `['a']`, are also evaluated this way, and are marked as `literal`.
+    IndexExpr {
+        expr: proc_macro2::TokenStream,
+        binding: Ident,
+        literal: bool,
+    },
 }
 
+#[derive(Clone)]
 pub enum FieldIdent {
     Named(String),
     Unnamed(usize),
@@ -21,13 +50,43 @@ impl PathComponent {
         PathComponent::Field(FieldIdent::Named(name.into()))
     }
 
+    /// Whether this component can fail to resolve at runtime.
+    pub fn is_fallible(&self) -> bool {
+        !matches!(self, PathComponent::Field(_))
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        matches!(self, PathComponent::Wildcard)
+    }
+
     pub fn path_component_tokens(&self) -> proc_macro2::TokenStream {
         match self {
             PathComponent::Field(ident) => ident.path_component_tokens(),
             PathComponent::IndexInt(idx) => {
-                quote!(::keypath::internals::PathComponent::IndexInt(#idx))
+                quote!(__keypath::internals::PathComponent::IndexInt(#idx))
+            }
+            PathComponent::IndexStr(s) => quote!(
+                __keypath::internals::PathComponent::IndexStr(::std::borrow::Cow::Borrowed(#s))
+            ),
+            PathComponent::Variant(s) => quote!(
+                __keypath::internals::PathComponent::Variant(::std::borrow::Cow::Borrowed(#s))
+            ),
+            PathComponent::Optional => quote!(__keypath::internals::PathComponent::Optional),
+            PathComponent::Wildcard => quote!(__keypath::internals::PathComponent::Wildcard),
+            PathComponent::IndexExpr { binding, .. } => {
+                quote!(__keypath::internals::IndexKey::to_path_component(#binding))
+            }
+        }
+    }
+
+    /// For components that are evaluated at runtime, the statement that
+    /// evaluates the key.
+    pub fn key_binding_tokens(&self) -> Option<proc_macro2::TokenStream> {
+        match self {
+            PathComponent::IndexExpr { expr, binding, .. } => {
+                Some(quote!(let #binding = &(#expr);))
             }
-            PathComponent::IndexStr(s) => quote!(::keypath::internals::PathComponent::IndexStr(#s)),
+            _ => None,
         }
     }
 
@@ -36,19 +95,29 @@ impl PathComponent {
     pub fn mirror_item_access(&self, span: Span) -> proc_macro2::TokenStream {
         match self {
             PathComponent::Field(FieldIdent::Named(ident)) => {
-                let ident = Ident::new(&ident, span);
+                let ident = Ident::new(ident, span);
                 quote_spanned!(span=> .#ident)
             }
             PathComponent::Field(FieldIdent::Unnamed(ident)) => {
                 let lit = Literal::usize_unsuffixed(*ident);
                 quote_spanned!(span=> .#lit)
             }
-            // NOTE: we tried generating index syntax to improve error diagnostics
-            // but things got weird. Try again at some point?
-            //PathComponent::IndexInt(idx) => quote_spanned!(span=> [#idx]),
-            //PathComponent::IndexStr(s) => quote_spanned!(span=> [#s]),
-            PathComponent::IndexInt(_) => quote_spanned!(span=> .sequence_get()),
+            // mirrors that don't support these fall back to the methods on
+            // `UnsupportedComponent`, which produce more helpful errors.
+            PathComponent::IndexInt(idx) => quote_spanned!(span=> .sequence_get::<#idx>()),
             PathComponent::IndexStr(_) => quote_spanned!(span=> .map_get()),
+            PathComponent::Variant(ident) => {
+                let ident = Ident::new(ident, span);
+                quote_spanned!(span=> .#ident)
+            }
+            PathComponent::Optional => quote_spanned!(span=> .optional_get()),
+            PathComponent::Wildcard => quote_spanned!(span=> .wildcard_get()),
+            PathComponent::IndexExpr {
+                binding,
+                literal: true,
+                ..
+            } => quote_spanned!(span=> .key_get(#binding)),
+            PathComponent::IndexExpr { binding, .. } => quote_spanned!(span=> .index_get(#binding)),
         }
     }
 }
@@ -56,8 +125,23 @@ impl PathComponent {
 impl FieldIdent {
     pub fn path_component_tokens(&self) -> proc_macro2::TokenStream {
         match self {
-            FieldIdent::Named(s) => quote!(::keypath::internals::PathComponent::Named(#s)),
-            FieldIdent::Unnamed(idx) => quote!(::keypath::internals::PathComponent::Unnamed(#idx)),
+            FieldIdent::Named(s) => quote!(
+                __keypath::internals::PathComponent::Named(::std::borrow::Cow::Borrowed(#s))
+            ),
+            FieldIdent::Unnamed(idx) => quote!(__keypath::internals::PathComponent::Unnamed(#idx)),
+        }
+    }
+
+    /// The head of a match arm that matches this field, as the first item of
+    /// `ident.split_first()`.
+    pub fn match_arm_head(&self) -> proc_macro2::TokenStream {
+        match self {
+            FieldIdent::Named(s) => quote!(
+                Some((__keypath::internals::PathComponent::Named(name), rest)) if name == #s
+            ),
+            FieldIdent::Unnamed(idx) => {
+                quote!(Some((__keypath::internals::PathComponent::Unnamed(#idx), rest)))
+            }
         }
     }
 }
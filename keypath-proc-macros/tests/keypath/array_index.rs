@@ -0,0 +1,18 @@
+use std::collections::BTreeMap;
+
+use keypath::{keypath, Keyable};
+
+#[derive(Keyable)]
+struct Swatch {
+    colour: [f32; 4],
+    history: Vec<[u8; 3]>,
+    names: BTreeMap<u8, String>,
+}
+
+fn main() {
+    let _ = keypath!(Swatch.colour[3]);
+    let _ = keypath!(Swatch.colour[4]);
+    let _ = keypath!(Swatch.history[7][3]);
+    let _ = keypath!(Swatch.names[255]);
+    let _ = keypath!(Swatch.names[256]);
+}
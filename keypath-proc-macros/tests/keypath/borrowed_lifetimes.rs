@@ -0,0 +1,30 @@
+use std::marker::PhantomData;
+
+use keypath::{keypath, KeyPath, Keyable};
+
+#[derive(Keyable)]
+struct View<'a> {
+    name: &'a str,
+}
+
+// the value type has the root's lifetime, which can't be extended
+fn longer<'a>() -> KeyPath<View<'a>, &'static str> {
+    keypath!(View.name)
+}
+
+// or shortened independently of the root
+fn shorter<'a: 'b, 'b>(path: KeyPath<View<'a>, &'a str>) -> KeyPath<View<'a>, &'b str> {
+    path
+}
+
+// runtime-typed paths have nothing to tie the lifetimes together
+fn parsed<'a>() -> KeyPath<View<'a>, &'a str> {
+    KeyPath::parse("name").unwrap()
+}
+
+// and paths whose value type isn't inferred from the mirror are unchecked
+fn unchecked<'a>() -> KeyPath<View<'a>, &'static str> {
+    KeyPath::__conjure_from_abyss(|_| PhantomData, &[])
+}
+
+fn main() {}
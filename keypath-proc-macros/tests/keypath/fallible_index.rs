@@ -0,0 +1,11 @@
+use keypath::{keypath, Keyable};
+
+#[derive(Keyable)]
+struct Person {
+    friends: Vec<String>,
+}
+
+fn main() {
+    let person = Person { friends: Vec::new() };
+    let _ = &person[&keypath!(Person.friends[0])];
+}
@@ -0,0 +1,82 @@
+use keypath::{keypath, Keyable};
+
+#[derive(Keyable)]
+struct Person {
+    #[keypath(rename = "title")]
+    name: String,
+    #[keypath(skip)]
+    cache: u8,
+}
+
+#[derive(Keyable)]
+struct Pair(#[keypath(skip)] u8, u8);
+
+#[derive(Keyable)]
+struct Unknown {
+    #[keypath(hidden)]
+    name: String,
+}
+
+#[derive(Keyable)]
+struct Conflict {
+    #[keypath(skip, opaque)]
+    name: String,
+}
+
+#[derive(Keyable)]
+struct BadName {
+    #[keypath(rename = "two words")]
+    name: String,
+}
+
+#[derive(Keyable)]
+#[keypath(crate = "not a path")]
+struct BadCrate {
+    name: String,
+}
+
+#[derive(Keyable)]
+struct RenameUnnamed(#[keypath(rename = "first")] u8);
+
+fn main() {
+    let _ = keypath!(Person.name);
+    let _ = keypath!(Person.cache);
+    let _ = keypath!(Pair.0);
+}
+
+#[derive(Keyable)]
+struct Outer {
+    #[keypath(flatten)]
+    inner: Pair,
+    #[keypath(flatten)]
+    other: Pair,
+}
+
+#[derive(Keyable)]
+#[keypath(transparent)]
+struct Wide(u8, u8);
+
+#[derive(Keyable)]
+#[keypath(transparent)]
+enum Choice {
+    A(u8),
+}
+
+#[derive(Keyable)]
+#[keypath(sequence, transparent)]
+struct Stack(Vec<u8>);
+
+#[derive(Keyable)]
+#[keypath(sequence)]
+struct Unimplemented(Vec<u8>);
+
+#[derive(Keyable)]
+struct Config {
+    #[keypath(flatten)]
+    inner: Person,
+}
+
+fn flattened() {
+    let _ = keypath!(Config.title);
+    let _ = keypath!(Config.inner);
+}
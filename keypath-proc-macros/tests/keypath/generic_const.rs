@@ -0,0 +1,18 @@
+use keypath::{keypath, FallibleKeyPath, KeyPath, Keyable};
+
+#[derive(Keyable)]
+struct Container<T> {
+    names: Vec<T>,
+    #[keypath(flatten)]
+    extra: Extra,
+}
+
+#[derive(Keyable)]
+struct Extra {
+    count: usize,
+}
+
+const _PATH: FallibleKeyPath<Container<String>, String> = keypath!(Container<String>.names[0]);
+const _COUNT: KeyPath<Container<String>, usize> = keypath!(Container<String>.count);
+
+fn main() {}
@@ -19,5 +19,7 @@ fn main() {
     let _ = keypath!(DemoStruct.friend_lists[5_f64].name);
     let _ = keypath!(DemoStruct.friend_lists[five].name);
     let _ = keypath!(DemoStruct.friend_lists['5'].name);
+    let _ = keypath!(DemoStruct.friend_lists[b"\xff"].name);
+    let _ = keypath!(DemoStruct.friend_lists[99999999999999999999999].name);
 
 }
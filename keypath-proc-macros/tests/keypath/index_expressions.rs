@@ -0,0 +1,20 @@
+use keypath::{keypath, Keyable};
+
+#[derive(Keyable)]
+struct DemoStruct {
+    friend_lists: Vec<Person>,
+}
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+}
+
+fn main() {
+    // expressions are type-checked against the collection's index
+    let name = String::from("coco");
+    let _ = keypath!(DemoStruct.friend_lists[name].name);
+    let small: u8 = 1;
+    let _ = keypath!(DemoStruct.friend_lists[small].name);
+    let _ = keypath!(DemoStruct.friend_lists[small as usize].name);
+}
@@ -9,4 +9,6 @@ pub struct Size(f64, f64);
 fn main() {
     let _ = keypath!(TuplePerson.2. 0);
     let _ = keypath!(TuplePerson.2..0);
+    let _ = keypath!(crate = keypath TuplePerson.0);
+    let _ = keypath!(crate = "keypath"; TuplePerson.0);
 }
@@ -0,0 +1,24 @@
+use std::collections::HashMap;
+
+use keypath::{keypath, Keyable};
+
+#[derive(Keyable)]
+struct Ledger {
+    accounts: HashMap<u32, Account>,
+    aliases: HashMap<String, u32>,
+}
+
+#[derive(Keyable)]
+struct Account {
+    balance: i64,
+}
+
+fn main() {
+    let _ = keypath!(Ledger.accounts[5].balance);
+    let _ = keypath!(Ledger.accounts["five"].balance);
+    let _ = keypath!(Ledger.aliases[5]);
+
+    let id: usize = 5;
+    let _ = keypath!(Ledger.accounts[id].balance);
+    let _ = keypath!(Ledger.accounts[5_u64].balance);
+}
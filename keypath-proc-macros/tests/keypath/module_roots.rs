@@ -0,0 +1,37 @@
+use keypath::{keypath, Keyable};
+
+#[allow(non_snake_case)]
+mod Outer {
+    use keypath::Keyable;
+
+    #[derive(Keyable)]
+    pub struct Inner {
+        pub value: u8,
+        pub shape: Shape,
+    }
+
+    #[derive(Keyable)]
+    pub enum Shape {
+        Circle { radius: u8 },
+    }
+}
+
+#[derive(Keyable)]
+struct Holder<T> {
+    item: T,
+}
+
+trait HasItem {
+    type Item: Keyable;
+}
+
+impl<T: Keyable> HasItem for Holder<T> {
+    type Item = T;
+}
+
+fn main() {
+    let _ = keypath!(Outer::Inner.value);
+    let _ = keypath!(crate::Outer::Inner.shape::Circle.radius);
+    let _ = keypath!((Outer::Shape)::Circle.radius);
+    let _ = keypath!(<Holder<Outer::Inner> as HasItem>::Item.value);
+}
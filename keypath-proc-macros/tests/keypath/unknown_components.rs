@@ -0,0 +1,35 @@
+use keypath::{keypath, Keyable};
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    size: Size,
+    friends: Vec<Person>,
+    pet: Option<Pet>,
+}
+
+#[derive(Keyable)]
+struct Size {
+    height: u8,
+    width: u8,
+}
+
+#[derive(Keyable)]
+enum Pet {
+    Cat { lives: u8 },
+    Dog(String),
+}
+
+fn main() {
+    let _ = keypath!(Person.size.hieght);
+    let _ = keypath!(Person.friends[0].nmae);
+    let _ = keypath!(Person.pet?::Cat.lifes);
+    let _ = keypath!((Pet)::Dgo.0);
+    let _ = keypath!(Pet::Dog.0);
+    let _ = keypath!(Person.size.height[0]);
+    let _ = keypath!(Person.friends["coco"]);
+    let _ = keypath!(Person.size?);
+    let _ = keypath!(Person.name[{ 1 }]);
+    let _ = keypath!(Person.size[*]);
+    let _ = keypath!(Person.size.width[-1]);
+}
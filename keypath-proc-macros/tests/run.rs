@@ -4,5 +4,14 @@ fn keypath() {
     t.pass("tests/keypath/basic_structs.rs");
     t.compile_fail("tests/keypath/invalid_path_syntax.rs");
     t.pass("tests/keypath/generic.rs");
-    t.compile_fail("tests/keypath/generic_const_fail.rs");
+    t.pass("tests/keypath/generic_const.rs");
+    t.pass("tests/keypath/module_roots.rs");
+    t.compile_fail("tests/keypath/fallible_index.rs");
+    t.compile_fail("tests/keypath/illegal_index.rs");
+    t.compile_fail("tests/keypath/index_expressions.rs");
+    t.compile_fail("tests/keypath/array_index.rs");
+    t.compile_fail("tests/keypath/map_keys.rs");
+    t.compile_fail("tests/keypath/unknown_components.rs");
+    t.compile_fail("tests/keypath/field_attributes.rs");
+    t.compile_fail("tests/keypath/borrowed_lifetimes.rs");
 }
@@ -0,0 +1,615 @@
+//! Traits for collections that keypaths can index into.
+
+use std::borrow::{Borrow, Cow};
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::internals::{
+    IntegerKey, KeyedMap, Mirror, PathComponent, RawKeyable, StringKey, TypeInfo,
+};
+use super::{FieldError, FieldErrorKind, Keyable};
+
+/// A collection that keypaths index with integers, such as `[3]`.
+///
+/// This is implemented for `Vec`, `VecDeque`, arrays, and boxed, `Rc` and
+/// `Arc` slices. To
+/// make another sequence keyable, implement this trait, and derive
+/// [`Keyable`] with `#[keypath(sequence)]`; paths then index the type through
+/// this trait, rather than traversing its fields.
+///
+/// # Examples
+///
+/// ```
+/// use keypath::{Keyable, KeyableSequence, keypath};
+///
+/// #[derive(Keyable)]
+/// #[keypath(sequence)]
+/// struct Layers {
+///     items: [Layer; 4],
+///     len: usize,
+/// }
+///
+/// impl KeyableSequence for Layers {
+///     type Item = Layer;
+///
+///     fn len(&self) -> usize {
+///         self.len
+///     }
+///
+///     fn get(&self, index: usize) -> Option<&Layer> {
+///         self.items[..self.len].get(index)
+///     }
+///
+///     fn get_mut(&mut self, index: usize) -> Option<&mut Layer> {
+///         self.items[..self.len].get_mut(index)
+///     }
+///
+///     fn items_mut(&mut self) -> Box<dyn Iterator<Item = &mut Layer> + '_> {
+///         Box::new(self.items[..self.len].iter_mut())
+///     }
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Layer {
+///     opacity: u8,
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Doc {
+///     layers: Layers,
+/// }
+///
+/// let mut doc = Doc {
+///     layers: Layers {
+///         items: [
+///             Layer { opacity: 1 },
+///             Layer { opacity: 2 },
+///             Layer { opacity: 0 },
+///             Layer { opacity: 0 },
+///         ],
+///         len: 2,
+///     },
+/// };
+///
+/// let opacity = keypath!(Doc.layers[1].opacity);
+/// assert_eq!(opacity.get(&doc).unwrap(), &2);
+/// *opacity.get_mut(&mut doc).unwrap() = 5;
+/// assert!(keypath!(Doc.layers[3].opacity).get(&doc).is_err());
+///
+/// keypath!(Doc.layers[*].opacity).for_each_mut(&mut doc, |_, opacity| *opacity += 1).unwrap();
+/// assert_eq!(opacity.get(&doc).unwrap(), &6);
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a `KeyableSequence`",
+    note = "types marked `#[keypath(sequence)]` must implement `KeyableSequence`"
+)]
+pub trait KeyableSequence {
+    /// The type of the sequence's items.
+    type Item: Keyable;
+
+    /// The number of items in the sequence.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the sequence has no items.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The item at `index`, if there is one.
+    fn get(&self, index: usize) -> Option<&Self::Item>;
+
+    /// A mutable reference to the item at `index`, if there is one.
+    fn get_mut(&mut self, index: usize) -> Option<&mut Self::Item>;
+
+    /// Mutable references to all of the items, in order, so that the `n`th
+    /// item is the one at index `n`.
+    ///
+    /// This is used by wildcards, such as `[*]`, with a mutable root; with a
+    /// shared root, they visit the items with [`get`](KeyableSequence::get).
+    fn items_mut(&mut self) -> Box<dyn Iterator<Item = &mut Self::Item> + '_>;
+}
+
+/// A collection that keypaths index with keys, such as `["key"]` or `[5]`.
+///
+/// This is implemented for `HashMap` and `BTreeMap`, whose keys are any
+/// [`IndexKey`]. As with [`KeyableSequence`], other maps implement this trait
+/// and derive [`Keyable`] with `#[keypath(map)]`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a `KeyableMap`",
+    note = "types marked `#[keypath(map)]` must implement `KeyableMap`"
+)]
+pub trait KeyableMap {
+    /// The type of the map's keys.
+    type Key: IndexKey + ?Sized;
+    /// The type of the map's values.
+    type Value: Keyable;
+
+    /// The value for `key`, if there is one.
+    fn get_by_key(&self, key: &<Self::Key as IndexKey>::Lookup) -> Option<&Self::Value>;
+
+    /// A mutable reference to the value for `key`, if there is one.
+    fn get_by_key_mut(&mut self, key: &<Self::Key as IndexKey>::Lookup)
+        -> Option<&mut Self::Value>;
+
+    /// All of the map's keys and values.
+    ///
+    /// This is used by wildcards, such as `[*]`.
+    fn entries(&self) -> Box<dyn Iterator<Item = (&Self::Key, &Self::Value)> + '_>;
+
+    /// All of the map's keys, and mutable references to its values.
+    fn entries_mut(&mut self) -> Box<dyn Iterator<Item = (&Self::Key, &mut Self::Value)> + '_>;
+}
+
+/// A type that can be the key of a [`KeyableMap`], or a collection index
+/// that is determined at runtime, such as `keypath!(Person.friends[i])`.
+///
+/// This converts keys to and from [`PathComponent`]s. It is implemented for
+/// integers, which are [`IndexInt`] components, for strings, which are
+/// [`IndexStr`] components, and for `char` and `bool`. Other keys, such as
+/// enums, can be held by a [`Key`] component, which requires them to be `Ord`
+/// so that paths can be sorted.
+///
+/// [`IndexInt`]: PathComponent::IndexInt
+/// [`IndexStr`]: PathComponent::IndexStr
+/// [`Key`]: PathComponent::Key
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use std::collections::HashMap;
+///
+/// use keypath::{IndexKey, Keyable, keypath, internals::PathComponent};
+///
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// enum Suit {
+///     Hearts,
+///     Spades,
+/// }
+///
+/// impl IndexKey for Suit {
+///     type Lookup = Suit;
+///
+///     fn to_path_component(&self) -> PathComponent {
+///         PathComponent::key(*self)
+///     }
+///
+///     fn from_path_component(component: &PathComponent) -> Option<Cow<'_, Suit>> {
+///         component.key_ref().map(Cow::Borrowed)
+///     }
+/// }
+///
+/// /// A newtype whose keys are written as integers.
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// struct PlayerId(u32);
+///
+/// impl IndexKey for PlayerId {
+///     type Lookup = PlayerId;
+///
+///     fn to_path_component(&self) -> PathComponent {
+///         self.0.to_path_component()
+///     }
+///
+///     fn from_path_component(component: &PathComponent) -> Option<Cow<'_, PlayerId>> {
+///         let id = u32::from_path_component(component)?;
+///         Some(Cow::Owned(PlayerId(*id)))
+///     }
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Game {
+///     tricks: HashMap<Suit, u8>,
+///     scores: HashMap<PlayerId, u32>,
+/// }
+///
+/// let mut game = Game {
+///     tricks: HashMap::new(),
+///     scores: HashMap::new(),
+/// };
+/// game.tricks.insert(Suit::Spades, 2);
+/// game.scores.insert(PlayerId(7), 40);
+///
+/// let spades = keypath!(Game.tricks[Suit::Spades]);
+/// assert_eq!(spades.get(&game).unwrap(), &2);
+/// assert_eq!(spades.to_string(), "Game.tricks[Spades]");
+/// assert!(keypath!(Game.tricks[Suit::Hearts]).get(&game).is_err());
+///
+/// let id = PlayerId(7);
+/// *keypath!(Game.scores[id]).get_mut(&mut game).unwrap() += 2;
+/// assert_eq!(game.scores[&id], 42);
+/// ```
+pub trait IndexKey {
+    /// The type that keys are looked up by, such as `str` for `String`.
+    type Lookup: ?Sized + ToOwned;
+
+    /// The component that refers to this key.
+    fn to_path_component(&self) -> PathComponent;
+
+    /// The key that `component` refers to, if it refers to a key of this
+    /// type.
+    fn from_path_component(component: &PathComponent) -> Option<Cow<'_, Self::Lookup>>;
+}
+
+/// Implements `IndexKey` for integers; those that fit in a `usize` are
+/// `IndexInt` components, and others are `Key`s, holding an `i128`, or a
+/// `u128` if they do not fit in that.
+macro_rules! integer_keys {
+    ($($int:ty),*) => {$(
+        impl IndexKey for $int {
+            type Lookup = $int;
+
+            fn to_path_component(&self) -> PathComponent {
+                usize::try_from(*self)
+                    .map(PathComponent::IndexInt)
+                    .or_else(|_| i128::try_from(*self).map(PathComponent::key))
+                    .unwrap_or_else(|_| PathComponent::key(*self as u128))
+            }
+
+            fn from_path_component(component: &PathComponent) -> Option<Cow<'_, $int>> {
+                let int = match component {
+                    PathComponent::IndexInt(idx) => <$int>::try_from(*idx).ok(),
+                    PathComponent::Key(key) => match key.downcast_ref::<i128>() {
+                        Some(int) => <$int>::try_from(*int).ok(),
+                        None => <$int>::try_from(*key.downcast_ref::<u128>()?).ok(),
+                    },
+                    _ => None,
+                };
+                int.map(Cow::Owned)
+            }
+        }
+
+        impl IntegerKey for $int {
+            const MAX: u128 = <$int>::MAX as u128;
+        }
+    )*};
+}
+
+integer_keys!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Implements `IndexKey` for types that borrow as `str`.
+macro_rules! string_keys {
+    ($($ty:ty),*) => {$(
+        impl IndexKey for $ty {
+            type Lookup = str;
+
+            fn to_path_component(&self) -> PathComponent {
+                PathComponent::index_str(Borrow::<str>::borrow(self).to_owned())
+            }
+
+            fn from_path_component(component: &PathComponent) -> Option<Cow<'_, str>> {
+                match component {
+                    PathComponent::IndexStr(key) => Some(Cow::Borrowed(&**key)),
+                    _ => None,
+                }
+            }
+        }
+
+        impl StringKey for $ty {}
+    )*};
+}
+
+string_keys!(str, String, Box<str>, Rc<str>, Arc<str>, Cow<'_, str>);
+
+/// Implements `IndexKey` for types that are held by `Key` components.
+macro_rules! any_keys {
+    ($($ty:ty),*) => {$(
+        impl IndexKey for $ty {
+            type Lookup = $ty;
+
+            fn to_path_component(&self) -> PathComponent {
+                PathComponent::key(*self)
+            }
+
+            fn from_path_component(component: &PathComponent) -> Option<Cow<'_, $ty>> {
+                component.key_ref().map(Cow::Borrowed)
+            }
+        }
+    )*};
+}
+
+any_keys!(bool, char);
+
+impl<K: IndexKey + ?Sized> IndexKey for &K {
+    type Lookup = K::Lookup;
+
+    fn to_path_component(&self) -> PathComponent {
+        (**self).to_path_component()
+    }
+
+    fn from_path_component(component: &PathComponent) -> Option<Cow<'_, K::Lookup>> {
+        K::from_path_component(component)
+    }
+}
+
+impl<K: IntegerKey + ?Sized> IntegerKey for &K {
+    const MAX: u128 = K::MAX;
+}
+
+impl<K: StringKey + ?Sized> StringKey for &K {}
+
+/// The mirror of a [`KeyableSequence`] `S`.
+pub struct SequenceMirror<S>(PhantomData<S>);
+
+impl<S> SequenceMirror<S> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        SequenceMirror(PhantomData)
+    }
+
+    pub const fn value_type(&self) -> PhantomData<S> {
+        PhantomData
+    }
+}
+
+impl<S> Mirror for SequenceMirror<S> {
+    type Value = S;
+}
+
+impl<S: KeyableSequence> SequenceMirror<S> {
+    /// Used for literal indices, such as `[2]`, which is `IDX`.
+    pub fn sequence_get<const IDX: usize>(&self) -> <S::Item as Keyable>::Mirror {
+        S::Item::mirror()
+    }
+
+    /// Used for indices that are determined at runtime; this checks that the
+    /// index is the correct type.
+    pub fn index_get(&self, _idx: &usize) -> <S::Item as Keyable>::Mirror {
+        S::Item::mirror()
+    }
+
+    /// Used for literals that can only be map keys, such as `[-1]`; the bound
+    /// is never satisfied, and only exists to produce a helpful error.
+    pub fn key_get<K: ?Sized>(&self, _key: &K) -> <S::Item as Keyable>::Mirror
+    where
+        S: KeyedMap,
+    {
+        S::Item::mirror()
+    }
+
+    /// Used for wildcards, `[*]`, which match every item.
+    pub fn wildcard_get(&self) -> <S::Item as Keyable>::Mirror {
+        S::Item::mirror()
+    }
+}
+
+/// The mirror of a [`KeyableMap`] `M`.
+pub struct MapMirror<M>(PhantomData<M>);
+
+impl<M> MapMirror<M> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        MapMirror(PhantomData)
+    }
+
+    pub const fn value_type(&self) -> PhantomData<M> {
+        PhantomData
+    }
+}
+
+impl<M> Mirror for MapMirror<M> {
+    type Value = M;
+}
+
+impl<M: KeyableMap> MapMirror<M> {
+    /// Used for integer literals, such as `[5]`, which is `IDX`; this checks
+    /// that the map's keys are integers that `IDX` fits in.
+    pub fn sequence_get<const IDX: usize>(&self) -> <M::Value as Keyable>::Mirror
+    where
+        M::Key: IntegerKey,
+    {
+        #[allow(clippy::let_unit_value)]
+        let () = IntegerIndex::<M::Key, IDX>::IN_RANGE;
+        M::Value::mirror()
+    }
+
+    /// Used for string literals, such as `["key"]`; this checks that the
+    /// map's keys are strings.
+    pub fn map_get(&self) -> <M::Value as Keyable>::Mirror
+    where
+        M::Key: StringKey,
+    {
+        M::Value::mirror()
+    }
+
+    /// Used for keys that are determined at runtime; this checks that the
+    /// key is the correct type.
+    pub fn index_get(&self, _key: &<M::Key as IndexKey>::Lookup) -> <M::Value as Keyable>::Mirror {
+        M::Value::mirror()
+    }
+
+    /// Used for literals that can only be keys, such as `[-1]`, `[5_u8]` or
+    /// `['a']`; this checks that the key is the correct type.
+    pub fn key_get(&self, _key: &<M::Key as IndexKey>::Lookup) -> <M::Value as Keyable>::Mirror {
+        M::Value::mirror()
+    }
+
+    /// Used for wildcards, `[*]`, which match every value.
+    pub fn wildcard_get(&self) -> <M::Value as Keyable>::Mirror {
+        M::Value::mirror()
+    }
+}
+
+/// An integer literal used as a key into a map with integer keys `K`.
+struct IntegerIndex<K: ?Sized, const IDX: usize>(PhantomData<K>);
+
+impl<K: IntegerKey + ?Sized, const IDX: usize> IntegerIndex<K, IDX> {
+    /// Fails to evaluate if the literal is too large for `K`, in the same way
+    /// as the bounds check on array indices.
+    const IN_RANGE: () = assert!(
+        IDX as u128 <= K::MAX,
+        "keypath index is too large for the map's keys"
+    );
+}
+
+// The `RawKeyable` and `Keyable` methods of sequences and maps, which the
+// impls for std types and `#[keypath(sequence)]` and `#[keypath(map)]` types
+// delegate to.
+
+pub fn sequence_get_field<'a, S>(
+    seq: &'a S,
+    ident: &[PathComponent],
+) -> Result<&'a dyn RawKeyable, FieldError>
+where
+    S: KeyableSequence + RawKeyable,
+{
+    match ident.split_first() {
+        None => Ok(seq),
+        Some((PathComponent::IndexInt(idx), rest)) => seq
+            .get(*idx)
+            .ok_or_else(|| {
+                FieldErrorKind::IndexOutOfRange {
+                    index: *idx,
+                    len: seq.len(),
+                }
+                .into_error_for::<S>(rest.len())
+            })
+            .and_then(|t| t.get_field(rest)),
+        Some((field, rest)) => {
+            Err(FieldErrorKind::InvalidField(field.clone()).into_error_for::<S>(rest.len()))
+        }
+    }
+}
+
+pub fn sequence_get_field_mut<'a, S>(
+    seq: &'a mut S,
+    ident: &[PathComponent],
+) -> Result<&'a mut dyn RawKeyable, FieldError>
+where
+    S: KeyableSequence + RawKeyable,
+{
+    match ident.split_first() {
+        None => Ok(seq),
+        Some((PathComponent::IndexInt(idx), rest)) => {
+            let len = seq.len();
+            seq.get_mut(*idx)
+                .ok_or_else(|| {
+                    FieldErrorKind::IndexOutOfRange { index: *idx, len }
+                        .into_error_for::<S>(rest.len())
+                })
+                .and_then(|t| t.get_field_mut(rest))
+        }
+        Some((field, rest)) => {
+            Err(FieldErrorKind::InvalidField(field.clone()).into_error_for::<S>(rest.len()))
+        }
+    }
+}
+
+pub fn sequence_for_each_item<'a, S: KeyableSequence>(
+    seq: &'a S,
+    f: &mut dyn FnMut(PathComponent, &'a dyn RawKeyable),
+) {
+    for idx in 0..seq.len() {
+        if let Some(t) = seq.get(idx) {
+            f(PathComponent::IndexInt(idx), t);
+        }
+    }
+}
+
+pub fn sequence_for_each_item_mut<'a, S: KeyableSequence>(
+    seq: &'a mut S,
+    f: &mut dyn FnMut(PathComponent, &'a mut dyn RawKeyable),
+) -> Result<(), FieldError> {
+    for (idx, t) in seq.items_mut().enumerate() {
+        f(PathComponent::IndexInt(idx), t);
+    }
+    Ok(())
+}
+
+pub fn sequence_type_at_path<S: KeyableSequence>(
+    path: &[PathComponent],
+) -> Result<TypeInfo, FieldError> {
+    match path.split_first() {
+        None => Ok(TypeInfo::of::<S>()),
+        Some((PathComponent::IndexInt(_), rest)) | Some((PathComponent::Wildcard, rest)) => {
+            S::Item::type_at_path(rest)
+        }
+        Some((field, rest)) => {
+            Err(FieldErrorKind::InvalidField(field.clone()).into_error_for::<S>(rest.len()))
+        }
+    }
+}
+
+pub fn map_get_field<'a, M>(
+    map: &'a M,
+    ident: &[PathComponent],
+) -> Result<&'a dyn RawKeyable, FieldError>
+where
+    M: KeyableMap + RawKeyable,
+{
+    match ident.split_first() {
+        None => Ok(map),
+        Some((component, rest)) => match M::Key::from_path_component(component) {
+            Some(key) => map
+                .get_by_key(&key)
+                .ok_or_else(|| missing_key::<M>(component, rest.len()))
+                .and_then(|t| t.get_field(rest)),
+            None => {
+                Err(FieldErrorKind::InvalidField(component.clone()).into_error_for::<M>(rest.len()))
+            }
+        },
+    }
+}
+
+pub fn map_get_field_mut<'a, M>(
+    map: &'a mut M,
+    ident: &[PathComponent],
+) -> Result<&'a mut dyn RawKeyable, FieldError>
+where
+    M: KeyableMap + RawKeyable,
+{
+    match ident.split_first() {
+        None => Ok(map),
+        Some((component, rest)) => match M::Key::from_path_component(component) {
+            Some(key) => map
+                .get_by_key_mut(&key)
+                .ok_or_else(|| missing_key::<M>(component, rest.len()))
+                .and_then(|t| t.get_field_mut(rest)),
+            None => {
+                Err(FieldErrorKind::InvalidField(component.clone()).into_error_for::<M>(rest.len()))
+            }
+        },
+    }
+}
+
+/// The error for a key that is not in the map `M`.
+fn missing_key<M>(component: &PathComponent, depth: usize) -> FieldError {
+    let kind = match component {
+        PathComponent::IndexStr(key) => FieldErrorKind::MissinngKey(key.to_string()),
+        other => FieldErrorKind::MissingTypedKey(other.clone()),
+    };
+    kind.into_error_for::<M>(depth)
+}
+
+pub fn map_for_each_item<'a, M: KeyableMap>(
+    map: &'a M,
+    f: &mut dyn FnMut(PathComponent, &'a dyn RawKeyable),
+) {
+    for (key, t) in map.entries() {
+        f(key.to_path_component(), t);
+    }
+}
+
+pub fn map_for_each_item_mut<'a, M: KeyableMap>(
+    map: &'a mut M,
+    f: &mut dyn FnMut(PathComponent, &'a mut dyn RawKeyable),
+) -> Result<(), FieldError> {
+    for (key, t) in map.entries_mut() {
+        f(key.to_path_component(), t);
+    }
+    Ok(())
+}
+
+pub fn map_type_at_path<M: KeyableMap>(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+    match path.split_first() {
+        None => Ok(TypeInfo::of::<M>()),
+        Some((PathComponent::Wildcard, rest)) => M::Value::type_at_path(rest),
+        Some((component, rest)) if M::Key::from_path_component(component).is_some() => {
+            M::Value::type_at_path(rest)
+        }
+        Some((field, rest)) => {
+            Err(FieldErrorKind::InvalidField(field.clone()).into_error_for::<M>(rest.len()))
+        }
+    }
+}
@@ -1,32 +1,304 @@
-use super::internals::PathComponent;
+use std::fmt;
+
+use super::internals::{PathComponent, TypeInfo};
 
 #[derive(Debug, Clone)]
 pub enum FieldErrorKind {
-    IndexOutOfRange(usize),
+    /// An index into a sequence is past the end of the sequence.
+    IndexOutOfRange {
+        index: usize,
+        len: usize,
+    },
     MissinngKey(String),
+    /// A map has no value for a key that is not a string, such as an
+    /// integer; this holds the component for the key.
+    MissingTypedKey(PathComponent),
     InvalidField(PathComponent),
+    /// The path refers to an enum variant that is not the active variant.
+    VariantMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The path traverses an `Option` that is `None`.
+    NoneValue,
+    /// The value at the end of the path is not of the expected type.
+    ///
+    /// This can only happen with paths that were not created by the
+    /// [`keypath!`](crate::keypath) macro, such as those built from
+    /// [`PathComponent`]s at runtime.
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The path continues into a type whose contents can only be borrowed
+    /// within a scope, such as a `RefCell`, and so needs closure-based access
+    /// such as [`Keyable::with_any_at_path`](crate::Keyable::with_any_at_path).
+    ScopedContents,
+    /// A `RefCell` is already mutably borrowed.
+    AlreadyBorrowed,
+    /// A `Mutex` or `RwLock` is poisoned.
+    Poisoned,
+    /// The path mutates through an `Rc` or `Arc` that is shared.
+    SharedPointer,
 }
 
+/// An error that occurs when traversing a keypath.
+///
+/// When returned from a method that takes a complete path, such as
+/// [`FallibleKeyPath::get`], this includes the full path that was attempted,
+/// and the `Display` impl describes where it failed:
+///
+/// ```
+/// use keypath::{Keyable, internals::PathComponent};
+///
+/// #[derive(Keyable)]
+/// struct Person {
+///     size: Size,
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Size {
+///     height: u8,
+/// }
+///
+/// let person = Person { size: Size { height: 45 } };
+/// let path = [PathComponent::named("size"), PathComponent::named("hieght")];
+/// let err = person.try_any_at_path(&path).unwrap_err();
+///
+/// assert_eq!(err.failed_index(), Some(1));
+/// assert_eq!(err.to_string(), "no field 'hieght' on Size at Person.size.hieght");
+/// ```
+///
+/// [`FallibleKeyPath::get`]: crate::FallibleKeyPath::get
 #[derive(Debug, Clone)]
 pub struct FieldError {
     pub(crate) kind: FieldErrorKind,
     pub(crate) type_name: &'static str,
     // the number of *remaining* fields at which the error occured
     pub(crate) depth: usize,
+    // the full path and its root type, if known
+    pub(crate) path: Vec<PathComponent>,
+    pub(crate) root_type_name: Option<&'static str>,
+}
+
+impl FieldError {
+    /// The kind of error that occured.
+    pub fn kind(&self) -> &FieldErrorKind {
+        &self.kind
+    }
+
+    /// The name of the type on which the error occured.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The number of path components remaining after the one that failed.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The full path that was being traversed, if known.
+    ///
+    /// This is empty if the error was not returned from a method that takes
+    /// a complete path.
+    pub fn path(&self) -> &[PathComponent] {
+        &self.path
+    }
+
+    /// The name of the type at the root of the path, if known.
+    pub fn root_type_name(&self) -> Option<&'static str> {
+        self.root_type_name
+    }
+
+    /// The index in [`path`] of the component that failed, if known.
+    ///
+    /// [`path`]: FieldError::path
+    pub fn failed_index(&self) -> Option<usize> {
+        self.path.len().checked_sub(self.depth + 1)
+    }
+
+    /// Record the full path that was being traversed from the root type `Root`.
+    pub(crate) fn with_path<Root: ?Sized>(mut self, path: &[PathComponent]) -> Self {
+        self.path = path.to_vec();
+        self.root_type_name = Some(std::any::type_name::<Root>());
+        self
+    }
 }
 
-impl std::fmt::Display for FieldError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "")
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let type_name = short_type_name(self.type_name);
+        match &self.kind {
+            FieldErrorKind::IndexOutOfRange { index, len } => write!(
+                f,
+                "index {} is out of range for {} of length {}",
+                index, type_name, len
+            )?,
+            FieldErrorKind::MissinngKey(key) => write!(f, "no key {:?} in {}", key, type_name)?,
+            FieldErrorKind::MissingTypedKey(component) => match component {
+                PathComponent::IndexInt(key) => write!(f, "no key {} in {}", key, type_name)?,
+                PathComponent::Key(key) => write!(f, "no key {:?} in {}", key, type_name)?,
+                other => write!(f, "no key {} in {}", other, type_name)?,
+            },
+            FieldErrorKind::InvalidField(component) => match component {
+                PathComponent::Named(name) => write!(f, "no field '{}' on {}", name, type_name)?,
+                PathComponent::Unnamed(idx) => write!(f, "no field '{}' on {}", idx, type_name)?,
+                PathComponent::Variant(name) => {
+                    write!(f, "no variant '{}' on {}", name, type_name)?
+                }
+                PathComponent::Optional => write!(f, "{} is not an Option", type_name)?,
+                PathComponent::IndexInt(_)
+                | PathComponent::IndexStr(_)
+                | PathComponent::Key(_)
+                | PathComponent::Wildcard => {
+                    write!(f, "{} cannot be indexed with '{}'", type_name, component)?
+                }
+            },
+            FieldErrorKind::VariantMismatch { expected, found } => write!(
+                f,
+                "expected {}::{}, found {}::{}",
+                type_name, expected, type_name, found
+            )?,
+            FieldErrorKind::NoneValue => write!(f, "{} is None", type_name)?,
+            FieldErrorKind::TypeMismatch { expected, found } => write!(
+                f,
+                "expected a value of type {}, found {}",
+                short_type_name(expected),
+                short_type_name(found)
+            )?,
+            FieldErrorKind::ScopedContents => write!(
+                f,
+                "the contents of {} can only be borrowed within a closure",
+                type_name
+            )?,
+            FieldErrorKind::AlreadyBorrowed => {
+                write!(f, "{} is already mutably borrowed", type_name)?
+            }
+            FieldErrorKind::Poisoned => write!(f, "{} is poisoned", type_name)?,
+            FieldErrorKind::SharedPointer => write!(
+                f,
+                "cannot mutate through {}, because it is shared",
+                type_name
+            )?,
+        }
+
+        if let Some(root) = self.root_type_name {
+            f.write_str(" at ")?;
+            write_path(f, root, &self.path)?;
+        }
+        Ok(())
     }
 }
 
+impl std::error::Error for FieldError {}
+
 impl FieldErrorKind {
     pub fn into_error<T>(self, _source: &T, depth: usize) -> FieldError {
+        self.into_error_for::<T>(depth)
+    }
+
+    /// Create an error for the type `T`, when there is no instance of it.
+    pub fn into_error_for<T: ?Sized>(self, depth: usize) -> FieldError {
         FieldError {
             kind: self,
             type_name: std::any::type_name::<T>(),
             depth,
+            path: Vec::new(),
+            root_type_name: None,
+        }
+    }
+}
+
+/// Write a path in the syntax of the `keypath!` macro, such as
+/// `Person.friends[0].name`.
+pub(crate) fn write_path(
+    f: &mut fmt::Formatter,
+    root_type_name: &str,
+    path: &[PathComponent],
+) -> fmt::Result {
+    f.write_str(&short_type_name(root_type_name))?;
+    for component in path {
+        write!(f, "{}", component)?;
+    }
+    Ok(())
+}
+
+/// Remove the module paths from a type name, such as one returned by
+/// [`std::any::type_name`]: `alloc::vec::Vec<my_crate::Person>` becomes
+/// `Vec<Person>`.
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    // the start of the current path segment in `result`
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            result.truncate(segment_start);
+        } else {
+            result.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = result.len();
+            }
+        }
+    }
+    result
+}
+
+/// An error that occurs when parsing a keypath from a string.
+#[derive(Debug, Clone)]
+pub enum ParseError {
+    /// The string is not a valid keypath.
+    Syntax {
+        /// The byte offset in the string at which the error occured.
+        position: usize,
+        message: &'static str,
+    },
+    /// A component of the path does not exist on the type it is applied to.
+    InvalidPath(FieldError),
+    /// The path is valid, but resolves to a different type than expected.
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The path contains components that may fail at runtime, and cannot
+    /// be used as a [`KeyPath`](crate::KeyPath).
+    Fallible,
+    /// The path contains a wildcard, and can only be used as a
+    /// [`WildcardKeyPath`](crate::WildcardKeyPath).
+    Wildcard,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Syntax { position, message } => {
+                write!(f, "invalid keypath at position {}: {}", position, message)
+            }
+            ParseError::InvalidPath(err) => write!(f, "invalid keypath: {}", err),
+            ParseError::WrongType { expected, found } => {
+                write!(f, "keypath resolves to {}, expected {}", found, expected)
+            }
+            ParseError::Fallible => {
+                f.write_str("keypath may fail at runtime and must be parsed as a FallibleKeyPath")
+            }
+            ParseError::Wildcard => {
+                f.write_str("keypath contains a wildcard and must be parsed as a WildcardKeyPath")
+            }
         }
     }
 }
+
+impl std::error::Error for ParseError {}
+
+/// Check that a parsed path resolves to a value of type `T`.
+pub(crate) fn check_value_type<T: 'static>(value: TypeInfo) -> Result<(), ParseError> {
+    if value.is::<T>() {
+        Ok(())
+    } else {
+        Err(ParseError::WrongType {
+            expected: std::any::type_name::<T>(),
+            found: value.type_name(),
+        })
+    }
+}
@@ -0,0 +1,232 @@
+//! Keypaths that may fail to resolve.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use super::internals::{self, PathComponent, RawKeyable, TypeInfo};
+use super::{error, FieldError, KeyPath, Keyable, ParseError, PartialKeyPath};
+
+/// A keypath that may fail to resolve.
+///
+/// This is created by the [`keypath!`] macro when a path includes a component
+/// that can fail at runtime: an index into a collection, such as
+/// `Person.friends[0]`, an enum variant, such as `Shape::Circle.radius`, or an
+/// optional, such as `Person.spouse?.name`. Unlike a [`KeyPath`], values
+/// can only be accessed through methods that return a `Result`.
+///
+/// # Examples
+///
+/// ```
+/// use keypath::{Keyable, FallibleKeyPath, keypath};
+///
+/// #[derive(Keyable, Default)]
+/// struct Person {
+///     name: String,
+///     pet: Option<Pet>,
+/// }
+///
+/// #[derive(Keyable, Default)]
+/// struct Pet {
+///     name: String,
+/// }
+///
+/// let mut person = Person { name: "coco".into(), pet: None };
+/// let pet_name: FallibleKeyPath<Person, String> = keypath!(Person.pet?.name);
+///
+/// assert!(pet_name.get(&person).is_err());
+///
+/// *pet_name.get_mut_or_insert_default(&mut person).unwrap() = "jojo".into();
+/// assert_eq!(pet_name.get(&person).unwrap(), "jojo");
+/// ```
+pub struct FallibleKeyPath<Root: ?Sized, Value> {
+    pub(crate) partial: PartialKeyPath<Root>,
+    pub(crate) _value: PhantomData<fn(Value) -> Value>,
+}
+
+impl<Root: Keyable, Value> FallibleKeyPath<Root, Value> {
+    /// Create a new typed `FallibleKeyPath` from the provided fields.
+    ///
+    /// This method does not ensure the path is valid; it is intended
+    /// to be called after a path has been type-checked, presumably in the
+    /// context of a proc_macro. As with [`KeyPath`], the value type is
+    /// inferred from a closure over the root's mirror, which is never called.
+    ///
+    /// # Safety
+    ///
+    /// The same as for `KeyPath::__conjure_from_abyss`.
+    #[doc(hidden)]
+    pub const unsafe fn __conjure_from_abyss<F>(value: F, fields: &'static [PathComponent]) -> Self
+    where
+        F: FnOnce(Root::Mirror) -> PhantomData<Value>,
+    {
+        internals::forget_mirror_closure(value);
+        FallibleKeyPath {
+            partial: PartialKeyPath {
+                fields: Cow::Borrowed(fields),
+                value: TypeInfo::of::<Value>(),
+                _root: PhantomData,
+            },
+            _value: PhantomData,
+        }
+    }
+
+    /// Create a new typed `FallibleKeyPath` from fields that are only known
+    /// at runtime, such as when the path contains an index variable.
+    ///
+    /// As with `__conjure_from_abyss`, this does not ensure the path is valid.
+    ///
+    /// # Safety
+    ///
+    /// The same as for `KeyPath::__conjure_from_abyss`.
+    #[doc(hidden)]
+    pub unsafe fn __conjure_from_abyss_owned<F>(value: F, fields: Vec<PathComponent>) -> Self
+    where
+        F: FnOnce(Root::Mirror) -> PhantomData<Value>,
+    {
+        internals::forget_mirror_closure(value);
+        FallibleKeyPath {
+            partial: PartialKeyPath {
+                fields: Cow::Owned(fields),
+                value: TypeInfo::of::<Value>(),
+                _root: PhantomData,
+            },
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<Root, Value> FallibleKeyPath<Root, Value> {
+    /// Create a new `FallibleKeyPath` by combining two routes.
+    ///
+    /// The final type of the first route must be the first type of the second
+    /// route.
+    pub fn append<T>(&self, other: &FallibleKeyPath<Value, T>) -> FallibleKeyPath<Root, T> {
+        let mut partial = self.partial.clone();
+        partial
+            .fields
+            .to_mut()
+            .extend(other.partial.fields.iter().cloned());
+        partial.value = other.partial.value;
+        FallibleKeyPath {
+            partial,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<Root: Keyable + 'static, Value: 'static> FallibleKeyPath<Root, Value> {
+    /// Parse a `FallibleKeyPath` from a string, checking it against the root type.
+    ///
+    /// The syntax is the same as for the [`keypath!`] macro, without the root
+    /// type. Indices must be literals: integers, which may be negative or have a
+    /// type suffix such as `5_u8`, strings, byte strings, characters, bytes,
+    /// `true` and `false`. Keys that are expressions in the macro, such as
+    /// enum variants, can't be parsed. The path must resolve to a value of
+    /// type `Value`, and paths with wildcards should be parsed with
+    /// [`WildcardKeyPath::parse`](crate::WildcardKeyPath::parse). As with
+    /// [`KeyPath::parse`], both types must be `'static`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use keypath::{Keyable, FallibleKeyPath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Config {
+    ///     servers: HashMap<String, Server>,
+    /// }
+    ///
+    /// #[derive(Keyable)]
+    /// struct Server {
+    ///     ports: Vec<u16>,
+    /// }
+    ///
+    /// let mut servers = HashMap::new();
+    /// servers.insert("primary".to_string(), Server { ports: vec![80, 443] });
+    /// let config = Config { servers };
+    ///
+    /// let port = FallibleKeyPath::<Config, u16>::parse(r#"servers["primary"].ports[1]"#).unwrap();
+    /// assert_eq!(port.get(&config).unwrap(), &443);
+    /// ```
+    ///
+    /// [`keypath!`]: crate::keypath
+    pub fn parse(path: &str) -> Result<Self, ParseError> {
+        let partial = PartialKeyPath::parse(path)?;
+        if partial.fields.contains(&PathComponent::Wildcard) {
+            return Err(ParseError::Wildcard);
+        }
+        error::check_value_type::<Value>(partial.value)?;
+        Ok(FallibleKeyPath {
+            partial,
+            _value: PhantomData,
+        })
+    }
+}
+
+impl<Root: Keyable, Value> FallibleKeyPath<Root, Value> {
+    /// Attempt to get a reference to the value at this path.
+    pub fn get<'a>(&self, root: &'a Root) -> Result<&'a Value, FieldError> {
+        root.get_field(self.as_ref())
+            // SAFETY: the value type of a typed keypath has the root's lifetimes
+            .and_then(|value| unsafe { value.downcast_ref() })
+            .map_err(|e| e.with_path::<Root>(self.as_ref()))
+    }
+
+    /// Attempt to get a mutable reference to the value at this path.
+    pub fn get_mut<'a>(&self, root: &'a mut Root) -> Result<&'a mut Value, FieldError> {
+        root.get_field_mut(self.as_ref())
+            // SAFETY: the value type of a typed keypath has the root's lifetimes
+            .and_then(|value| unsafe { value.downcast_mut() })
+            .map_err(|e| e.with_path::<Root>(self.as_ref()))
+    }
+
+    /// Attempt to get a mutable reference to the value at this path,
+    /// inserting default values for any `Option`s along the path that
+    /// are `None`.
+    ///
+    /// This only works if the type inside the `Option` has a known default
+    /// value; for derived types, this means that the type implements `Default`,
+    /// and, if it has type parameters, is marked `#[keypath(default)]`.
+    /// If there is no default, this returns an error, the same as [`get_mut`].
+    ///
+    /// [`get_mut`]: FallibleKeyPath::get_mut
+    pub fn get_mut_or_insert_default<'a>(
+        &self,
+        root: &'a mut Root,
+    ) -> Result<&'a mut Value, FieldError> {
+        let path = self.partial.fields.as_ref();
+        let mut node: &mut dyn RawKeyable = root;
+        let mut start = 0;
+        for (i, component) in path.iter().enumerate() {
+            if matches!(component, PathComponent::Optional) {
+                // the remaining depth is relative to the subpath; fix it up
+                node = node.get_field_mut(&path[start..i]).map_err(|mut e| {
+                    e.depth += path.len() - i;
+                    e.with_path::<Root>(path)
+                })?;
+                node.insert_default_if_none();
+                start = i;
+            }
+        }
+        node.get_field_mut(&path[start..])
+            // SAFETY: as for `get_mut`
+            .and_then(|value| unsafe { value.downcast_mut() })
+            .map_err(|e| e.with_path::<Root>(path))
+    }
+}
+
+impl<Root, Value> From<KeyPath<Root, Value>> for FallibleKeyPath<Root, Value> {
+    fn from(src: KeyPath<Root, Value>) -> Self {
+        FallibleKeyPath {
+            partial: src.partial,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<Root: ?Sized, Value> AsRef<[PathComponent]> for FallibleKeyPath<Root, Value> {
+    fn as_ref(&self) -> &[PathComponent] {
+        self.partial.fields.as_ref()
+    }
+}
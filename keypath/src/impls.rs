@@ -1,37 +1,206 @@
 //! trait impls for std types
 
 use std::any::Any;
-use std::collections::HashMap;
+use std::borrow::{Borrow, Cow};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hash;
 use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
 
-use super::internals::{PathComponent, RawKeyable};
-use super::{FieldError, FieldErrorKind, KeyPath, Keyable};
+use super::collections::{self, MapMirror, SequenceMirror};
+use super::internals::{KeyedMap, Mirror, PathComponent, RawKeyable, TypeInfo};
+use super::{FieldError, FieldErrorKind, IndexKey, Keyable, KeyableMap, KeyableSequence};
+
+/// Implement [`Keyable::KeyPaths`] and [`Keyable::keypaths_from`] for a type
+/// without typed accessors, whose accessor is just its keypath.
+///
+/// This is for hand-written `Keyable` impls, such as those of leaf types,
+/// which would otherwise have to spell these items out.
+///
+/// # Examples
+///
+/// ```
+/// use keypath::internals::{Leaf, PathComponent, RawKeyable};
+/// use keypath::{keypath, FieldError, FieldErrorKind, Keyable};
+///
+/// struct Celsius(f32);
+///
+/// // SAFETY: a `Celsius` has no fields, and only returns itself
+/// unsafe impl RawKeyable for Celsius {
+///     fn as_any(&self) -> &dyn std::any::Any {
+///         self
+///     }
+///
+///     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+///         self
+///     }
+///
+///     fn get_field(&self, path: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
+///         match path.split_first() {
+///             None => Ok(self),
+///             Some((head, rest)) => {
+///                 Err(FieldErrorKind::InvalidField(head.clone()).into_error(self, rest.len()))
+///             }
+///         }
+///     }
+///
+///     fn get_field_mut(
+///         &mut self,
+///         path: &[PathComponent],
+///     ) -> Result<&mut dyn RawKeyable, FieldError> {
+///         match path.split_first() {
+///             None => Ok(self),
+///             Some((head, rest)) => {
+///                 Err(FieldErrorKind::InvalidField(head.clone()).into_error(self, rest.len()))
+///             }
+///         }
+///     }
+/// }
+///
+/// impl Keyable for Celsius {
+///     type Mirror = Leaf<Celsius>;
+///     const MIRROR: Leaf<Celsius> = Leaf::new();
+///     keypath::keypaths_are_path!();
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Room {
+///     temperature: Celsius,
+/// }
+///
+/// let room = Room { temperature: Celsius(21.5) };
+/// assert_eq!(room[&keypath!(Room.temperature)].0, 21.5);
+/// assert_eq!(room[&Room::keypaths().temperature].0, 21.5);
+/// ```
+#[macro_export]
+macro_rules! keypaths_are_path {
+    () => {
+        type KeyPaths<Root> = $crate::KeyPath<Root, Self>;
+
+        fn keypaths_from<Root>(path: $crate::KeyPath<Root, Self>) -> $crate::KeyPath<Root, Self> {
+            path
+        }
+    };
+}
 
 pub struct Leaf<T> {
     _type: PhantomData<T>,
 }
 
 impl<T> Leaf<T> {
+    #[allow(clippy::new_without_default)]
     pub const fn new() -> Self {
         Leaf { _type: PhantomData }
     }
 
-    pub const fn to_key_path_with_root<Root>(
-        &self,
-        fields: &'static [PathComponent],
-    ) -> KeyPath<Root, T> {
-        KeyPath::__conjure_from_abyss(fields)
+    pub const fn value_type(&self) -> PhantomData<T> {
+        PhantomData
+    }
+}
+
+impl<T> Mirror for Leaf<T> {
+    type Value = T;
+}
+
+/// A wrapper for fields marked `#[keypath(opaque)]`, which are treated as
+/// leaves without requiring their type to implement `Keyable`.
+///
+/// Derived code borrows the field as an `Opaque`; paths that end at the field
+/// resolve to the field's own type.
+#[repr(transparent)]
+pub struct Opaque<T>(T);
+
+impl<T> Opaque<T> {
+    pub fn from_ref(value: &T) -> &Self {
+        // SAFETY: `Opaque` is `repr(transparent)`, so it has the same layout as `T`.
+        unsafe { &*(value as *const T as *const Opaque<T>) }
+    }
+
+    pub fn from_mut(value: &mut T) -> &mut Self {
+        // SAFETY: `Opaque` is `repr(transparent)`, so it has the same layout as `T`.
+        unsafe { &mut *(value as *mut T as *mut Opaque<T>) }
+    }
+}
+
+// SAFETY: `Opaque` reports the type of `T`, which it has the layout of
+unsafe impl<T> RawKeyable for Opaque<T> {
+    fn as_any(&self) -> &dyn Any
+    where
+        Self: 'static,
+    {
+        &self.0
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    where
+        Self: 'static,
+    {
+        &mut self.0
+    }
+
+    fn type_info(&self) -> TypeInfo {
+        TypeInfo::of::<T>()
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
+        match ident.split_first() {
+            None => Ok(self),
+            Some((head, rest)) => {
+                Err(FieldErrorKind::InvalidField(head.to_owned()).into_error_for::<T>(rest.len()))
+            }
+        }
+    }
+
+    fn get_field_mut(
+        &mut self,
+        ident: &[PathComponent],
+    ) -> Result<&mut dyn RawKeyable, FieldError> {
+        match ident.split_first() {
+            None => Ok(self),
+            Some((head, rest)) => {
+                Err(FieldErrorKind::InvalidField(head.to_owned()).into_error_for::<T>(rest.len()))
+            }
+        }
     }
 }
 
+impl<T> Keyable for Opaque<T> {
+    type Mirror = Leaf<T>;
+    const MIRROR: Leaf<T> = Leaf::new();
+    keypaths_are_path!();
+
+    fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+        match path.split_first() {
+            None => Ok(TypeInfo::of::<T>()),
+            Some((head, rest)) => {
+                Err(FieldErrorKind::InvalidField(head.clone()).into_error_for::<T>(rest.len()))
+            }
+        }
+    }
+}
+
+/// Implements `Keyable` for a leaf type, optionally given its generic params.
 macro_rules! keyable_leaf {
-    ($name:ty) => {
-        impl RawKeyable for $name {
-            fn as_any(&self) -> &dyn Any {
+    ([$($params:tt)*] $name:ty) => {
+        // SAFETY: leaves only return themselves
+        unsafe impl<$($params)*> RawKeyable for $name {
+            fn as_any(&self) -> &dyn Any
+            where
+                Self: 'static,
+            {
                 self
             }
 
-            fn as_any_mut(&mut self) -> &mut dyn Any {
+            fn as_any_mut(&mut self) -> &mut dyn Any
+            where
+                Self: 'static,
+            {
                 self
             }
 
@@ -59,13 +228,19 @@ macro_rules! keyable_leaf {
             }
         }
 
-        impl Keyable for $name {
+        impl<$($params)*> Keyable for $name {
             type Mirror = Leaf<$name>;
-            fn mirror() -> Leaf<$name> {
-                Leaf { _type: PhantomData }
+            const MIRROR: Leaf<$name> = Leaf::new();
+            keypaths_are_path!();
+
+            fn default_value() -> Option<Self> {
+                Some(Default::default())
             }
         }
     };
+    ($name:ty) => {
+        keyable_leaf!([] $name);
+    };
 }
 
 keyable_leaf!(bool);
@@ -89,6 +264,9 @@ keyable_leaf!(f32);
 keyable_leaf!(f64);
 
 keyable_leaf!(String);
+// borrowed strings are leaves, like `String`; the `Cow` impl for pointers
+// needs a `Clone` type to hold.
+keyable_leaf!(['a] Cow<'a, str>);
 
 macro_rules! tuple_impls {
     ($(
@@ -97,11 +275,18 @@ macro_rules! tuple_impls {
         }
     )+) => {
         $(
-            impl<$($T: RawKeyable),+> RawKeyable for ($($T,)+) {
-                fn as_any(&self) -> &dyn Any {
+            // SAFETY: fields are returned by their own impls
+            unsafe impl<$($T: RawKeyable),+> RawKeyable for ($($T,)+) {
+                fn as_any(&self) -> &dyn Any
+    where
+        Self: 'static,
+    {
                     self
                 }
-                fn as_any_mut(&mut self) -> &mut dyn Any {
+                fn as_any_mut(&mut self) -> &mut dyn Any
+    where
+        Self: 'static,
+    {
                     self
                 }
                 fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
@@ -130,21 +315,37 @@ macro_rules! tuple_impls {
                 }
             }
 
-            pub struct $Tuple<$($T),+>($(PhantomData<$T>),+);
-
+            pub struct $Tuple<$($T: Keyable),+>($(pub <$T as Keyable>::Mirror),+);
 
-            impl<$($T),+> $Tuple<$($T,)+> {
+            impl<$($T: Keyable),+> $Tuple<$($T,)+> {
                 pub const fn new() -> Self {
-                    $Tuple($( make_phantom::<$T>() ),+)
+                    $Tuple($( $T::MIRROR ),+)
+                }
+
+                pub const fn value_type(&self) -> PhantomData<($($T,)+)> {
+                    PhantomData
                 }
             }
 
-            impl<$($T: RawKeyable),+> Keyable for ($($T,)+) {
+            impl<$($T: Keyable),+> Mirror for $Tuple<$($T,)+> {
+                type Value = ($($T,)+);
+            }
+
+            impl<$($T: Keyable),+> Keyable for ($($T,)+) {
                 type Mirror = $Tuple<$($T),+>;
-                fn mirror() -> Self::Mirror {
-                    $Tuple::<$($T),+>::new()
-                }
+                const MIRROR: Self::Mirror = $Tuple::new();
+                keypaths_are_path!();
 
+                fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+                    match path.split_first() {
+                        None => Ok(TypeInfo::of::<Self>()),
+                        $( Some((PathComponent::Unnamed($idx), rest)) => $T::type_at_path(rest),)+
+                        Some((head, rest)) => {
+                            Err(FieldErrorKind::InvalidField(head.to_owned())
+                                .into_error_for::<Self>(rest.len()))
+                        }
+                    }
+                }
             }
 
 
@@ -152,9 +353,6 @@ macro_rules! tuple_impls {
     }
 }
 
-const fn make_phantom<T>() -> PhantomData<T> {
-    PhantomData
-}
 tuple_impls! {
     Tuple1 {
         (0) -> A
@@ -260,30 +458,279 @@ tuple_impls! {
     }
 }
 
-impl<T: RawKeyable> RawKeyable for Vec<T> {
-    fn as_any(&self) -> &dyn Any {
+/// Implements `KeyableSequence` for a sequence of `T`, given its generic
+/// params, by calling the methods of `$Base`, along with `Keyable`.
+macro_rules! keyable_sequence {
+    ([$($params:tt)*] $ty:ty, via $Base:ty, $Mirror:ty $(, default: $default:expr)?) => {
+        impl<$($params)*> KeyableSequence for $ty
+        where
+            T: Keyable,
+        {
+            type Item = T;
+
+            fn len(&self) -> usize {
+                <$Base>::len(self)
+            }
+
+            fn get(&self, index: usize) -> Option<&T> {
+                <$Base>::get(self, index)
+            }
+
+            fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+                <$Base>::get_mut(self, index)
+            }
+
+            fn items_mut(&mut self) -> Box<dyn Iterator<Item = &mut T> + '_> {
+                Box::new(<$Base>::iter_mut(self))
+            }
+        }
+
+        // SAFETY: items are returned by their own impls
+        unsafe impl<$($params)*> RawKeyable for $ty
+        where
+            T: Keyable,
+        {
+            fn as_any(&self) -> &dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+            fn as_any_mut(&mut self) -> &mut dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+
+            fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
+                collections::sequence_get_field(self, ident)
+            }
+
+            fn get_field_mut(
+                &mut self,
+                ident: &[PathComponent],
+            ) -> Result<&mut dyn RawKeyable, FieldError> {
+                collections::sequence_get_field_mut(self, ident)
+            }
+
+            fn for_each_item<'b>(&'b self, f: &mut dyn FnMut(PathComponent, &'b dyn RawKeyable)) {
+                collections::sequence_for_each_item(self, f)
+            }
+
+            fn for_each_item_mut<'b>(
+                &'b mut self,
+                f: &mut dyn FnMut(PathComponent, &'b mut dyn RawKeyable),
+            )-> Result<(), FieldError> {
+                collections::sequence_for_each_item_mut(self, f)
+            }
+        }
+
+        impl<$($params)*> Keyable for $ty
+        where
+            T: Keyable,
+        {
+            type Mirror = $Mirror;
+            const MIRROR: Self::Mirror = <$Mirror>::new();
+            keypaths_are_path!();
+
+            $(
+                fn default_value() -> Option<Self> {
+                    Some($default)
+                }
+            )?
+
+            fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+                collections::sequence_type_at_path::<Self>(path)
+            }
+        }
+    };
+}
+
+/// A literal index into an array of length `N`.
+struct ArrayIndex<const IDX: usize, const N: usize>;
+
+impl<const IDX: usize, const N: usize> ArrayIndex<IDX, N> {
+    /// Fails to evaluate if the index is out of bounds. This happens when the
+    /// mirror closure of a path is instantiated, which `__conjure_from_abyss`
+    /// ensures, so indices are checked at compile time.
+    const IN_BOUNDS: () = assert!(IDX < N, "keypath index is out of bounds for the array");
+}
+
+keyable_sequence!([T] Vec<T>, via [T], SequenceMirror<Self>, default: Vec::new());
+keyable_sequence!([T] VecDeque<T>, via VecDeque<T>, SequenceMirror<Self>, default: VecDeque::new());
+keyable_sequence!([T] Box<[T]>, via [T], SequenceMirror<Self>, default: Box::default());
+keyable_sequence!([T, const N: usize] [T; N], via [T], ArrayMirror<T, N>);
+
+/// Implements `KeyableSequence` for a shared slice, `$Ptr<[T]>`, along with
+/// `Keyable`.
+///
+/// As with other shared pointers, paths that mutate items go through
+/// `$Ptr::get_mut`, and fail with a `SharedPointer` error if the slice is
+/// shared.
+macro_rules! keyable_shared_slice {
+    ($Ptr:ident) => {
+        impl<T: Keyable> KeyableSequence for $Ptr<[T]> {
+            type Item = T;
+
+            fn len(&self) -> usize {
+                <[T]>::len(self)
+            }
+
+            fn get(&self, index: usize) -> Option<&T> {
+                <[T]>::get(self, index)
+            }
+
+            fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+                $Ptr::get_mut(self)?.get_mut(index)
+            }
+
+            fn items_mut(&mut self) -> Box<dyn Iterator<Item = &mut T> + '_> {
+                Box::new($Ptr::get_mut(self).into_iter().flatten())
+            }
+        }
+
+        // SAFETY: items are returned by their own impls
+        unsafe impl<T: Keyable> RawKeyable for $Ptr<[T]> {
+            fn as_any(&self) -> &dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+            fn as_any_mut(&mut self) -> &mut dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+
+            fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
+                collections::sequence_get_field(self, ident)
+            }
+
+            fn get_field_mut(
+                &mut self,
+                ident: &[PathComponent],
+            ) -> Result<&mut dyn RawKeyable, FieldError> {
+                if !ident.is_empty() && $Ptr::get_mut(self).is_none() {
+                    return Err(
+                        FieldErrorKind::SharedPointer.into_error_for::<Self>(ident.len() - 1)
+                    );
+                }
+                collections::sequence_get_field_mut(self, ident)
+            }
+
+            fn for_each_item<'b>(&'b self, f: &mut dyn FnMut(PathComponent, &'b dyn RawKeyable)) {
+                collections::sequence_for_each_item(self, f)
+            }
+
+            // as with other shared pointers, the items of a shared slice
+            // can't be visited through a mutable reference, which is an error.
+            fn for_each_item_mut<'b>(
+                &'b mut self,
+                f: &mut dyn FnMut(PathComponent, &'b mut dyn RawKeyable),
+            ) -> Result<(), FieldError> {
+                if $Ptr::get_mut(self).is_none() {
+                    return Err(FieldErrorKind::SharedPointer.into_error_for::<Self>(0));
+                }
+                collections::sequence_for_each_item_mut(self, f)
+            }
+        }
+
+        impl<T: Keyable> Keyable for $Ptr<[T]> {
+            type Mirror = SequenceMirror<Self>;
+            const MIRROR: Self::Mirror = SequenceMirror::new();
+            keypaths_are_path!();
+
+            fn default_value() -> Option<Self> {
+                Some($Ptr::from(Vec::new()))
+            }
+
+            fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+                collections::sequence_type_at_path::<Self>(path)
+            }
+        }
+    };
+}
+
+keyable_shared_slice!(Rc);
+keyable_shared_slice!(Arc);
+
+/// The mirror of an array, which checks literal indices against its length.
+pub struct ArrayMirror<T, const N: usize>(PhantomData<T>);
+
+impl<T, const N: usize> ArrayMirror<T, N> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        ArrayMirror(PhantomData)
+    }
+
+    pub const fn value_type(&self) -> PhantomData<[T; N]> {
+        PhantomData
+    }
+}
+
+impl<T, const N: usize> Mirror for ArrayMirror<T, N> {
+    type Value = [T; N];
+}
+
+impl<T: Keyable, const N: usize> ArrayMirror<T, N> {
+    /// Used for literal indices, such as `[2]`, which is `IDX`.
+    pub fn sequence_get<const IDX: usize>(&self) -> <T as Keyable>::Mirror {
+        #[allow(clippy::let_unit_value)]
+        let () = ArrayIndex::<IDX, N>::IN_BOUNDS;
+        <T as Keyable>::mirror()
+    }
+
+    /// Used for indices that are determined at runtime; this checks that the
+    /// index is the correct type.
+    pub fn index_get(&self, _idx: &usize) -> <T as Keyable>::Mirror {
+        <T as Keyable>::mirror()
+    }
+
+    /// Used for literals that can only be map keys, such as `[-1]`; the bound
+    /// is never satisfied, and only exists to produce a helpful error.
+    pub fn key_get<K: ?Sized>(&self, _key: &K) -> <T as Keyable>::Mirror
+    where
+        [T; N]: KeyedMap,
+    {
+        <T as Keyable>::mirror()
+    }
+
+    /// Used for wildcards, `[*]`, which match every item.
+    pub fn wildcard_get(&self) -> <T as Keyable>::Mirror {
+        <T as Keyable>::mirror()
+    }
+}
+
+/// Shared references are leaves: paths can end at them, but since their
+/// targets cannot be mutated, not traverse through them.
+///
+/// This includes shared slices, `&[T]`: an impl for them, as a sequence, would
+/// overlap with this one.
+// SAFETY: references are leaves, and only return themselves
+unsafe impl<T: ?Sized> RawKeyable for &T {
+    fn as_any(&self) -> &dyn Any
+    where
+        Self: 'static,
+    {
         self
     }
-    fn as_any_mut(&mut self) -> &mut dyn Any {
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    where
+        Self: 'static,
+    {
         self
     }
 
     fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
         match ident.split_first() {
             None => Ok(self),
-            Some((PathComponent::IndexInt(idx), rest)) => self
-                .get(*idx)
-                .ok_or_else(|| FieldError {
-                    kind: FieldErrorKind::IndexOutOfRange(*idx),
-                    type_name: std::any::type_name::<Self>(),
-                    depth: rest.len(),
-                })
-                .and_then(|t| t.get_field(rest)),
-            Some((field, rest)) => Err(FieldError {
-                kind: FieldErrorKind::InvalidField(*field),
-                type_name: std::any::type_name::<Self>(),
-                depth: rest.len(),
-            }),
+            Some((head, rest)) => {
+                Err(FieldErrorKind::InvalidField(head.to_owned()).into_error(self, rest.len()))
+            }
         }
     }
 
@@ -293,73 +740,141 @@ impl<T: RawKeyable> RawKeyable for Vec<T> {
     ) -> Result<&mut dyn RawKeyable, FieldError> {
         match ident.split_first() {
             None => Ok(self),
-            Some((PathComponent::IndexInt(idx), rest)) => self
-                .get_mut(*idx)
-                .ok_or_else(|| FieldError {
-                    kind: FieldErrorKind::IndexOutOfRange(*idx),
-                    type_name: std::any::type_name::<Self>(),
-                    depth: rest.len(),
-                })
-                .and_then(|t| t.get_field_mut(rest)),
-            Some((field, rest)) => Err(FieldError {
-                kind: FieldErrorKind::InvalidField(*field),
-                type_name: std::any::type_name::<Self>(),
-                depth: rest.len(),
-            }),
+            Some((head, rest)) => {
+                Err(FieldErrorKind::InvalidField(head.to_owned()).into_error(self, rest.len()))
+            }
         }
     }
 }
 
-impl<T: Keyable> Keyable for Vec<T> {
-    type Mirror = VecMirror<T>;
-
-    fn mirror() -> Self::Mirror {
-        VecMirror(PhantomData)
-    }
+impl<T: ?Sized> Keyable for &T {
+    type Mirror = Leaf<Self>;
+    const MIRROR: Leaf<Self> = Leaf::new();
+    keypaths_are_path!();
 }
 
-pub struct VecMirror<T>(PhantomData<T>);
+/// Implements `KeyableMap` for a map from `IndexKey`s to `T`, along with
+/// `Keyable`; keys are looked up by their `IndexKey::Lookup` type.
+macro_rules! keyable_map {
+    ($Map:ident, $($bounds:tt)+) => {
+        impl<K, T> KeyableMap for $Map<K, T>
+        where
+            K: IndexKey + Borrow<K::Lookup> + $($bounds)+,
+            K::Lookup: $($bounds)+,
+            T: Keyable,
+        {
+            type Key = K;
+            type Value = T;
 
-impl<T> VecMirror<T> {
-    pub const fn new() -> Self {
-        VecMirror(PhantomData)
-    }
-}
+            fn get_by_key(&self, key: &K::Lookup) -> Option<&T> {
+                self.get(key)
+            }
 
-impl<T: Keyable> VecMirror<T> {
-    pub fn sequence_get(self) -> <T as Keyable>::Mirror {
-        <T as Keyable>::mirror()
-    }
+            fn get_by_key_mut(&mut self, key: &K::Lookup) -> Option<&mut T> {
+                self.get_mut(key)
+            }
+
+            fn entries(&self) -> Box<dyn Iterator<Item = (&K, &T)> + '_> {
+                Box::new(self.iter())
+            }
+
+            fn entries_mut(&mut self) -> Box<dyn Iterator<Item = (&K, &mut T)> + '_> {
+                Box::new(self.iter_mut())
+            }
+        }
+
+        // SAFETY: values are returned by their own impls
+        unsafe impl<K, T> RawKeyable for $Map<K, T>
+        where
+            K: IndexKey + Borrow<K::Lookup> + $($bounds)+,
+            K::Lookup: $($bounds)+,
+            T: Keyable,
+        {
+            fn as_any(&self) -> &dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+            fn as_any_mut(&mut self) -> &mut dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+
+            fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
+                collections::map_get_field(self, ident)
+            }
+
+            fn get_field_mut(
+                &mut self,
+                ident: &[PathComponent],
+            ) -> Result<&mut dyn RawKeyable, FieldError> {
+                collections::map_get_field_mut(self, ident)
+            }
+
+            fn for_each_item<'a>(&'a self, f: &mut dyn FnMut(PathComponent, &'a dyn RawKeyable)) {
+                collections::map_for_each_item(self, f)
+            }
+
+            fn for_each_item_mut<'a>(
+                &'a mut self,
+                f: &mut dyn FnMut(PathComponent, &'a mut dyn RawKeyable),
+            )-> Result<(), FieldError> {
+                collections::map_for_each_item_mut(self, f)
+            }
+        }
+
+        impl<K, T> Keyable for $Map<K, T>
+        where
+            K: IndexKey + Borrow<K::Lookup> + $($bounds)+,
+            K::Lookup: $($bounds)+,
+            T: Keyable,
+        {
+            type Mirror = MapMirror<Self>;
+            const MIRROR: Self::Mirror = MapMirror::new();
+            keypaths_are_path!();
+
+            fn default_value() -> Option<Self> {
+                Some($Map::new())
+            }
+
+            fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+                collections::map_type_at_path::<Self>(path)
+            }
+        }
+    };
 }
 
-impl<K: 'static, T> RawKeyable for HashMap<K, T>
-where
-    T: Keyable,
-    K: std::cmp::Eq + std::hash::Hash + std::borrow::Borrow<str>,
-{
-    fn as_any(&self) -> &dyn Any {
+keyable_map!(HashMap, Eq + Hash);
+keyable_map!(BTreeMap, Ord);
+
+// SAFETY: the contents are returned by their own impl
+unsafe impl<T: Keyable> RawKeyable for Option<T> {
+    fn as_any(&self) -> &dyn Any
+    where
+        Self: 'static,
+    {
         self
     }
-    fn as_any_mut(&mut self) -> &mut dyn Any {
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    where
+        Self: 'static,
+    {
         self
     }
 
     fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
         match ident.split_first() {
             None => Ok(self),
-            Some((PathComponent::IndexStr(idx), rest)) => self
-                .get(*idx)
-                .ok_or_else(|| FieldError {
-                    kind: FieldErrorKind::MissinngKey(idx.to_string()),
-                    type_name: std::any::type_name::<Self>(),
-                    depth: rest.len(),
-                })
+            Some((PathComponent::Optional, rest)) => self
+                .as_ref()
+                .ok_or_else(|| FieldErrorKind::NoneValue.into_error(self, rest.len()))
                 .and_then(|t| t.get_field(rest)),
-            Some((field, rest)) => Err(FieldError {
-                kind: FieldErrorKind::InvalidField(*field),
-                type_name: std::any::type_name::<Self>(),
-                depth: rest.len(),
-            }),
+            Some((field, rest)) => {
+                Err(FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len()))
+            }
         }
     }
 
@@ -369,45 +884,349 @@ where
     ) -> Result<&mut dyn RawKeyable, FieldError> {
         match ident.split_first() {
             None => Ok(self),
-            Some((PathComponent::IndexStr(idx), rest)) => self
-                .get_mut(idx)
-                .ok_or_else(|| FieldError {
-                    kind: FieldErrorKind::MissinngKey(idx.to_string()),
-                    type_name: std::any::type_name::<Self>(),
-                    depth: rest.len(),
-                })
-                .and_then(|t| t.get_field_mut(rest)),
-            Some((field, rest)) => Err(FieldError {
-                kind: FieldErrorKind::InvalidField(*field),
-                type_name: std::any::type_name::<Self>(),
-                depth: rest.len(),
-            }),
+            Some((PathComponent::Optional, rest)) => match self {
+                Some(t) => t.get_field_mut(rest),
+                None => Err(FieldErrorKind::NoneValue.into_error(self, rest.len())),
+            },
+            Some((field, rest)) => {
+                Err(FieldErrorKind::InvalidField(field.clone()).into_error(self, rest.len()))
+            }
+        }
+    }
+
+    fn insert_default_if_none(&mut self) {
+        if self.is_none() {
+            *self = T::default_value();
         }
     }
 }
 
-impl<K, T> Keyable for HashMap<K, T>
-where
-    K: std::cmp::Eq + std::hash::Hash + std::borrow::Borrow<str> + 'static,
-    T: Keyable,
-{
-    type Mirror = HashMapMirror<K, T>;
+impl<T: Keyable> Keyable for Option<T> {
+    type Mirror = OptionMirror<T>;
+    const MIRROR: Self::Mirror = OptionMirror::new();
+    keypaths_are_path!();
 
-    fn mirror() -> Self::Mirror {
-        HashMapMirror(PhantomData, PhantomData)
+    fn default_value() -> Option<Self> {
+        Some(None)
+    }
+
+    fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+        match path.split_first() {
+            None => Ok(TypeInfo::of::<Self>()),
+            Some((PathComponent::Optional, rest)) => T::type_at_path(rest),
+            Some((field, rest)) => {
+                Err(FieldErrorKind::InvalidField(field.clone()).into_error_for::<Self>(rest.len()))
+            }
+        }
     }
 }
 
-pub struct HashMapMirror<K, T>(PhantomData<K>, PhantomData<T>);
+pub struct OptionMirror<T>(PhantomData<T>);
 
-impl<K, T> HashMapMirror<K, T> {
+impl<T> OptionMirror<T> {
     pub const fn new() -> Self {
-        HashMapMirror(PhantomData, PhantomData)
+        OptionMirror(PhantomData)
     }
+
+    pub const fn value_type(&self) -> PhantomData<Option<T>> {
+        PhantomData
+    }
+}
+
+impl<T> Mirror for OptionMirror<T> {
+    type Value = Option<T>;
 }
 
-impl<K, T: Keyable> HashMapMirror<K, T> {
-    pub fn map_get(self) -> <T as Keyable>::Mirror {
+impl<T: Keyable> OptionMirror<T> {
+    pub fn optional_get(&self) -> <T as Keyable>::Mirror {
         <T as Keyable>::mirror()
     }
 }
+
+/// Pointer and cell types, which paths pass through to the `T` they contain.
+trait Pointer<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind>;
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind>;
+    fn from_contents(contents: T) -> Self;
+
+    /// For types whose contents can only be borrowed within a scope, call `f`
+    /// with the contents; `contents` returns a `ScopedContents` error.
+    fn with_scoped_contents(&self, _f: &mut dyn FnMut(&T)) -> Option<Result<(), FieldErrorKind>> {
+        None
+    }
+}
+
+impl<T> Pointer<T> for Box<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Ok(self)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        Ok(self)
+    }
+
+    fn from_contents(contents: T) -> Self {
+        Box::new(contents)
+    }
+}
+
+impl<T> Pointer<T> for Rc<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Ok(self)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        Rc::get_mut(self).ok_or(FieldErrorKind::SharedPointer)
+    }
+
+    fn from_contents(contents: T) -> Self {
+        Rc::new(contents)
+    }
+}
+
+impl<T> Pointer<T> for Arc<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Ok(self)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        Arc::get_mut(self).ok_or(FieldErrorKind::SharedPointer)
+    }
+
+    fn from_contents(contents: T) -> Self {
+        Arc::new(contents)
+    }
+}
+
+impl<T: Clone> Pointer<T> for Cow<'_, T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Ok(self)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        Ok(self.to_mut())
+    }
+
+    fn from_contents(contents: T) -> Self {
+        Cow::Owned(contents)
+    }
+}
+
+impl<T> Pointer<T> for RefCell<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Err(FieldErrorKind::ScopedContents)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        Ok(self.get_mut())
+    }
+
+    fn from_contents(contents: T) -> Self {
+        RefCell::new(contents)
+    }
+
+    fn with_scoped_contents(&self, f: &mut dyn FnMut(&T)) -> Option<Result<(), FieldErrorKind>> {
+        Some(
+            self.try_borrow()
+                .map(|contents| f(&contents))
+                .map_err(|_| FieldErrorKind::AlreadyBorrowed),
+        )
+    }
+}
+
+impl<T> Pointer<T> for Mutex<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Err(FieldErrorKind::ScopedContents)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        self.get_mut().map_err(|_| FieldErrorKind::Poisoned)
+    }
+
+    fn from_contents(contents: T) -> Self {
+        Mutex::new(contents)
+    }
+
+    fn with_scoped_contents(&self, f: &mut dyn FnMut(&T)) -> Option<Result<(), FieldErrorKind>> {
+        Some(
+            self.lock()
+                .map(|contents| f(&contents))
+                .map_err(|_| FieldErrorKind::Poisoned),
+        )
+    }
+}
+
+impl<T> Pointer<T> for RwLock<T> {
+    fn contents(&self) -> Result<&T, FieldErrorKind> {
+        Err(FieldErrorKind::ScopedContents)
+    }
+
+    fn contents_mut(&mut self) -> Result<&mut T, FieldErrorKind> {
+        self.get_mut().map_err(|_| FieldErrorKind::Poisoned)
+    }
+
+    fn from_contents(contents: T) -> Self {
+        RwLock::new(contents)
+    }
+
+    fn with_scoped_contents(&self, f: &mut dyn FnMut(&T)) -> Option<Result<(), FieldErrorKind>> {
+        Some(
+            self.read()
+                .map(|contents| f(&contents))
+                .map_err(|_| FieldErrorKind::Poisoned),
+        )
+    }
+}
+
+/// Implements `Keyable` for a pointer or cell type that contains a `T`, given
+/// its generic params.
+///
+/// Paths pass through the pointer: a path that ends at the pointer resolves to
+/// the pointer itself, and any other path continues into its contents.
+macro_rules! keyable_pointer {
+    ([$($params:tt)*] $ty:ty) => {
+        // SAFETY: the contents are returned by their own impl
+        unsafe impl<$($params)*> RawKeyable for $ty
+        where
+            T: Keyable,
+        {
+            fn as_any(&self) -> &dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+            fn as_any_mut(&mut self) -> &mut dyn Any
+            where
+                Self: 'static,
+            {
+                self
+            }
+
+            fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError> {
+                if ident.is_empty() {
+                    return Ok(self);
+                }
+                match Pointer::contents(self) {
+                    Ok(contents) => contents.get_field(ident),
+                    Err(kind) => Err(kind.into_error_for::<Self>(ident.len() - 1)),
+                }
+            }
+
+            fn get_field_mut(
+                &mut self,
+                ident: &[PathComponent],
+            ) -> Result<&mut dyn RawKeyable, FieldError> {
+                if ident.is_empty() {
+                    return Ok(self);
+                }
+                match Pointer::contents_mut(self) {
+                    Ok(contents) => contents.get_field_mut(ident),
+                    Err(kind) => Err(kind.into_error_for::<Self>(ident.len() - 1)),
+                }
+            }
+
+            // a shared pointer can't be mutated; the path then fails when it
+            // continues into the contents.
+            fn insert_default_if_none(&mut self) {
+                if let Ok(contents) = Pointer::contents_mut(self) {
+                    contents.insert_default_if_none();
+                }
+            }
+
+            fn with_contents(
+                &self,
+                f: &mut dyn FnMut(&dyn RawKeyable),
+            ) -> Option<Result<(), FieldError>> {
+                let result = self.with_scoped_contents(&mut |contents: &T| f(contents))?;
+                Some(result.map_err(|kind| kind.into_error_for::<Self>(0)))
+            }
+
+            // the items of cells can't be borrowed beyond a scope, so wildcards
+            // only reach them through a mutable reference.
+            fn for_each_item<'b>(&'b self, f: &mut dyn FnMut(PathComponent, &'b dyn RawKeyable)) {
+                if let Ok(contents) = Pointer::contents(self) {
+                    contents.for_each_item(f);
+                }
+            }
+
+            fn for_each_item_mut<'b>(
+                &'b mut self,
+                f: &mut dyn FnMut(PathComponent, &'b mut dyn RawKeyable),
+            )-> Result<(), FieldError> {
+                match Pointer::contents_mut(self) {
+                    Ok(contents) => contents.for_each_item_mut(f),
+                    Err(kind) => Err(kind.into_error_for::<Self>(0)),
+                }
+            }
+        }
+
+        impl<$($params)*> Keyable for $ty
+        where
+            T: Keyable,
+        {
+            type Mirror = PointerMirror<Self, T>;
+            const MIRROR: Self::Mirror = PointerMirror::new();
+            keypaths_are_path!();
+
+            fn default_value() -> Option<Self> {
+                T::default_value().map(Pointer::from_contents)
+            }
+
+            fn type_at_path(path: &[PathComponent]) -> Result<TypeInfo, FieldError> {
+                match path {
+                    [] => Ok(TypeInfo::of::<Self>()),
+                    _ => T::type_at_path(path),
+                }
+            }
+        }
+    };
+}
+
+keyable_pointer!([T] Box<T>);
+keyable_pointer!([T] Rc<T>);
+keyable_pointer!([T] Arc<T>);
+keyable_pointer!(['a, T: Clone] Cow<'a, T>);
+keyable_pointer!([T] RefCell<T>);
+keyable_pointer!([T] Mutex<T>);
+keyable_pointer!([T] RwLock<T>);
+
+/// The mirror of a pointer or cell type `P` that contains a `T`.
+///
+/// This derefs to the mirror of `T`, so that the fields and methods of that
+/// mirror are found as if they were this one's. It doesn't contain that
+/// mirror, so that types can contain pointers to themselves.
+///
+/// It does not implement [`Mirror`], so that the fallbacks for unsupported
+/// components are found on the mirror of `T`, and name `T` in their errors.
+pub struct PointerMirror<P, T>(PhantomData<P>, PhantomData<T>);
+
+impl<P, T> PointerMirror<P, T> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        PointerMirror(PhantomData, PhantomData)
+    }
+
+    pub const fn value_type(&self) -> PhantomData<P> {
+        PhantomData
+    }
+}
+
+impl<P, T: Keyable> std::ops::Deref for PointerMirror<P, T> {
+    type Target = T::Mirror;
+
+    fn deref(&self) -> &T::Mirror {
+        // A const item can't borrow `T::MIRROR`, since it may not be 'static
+        // or free of interior mutability; but mirrors are zero-sized, and
+        // `T::MIRROR` shows that one exists, so any aligned pointer will do.
+        const {
+            assert!(
+                std::mem::size_of::<T::Mirror>() == 0,
+                "the mirrors of types behind pointers must be zero-sized"
+            )
+        };
+        // SAFETY: a dangling, aligned pointer is a valid reference to a
+        // zero-sized value.
+        unsafe { std::ptr::NonNull::dangling().as_ref() }
+    }
+}
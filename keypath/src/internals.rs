@@ -1,28 +1,715 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::sync::Arc;
 
-use super::FieldError;
+use super::{FieldError, FieldErrorKind};
+
+pub use super::collections::{
+    map_for_each_item, map_for_each_item_mut, map_get_field, map_get_field_mut, map_type_at_path,
+    sequence_for_each_item, sequence_for_each_item_mut, sequence_get_field, sequence_get_field_mut,
+    sequence_type_at_path, IndexKey, MapMirror, SequenceMirror,
+};
+pub use super::impls::{Leaf, Opaque};
 
 /// A trait for types that expose their properties via keypath.
 ///
 /// All of the dynamism and traversal logic happens here; its split into a
 /// separate trait for object safety.
-pub trait RawKeyable: 'static {
-    fn as_any(&self) -> &dyn Any;
-    fn as_any_mut(&mut self) -> &mut dyn Any;
+///
+/// This does not require `'static`, so that types that borrow their data can
+/// be traversed; such types can't be converted to [`Any`], and values are
+/// instead identified by their [`TypeInfo`].
+///
+/// This is implemented by `#[derive(Keyable)]`, which upholds the
+/// requirements below; implement it by hand only for leaf types, or with
+/// care.
+///
+/// # Safety
+///
+/// Keypaths downcast the values that these methods return with pointer
+/// casts, trusting the types that the implementation reports:
+///
+/// - `type_info` must describe the type of `self`, or a type with the same
+///   layout that `self` may be reinterpreted as, as for [`Opaque`].
+/// - the value at a path, whether it is returned by `get_field` or
+///   `get_field_mut`, or passed to the callback of `with_contents`,
+///   `for_each_item` or `for_each_item_mut`, must be of the type that
+///   [`Keyable::Mirror`] and [`Keyable::type_at_path`] give for that path,
+///   with the lifetimes of `Self`. In particular, every value reached from a
+///   `'static` value must itself be `'static`.
+///
+/// [`Keyable::Mirror`]: crate::Keyable::Mirror
+/// [`Keyable::type_at_path`]: crate::Keyable::type_at_path
+pub unsafe trait RawKeyable {
+    fn as_any(&self) -> &dyn Any
+    where
+        Self: 'static;
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    where
+        Self: 'static;
+
+    /// The type of the value, as used for downcasting.
+    fn type_info(&self) -> TypeInfo {
+        TypeInfo::of::<Self>()
+    }
+
+    /// The name of the concrete type, as returned by [`std::any::type_name`].
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     fn get_field(&self, ident: &[PathComponent]) -> Result<&dyn RawKeyable, FieldError>;
     fn get_field_mut(&mut self, ident: &[PathComponent])
         -> Result<&mut dyn RawKeyable, FieldError>;
+
+    /// If this is an `Option` that is `None`, replace it with `Some` default
+    /// value, if the inner type has a known default.
+    ///
+    /// This is used to implement [`FallibleKeyPath::get_mut_or_insert_default`].
+    /// Pointers and cells forward it to their contents, so that an `Option`
+    /// behind a `Box` is found; for all other types it does nothing.
+    ///
+    /// [`FallibleKeyPath::get_mut_or_insert_default`]: crate::FallibleKeyPath::get_mut_or_insert_default
+    fn insert_default_if_none(&mut self) {}
+
+    /// Call `f` with the contents of a type whose contents can only be
+    /// borrowed within a scope, such as a `RefCell` or a `Mutex`.
+    ///
+    /// The `get_field` impls of such types return a
+    /// [`FieldErrorKind::ScopedContents`] error for paths into their contents,
+    /// which are instead reached through this method. Other types return
+    /// `None`.
+    fn with_contents(&self, _f: &mut dyn FnMut(&dyn RawKeyable)) -> Option<Result<(), FieldError>> {
+        None
+    }
+
+    /// Call `f` with each item of a collection, and the component that
+    /// indexes it.
+    ///
+    /// This is how a [`PathComponent::Wildcard`] is expanded; types that are
+    /// not collections have no items.
+    fn for_each_item<'a>(&'a self, _f: &mut dyn FnMut(PathComponent, &'a dyn RawKeyable)) {}
+
+    /// Call `f` with a mutable reference to each item of a collection, and the
+    /// component that indexes it.
+    ///
+    /// Returns an error if the items can't be borrowed mutably, such as those
+    /// of a shared `Rc`; its depth is relative to the wildcard.
+    fn for_each_item_mut<'a>(
+        &'a mut self,
+        _f: &mut dyn FnMut(PathComponent, &'a mut dyn RawKeyable),
+    ) -> Result<(), FieldError> {
+        Ok(())
+    }
+}
+
+impl<'a> dyn RawKeyable + 'a {
+    /// Downcast to a concrete type, returning a [`FieldErrorKind::TypeMismatch`]
+    /// error if this is some other type.
+    ///
+    /// # Safety
+    ///
+    /// Types are compared without their lifetimes, so the caller must ensure
+    /// that if this is a `T`, it is a `T` with the same lifetimes. This holds
+    /// for the value of a typed keypath, whose value type is tied to its
+    /// root's by the [`keypath!`] macro, and for any value of a `'static` root,
+    /// by the requirements of [`RawKeyable`].
+    ///
+    /// [`keypath!`]: crate::keypath
+    pub(crate) unsafe fn downcast_ref<T>(&self) -> Result<&T, FieldError> {
+        match self.type_info().is::<T>() {
+            true => Ok(&*(self as *const Self as *const T)),
+            false => Err(type_mismatch::<T>(self.type_name())),
+        }
+    }
+
+    /// Downcast to a concrete type, returning a [`FieldErrorKind::TypeMismatch`]
+    /// error if this is some other type.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`downcast_ref`](Self::downcast_ref).
+    pub(crate) unsafe fn downcast_mut<T>(&mut self) -> Result<&mut T, FieldError> {
+        match self.type_info().is::<T>() {
+            true => Ok(&mut *(self as *mut Self as *mut T)),
+            false => Err(type_mismatch::<T>(self.type_name())),
+        }
+    }
+
+    /// Traverse `path`, and call `f` with the value at the end of it.
+    ///
+    /// Unlike `get_field`, this continues through types whose contents can
+    /// only be borrowed within a scope, such as `RefCell`s; `f` is called while
+    /// they are borrowed.
+    pub(crate) fn with_field(
+        &self,
+        path: &[PathComponent],
+        f: &mut dyn FnMut(&dyn RawKeyable),
+    ) -> Result<(), FieldError> {
+        let err = match self.get_field(path) {
+            Ok(value) => {
+                f(value);
+                return Ok(());
+            }
+            Err(err) if matches!(err.kind, FieldErrorKind::ScopedContents) => err,
+            Err(err) => return Err(err),
+        };
+
+        // the error is for the first component inside the scoped type, so
+        // everything before it leads to that type.
+        let (outer, inner) = path.split_at(path.len() - err.depth - 1);
+        let mut result = Ok(());
+        self.get_field(outer)?
+            .with_contents(&mut |contents| result = contents.with_field(inner, f))
+            .unwrap_or(Err(err))?;
+        result
+    }
+
+    /// Extend the lifetime of the trait object to `'static`.
+    ///
+    /// # Safety
+    ///
+    /// The value must actually be `'static`; [`RawKeyable`] requires this of
+    /// every value reached from a `'static` root.
+    pub(crate) unsafe fn assume_static(&self) -> &(dyn RawKeyable + 'static) {
+        std::mem::transmute::<&(dyn RawKeyable + 'a), &(dyn RawKeyable + 'static)>(self)
+    }
+
+    /// Extend the lifetime of the trait object to `'static`.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`assume_static`](Self::assume_static).
+    pub(crate) unsafe fn assume_static_mut(&mut self) -> &mut (dyn RawKeyable + 'static) {
+        std::mem::transmute::<&mut (dyn RawKeyable + 'a), &mut (dyn RawKeyable + 'static)>(self)
+    }
+}
+
+fn type_mismatch<T>(found: &'static str) -> FieldError {
+    let mut err = FieldErrorKind::TypeMismatch {
+        expected: std::any::type_name::<T>(),
+        found,
+    }
+    .into_error_for::<T>(0);
+    err.type_name = found;
+    err
 }
 
 /// A component of a keypath.
-#[derive(Debug, Clone, Copy)]
+///
+/// Paths created by the [`keypath!`] macro borrow their names from static
+/// strings; paths constructed at runtime (for instance from user input) can
+/// own them instead, with the constructor methods on this type.
+///
+/// ```
+/// use keypath::{Keyable, internals::PathComponent};
+///
+/// #[derive(Keyable)]
+/// struct Person {
+///     name: String,
+/// }
+///
+/// let person = Person { name: "coco".into() };
+/// let field = String::from("name");
+/// let path = vec![PathComponent::named(field)];
+///
+/// let name = person.try_any_at_path(&path).unwrap();
+/// assert_eq!(name.downcast_ref::<String>().unwrap(), "coco");
+/// ```
+///
+/// [`keypath!`]: crate::keypath
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum PathComponent {
     /// An unnamed field, such as on a tuple or tuple struct
     Unnamed(usize),
     /// A named field.
-    Named(&'static str),
-    /// An index into a sequence, such as a vec.
+    Named(Cow<'static, str>),
+    /// An index into a sequence, such as a vec, or an integer key into a map.
     IndexInt(usize),
-    /// An index into a map with string keys.
-    IndexStr(&'static str),
+    /// A key into a map with string keys.
+    IndexStr(Cow<'static, str>),
+    /// A key of any other type into a map, such as an enum; see [`IndexKey`].
+    Key(AnyKey),
+    /// A variant of an enum.
+    Variant(Cow<'static, str>),
+    /// The value of an `Option`, which must be `Some`.
+    Optional,
+    /// Every item of a collection, written `[*]`; only a
+    /// [`WildcardKeyPath`](crate::WildcardKeyPath) can contain this.
+    Wildcard,
+}
+
+impl PathComponent {
+    /// Create a component for an unnamed field, such as on a tuple.
+    pub const fn unnamed(idx: usize) -> Self {
+        PathComponent::Unnamed(idx)
+    }
+
+    /// Create a component for a named field.
+    pub fn named(name: impl Into<Cow<'static, str>>) -> Self {
+        PathComponent::Named(name.into())
+    }
+
+    /// Create a component for an index into a sequence.
+    pub const fn index_int(idx: usize) -> Self {
+        PathComponent::IndexInt(idx)
+    }
+
+    /// Create a component for a key into a map with string keys.
+    pub fn index_str(key: impl Into<Cow<'static, str>>) -> Self {
+        PathComponent::IndexStr(key.into())
+    }
+
+    /// Create a component for a key of any other type into a map.
+    pub fn key<K>(key: K) -> Self
+    where
+        K: Hash + Ord + fmt::Debug + Send + Sync + 'static,
+    {
+        PathComponent::Key(AnyKey::new(key))
+    }
+
+    /// If this is a [`Key`](PathComponent::Key) holding a `K`, the key.
+    pub fn key_ref<K: 'static>(&self) -> Option<&K> {
+        match self {
+            PathComponent::Key(key) => key.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// Create a component for an enum variant.
+    pub fn variant(name: impl Into<Cow<'static, str>>) -> Self {
+        PathComponent::Variant(name.into())
+    }
+
+    /// Whether traversing this component can fail at runtime.
+    ///
+    /// Only fields are infallible; a path made up only of fields can be
+    /// a [`KeyPath`](crate::KeyPath).
+    pub fn is_fallible(&self) -> bool {
+        !matches!(self, PathComponent::Unnamed(_) | PathComponent::Named(_))
+    }
+}
+
+/// Components are displayed using the syntax of the [`keypath!`] macro,
+/// such as `.name`, `[0]` or `::Circle`, which can be parsed back unless they
+/// hold a key that is not a literal, such as an enum.
+///
+/// [`keypath!`]: crate::keypath
+impl fmt::Display for PathComponent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathComponent::Unnamed(idx) => write!(f, ".{}", idx),
+            PathComponent::Named(name) => write!(f, ".{}", name),
+            PathComponent::IndexInt(idx) => write!(f, "[{}]", idx),
+            PathComponent::IndexStr(key) => write!(f, "[{:?}]", key),
+            PathComponent::Key(key) => {
+                match (key.downcast_ref::<i128>(), key.downcast_ref::<u128>()) {
+                    // integer keys that don't fit in a `usize` keep their type
+                    (Some(int), _) if *int >= 0 => write!(f, "[{}_i128]", int),
+                    (_, Some(int)) => write!(f, "[{}_u128]", int),
+                    _ => write!(f, "[{:?}]", key),
+                }
+            }
+            PathComponent::Variant(name) => write!(f, "::{}", name),
+            PathComponent::Optional => f.write_str("?"),
+            PathComponent::Wildcard => f.write_str("[*]"),
+        }
+    }
+}
+
+/// A map key of any type, in a [`PathComponent::Key`].
+///
+/// Keys are compared with the `Eq` and `Ord` impls of their type, and keys
+/// of different types are never equal. So that paths can be sorted, keys of
+/// different types are ordered by their `TypeId`.
+#[derive(Clone)]
+pub struct AnyKey(Arc<dyn ErasedKey>);
+
+trait ErasedKey: fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn eq_key(&self, other: &dyn Any) -> bool;
+    /// Compare with a key of the same type, or `None` for another type.
+    fn cmp_key(&self, other: &dyn Any) -> Option<Ordering>;
+    fn hash_key(&self, state: &mut dyn Hasher);
+}
+
+impl<K: Hash + Ord + fmt::Debug + Send + Sync + 'static> ErasedKey for K {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn eq_key(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<K>() == Some(self)
+    }
+
+    fn cmp_key(&self, other: &dyn Any) -> Option<Ordering> {
+        other.downcast_ref::<K>().map(|other| self.cmp(other))
+    }
+
+    fn hash_key(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state)
+    }
+}
+
+impl AnyKey {
+    pub fn new<K>(key: K) -> Self
+    where
+        K: Hash + Ord + fmt::Debug + Send + Sync + 'static,
+    {
+        AnyKey(Arc::new(key))
+    }
+
+    /// The key, if it is a `K`.
+    pub fn downcast_ref<K: 'static>(&self) -> Option<&K> {
+        self.0.as_any().downcast_ref()
+    }
+
+    fn type_id(&self) -> TypeId {
+        self.0.as_any().type_id()
+    }
+}
+
+impl fmt::Debug for AnyKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for AnyKey {
+    fn eq(&self, other: &AnyKey) -> bool {
+        self.0.eq_key(other.0.as_any())
+    }
+}
+
+impl Eq for AnyKey {}
+
+impl Hash for AnyKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_id().hash(state);
+        self.0.hash_key(state);
+    }
+}
+
+impl PartialOrd for AnyKey {
+    fn partial_cmp(&self, other: &AnyKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AnyKey {
+    fn cmp(&self, other: &AnyKey) -> Ordering {
+        self.0
+            .cmp_key(other.0.as_any())
+            .unwrap_or_else(|| self.type_id().cmp(&other.type_id()))
+    }
+}
+
+/// Information about a type, used to check keypaths at runtime.
+///
+/// This is returned by [`Keyable::type_at_path`](crate::Keyable::type_at_path),
+/// and stored in type-erased keypaths so they can be downcast.
+///
+/// Types are identified without their lifetimes: `&'a str` has the same
+/// `TypeId` as `&'static str`, so types that borrow can be described too.
+#[derive(Clone, Copy)]
+pub struct TypeInfo {
+    // function pointers, so that this can be created in a const fn
+    type_id: fn() -> TypeId,
+    type_name: fn() -> &'static str,
+}
+
+impl TypeInfo {
+    pub const fn of<T: ?Sized>() -> Self {
+        TypeInfo {
+            type_id: erased_type_id::<T>,
+            type_name: std::any::type_name::<T>,
+        }
+    }
+
+    /// The `TypeId` of the type.
+    pub fn type_id(&self) -> TypeId {
+        (self.type_id)()
+    }
+
+    /// The name of the type, as returned by [`std::any::type_name`].
+    pub fn type_name(&self) -> &'static str {
+        (self.type_name)()
+    }
+
+    /// Returns `true` if this is the type `T`, ignoring lifetimes.
+    pub fn is<T: ?Sized>(&self) -> bool {
+        self.type_id() == erased_type_id::<T>()
+    }
+}
+
+/// The `TypeId` of `T`, ignoring its lifetimes; for a `'static` type, this
+/// is the same as `TypeId::of`.
+fn erased_type_id<T: ?Sized>() -> TypeId {
+    trait NonStaticAny {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+
+    impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+
+    let phantom = PhantomData::<T>;
+    // SAFETY: lifetimes are erased before code generation, so the `TypeId`
+    // does not depend on them; the `PhantomData` holds no data to outlive.
+    let phantom = unsafe {
+        std::mem::transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(&phantom)
+    };
+    NonStaticAny::type_id(phantom)
+}
+
+impl PartialEq for TypeInfo {
+    fn eq(&self, other: &TypeInfo) -> bool {
+        self.type_id() == other.type_id()
+    }
+}
+
+impl Eq for TypeInfo {}
+
+impl std::hash::Hash for TypeInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.type_id().hash(state)
+    }
+}
+
+impl PartialOrd for TypeInfo {
+    fn partial_cmp(&self, other: &TypeInfo) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TypeInfo {
+    fn cmp(&self, other: &TypeInfo) -> std::cmp::Ordering {
+        self.type_id().cmp(&other.type_id())
+    }
+}
+
+impl fmt::Debug for TypeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.type_name())
+    }
+}
+
+/// Forget a keypath's mirror closure, while making sure it is compiled.
+///
+/// The closure is never called, but taking a pointer to a function that calls
+/// it means that it is instantiated along with the keypath, which evaluates
+/// the compile-time checks in mirror methods, such as those on array indices.
+pub(crate) const fn forget_mirror_closure<M, V, F: FnOnce(M) -> V>(closure: F) {
+    let _ = call_mirror_closure::<M, V, F> as fn(F, M) -> V;
+    std::mem::forget(closure);
+}
+
+fn call_mirror_closure<M, V, F: FnOnce(M) -> V>(closure: F, mirror: M) -> V {
+    closure(mirror)
+}
+
+/// Implemented by every mirror type; `Value` is the type being mirrored.
+pub trait Mirror {
+    type Value: ?Sized;
+}
+
+/// Stands in for a `#[keypath(skip)]` field in the mirror of a tuple struct,
+/// so that the fields after it keep their positions.
+///
+/// It has no fields or `value_type`, so paths through it do not compile.
+pub struct Skipped;
+
+/// Holds the mirror of a `#[keypath(flatten)]` field, in the mirror of the
+/// type that contains it.
+///
+/// The containing mirror derefs to the flattened mirror, so that its fields
+/// are found as if they were the container's own. The flattened field itself
+/// has no `value_type`, so paths cannot name it.
+pub struct Flattened<M>(M);
+
+impl<M> Flattened<M> {
+    pub const fn new(mirror: M) -> Self {
+        Flattened(mirror)
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.0
+    }
+}
+
+/// Implemented by derived types, to provide their mirror.
+///
+/// The derived mirror is declared in its own scope, where it has the same name
+/// as the type it mirrors, so that errors for invalid fields name the right
+/// type; this is how the `Keyable` impl gets at it.
+pub trait DerivedMirror {
+    type Mirror;
+    const MIRROR: Self::Mirror;
+}
+
+/// Fallbacks for path components that a mirror does not support.
+///
+/// Mirrors that support indexing or optional chaining have inherent methods
+/// with these names, which method resolution prefers. These only exist so
+/// that using them on any other type produces a useful error, naming the
+/// type that was being traversed; their bounds are never satisfied, so they
+/// are never called.
+///
+/// These take `&self`, like the inherent methods, so that they are found
+/// through the mirrors of pointer types, which deref to their contents'.
+pub trait UnsupportedComponent: Mirror + Sized {
+    fn sequence_get<const IDX: usize>(&self) -> Self
+    where
+        Self::Value: Sequence,
+    {
+        unreachable!()
+    }
+
+    fn map_get(&self) -> Self
+    where
+        Self::Value: Map,
+    {
+        unreachable!()
+    }
+
+    fn index_get<K: ?Sized>(&self, _key: &K) -> Self
+    where
+        Self::Value: Collection,
+    {
+        unreachable!()
+    }
+
+    fn key_get<K: ?Sized>(&self, _key: &K) -> Self
+    where
+        Self::Value: KeyedMap,
+    {
+        unreachable!()
+    }
+
+    fn wildcard_get(&self) -> Self
+    where
+        Self::Value: Collection,
+    {
+        unreachable!()
+    }
+
+    fn optional_get(&self) -> Self
+    where
+        Self::Value: Optional,
+    {
+        unreachable!()
+    }
+}
+
+impl<M: Mirror> UnsupportedComponent for M {}
+
+/// Seals the diagnostic marker traits below.
+///
+/// Nothing implements them, so the `UnsupportedComponent` fallbacks that are
+/// bounded on them can never be called; they must not be implementable
+/// outside of this crate.
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Used in diagnostics for integer indices on types that are not sequences.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be indexed with an integer",
+    label = "`{Self}` is not a sequence",
+    note = "integer indices such as `[0]` can only be used with sequences such as `Vec`, and maps with integer keys"
+)]
+pub trait Sequence: sealed::Sealed {}
+
+/// Used in diagnostics for string keys on types that are not maps.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be indexed with a string key",
+    label = "`{Self}` is not a map",
+    note = "string keys such as `[\"key\"]` can only be used with maps such as `HashMap`"
+)]
+pub trait Map: sealed::Sealed {}
+
+/// Used in diagnostics for negative, suffixed and character literals, which
+/// can only be map keys, on types that are not maps.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be indexed with this key",
+    label = "`{Self}` is not a map",
+    note = "negative integers, integers with a suffix such as `5_u8`, and characters can only be the keys of maps; sequences are indexed with unsuffixed, non-negative integers such as `[0]`"
+)]
+pub trait KeyedMap: sealed::Sealed {}
+
+/// Implemented by integer map keys, which can be indexed with integer
+/// literals such as `[5]`; `MAX` is the largest such key.
+#[diagnostic::on_unimplemented(
+    message = "maps with `{Self}` keys cannot be indexed with an integer",
+    label = "`{Self}` is not an integer"
+)]
+pub trait IntegerKey {
+    const MAX: u128;
+}
+
+/// Implemented by string map keys, which can be indexed with string literals
+/// such as `["key"]`.
+#[diagnostic::on_unimplemented(
+    message = "maps with `{Self}` keys cannot be indexed with a string",
+    label = "`{Self}` is not a string"
+)]
+pub trait StringKey {}
+
+/// Used in diagnostics for runtime indices and wildcards on types that are
+/// not collections.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be indexed",
+    label = "`{Self}` is not a collection"
+)]
+pub trait Collection: sealed::Sealed {}
+
+/// Used in diagnostics for `?` on types that are not `Option`s.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not an `Option`",
+    label = "`?` can only follow a value of type `Option<T>`"
+)]
+pub trait Optional: sealed::Sealed {}
+
+/// Used by derived code to find out if a type implements `Default`.
+///
+/// Method resolution will prefer the [`ProbeDefault`] impl when it applies,
+/// and fall back to [`ProbeNoDefault`] otherwise.
+pub struct DefaultProbe<T>(PhantomData<T>);
+
+impl<T> DefaultProbe<T> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        DefaultProbe(PhantomData)
+    }
+}
+
+pub trait ProbeDefault<T> {
+    fn probe_default(&self) -> Option<T>;
+}
+
+impl<T: Default> ProbeDefault<T> for &DefaultProbe<T> {
+    fn probe_default(&self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+pub trait ProbeNoDefault<T> {
+    fn probe_default(&self) -> Option<T>;
+}
+
+impl<T> ProbeNoDefault<T> for DefaultProbe<T> {
+    fn probe_default(&self) -> Option<T> {
+        None
+    }
 }
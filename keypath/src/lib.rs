@@ -10,11 +10,13 @@
 //! cases this will be derived.
 //!
 //! [`KeyPath`][] instances can then be created with the [`keypath!`][] macro.
+//! Paths that may fail at runtime, such as those that index into a collection,
+//! are represented by [`FallibleKeyPath`][].
 //!
 //! # Examples
 //!
 //! ```
-//! use keypath::{Keyable, KeyPath, keypath};
+//! use keypath::{Keyable, KeyPath, FallibleKeyPath, keypath};
 //!
 //! #[derive(Keyable)]
 //! struct Person {
@@ -35,60 +37,257 @@
 //!     size: Size { big: false, heft: 45 }
 //! };
 //!
-//! let first_friend: KeyPath<Person, String> = keypath!(Person.friends[0]);
-//! let heft = keypath!(Person.size.heft);
-//!
-//! assert_eq!(person[&first_friend], "eli");
+//! let heft: KeyPath<Person, u8> = keypath!(Person.size.heft);
+//! assert_eq!(person[&heft], 45);
 //!
 //! // mutation:
 //! person[&heft] = 101;
 //! assert_eq!(person.size.heft, 101);
 //!
+//! // paths that might fail at runtime are a different type:
+//! let first_friend: FallibleKeyPath<Person, String> = keypath!(Person.friends[0]);
+//! assert_eq!(first_friend.get(&person).unwrap(), "eli");
+//!
+//! let tenth_friend = keypath!(Person.friends[10]);
+//! assert!(tenth_friend.get(&person).is_err());
+//! ```
+//!
+//! # Collections
+//!
+//! `Vec`, `VecDeque`, arrays, and boxed, `Rc` and `Arc` slices can be
+//! indexed with integers,
+//! and `HashMap` and `BTreeMap` with their keys, which may be strings,
+//! integers, or any other type that implements [`IndexKey`], such as an enum.
+//! Keys are checked against the map's key type when the path is compiled.
+//! Literal indices into arrays are checked against the array's length, and
+//! integer literals against the range of integer keys, as long as the path
+//! is not in a `const`. Shared references, including `&[T]` slices, are
+//! leaves, since their targets cannot be mutated; as with other shared
+//! pointers, mutating the items of an `Rc` or `Arc` slice fails if it is
+//! shared.
+//!
+//! A wildcard, `[*]`, matches every item of a collection; paths that contain
+//! one are a [`WildcardKeyPath`], which returns all of the values it matches.
+//!
+//! Other collections can be indexed by implementing [`KeyableSequence`] or
+//! [`KeyableMap`], and deriving `Keyable` with `#[keypath(sequence)]` or
+//! `#[keypath(map)]`.
+//!
+//! # Pointers and cells
+//!
+//! Paths pass through `Box`, `Rc`, `Arc` and `Cow` to their contents, and
+//! paths that end at one of them resolve to the pointer itself. Mutating
+//! through an `Rc` or `Arc` fails if it is shared, and mutating through a
+//! `Cow` makes it owned. A `Cow<str>` is a leaf, like a `String`.
+//!
+//! The contents of a `RefCell`, `Mutex` or `RwLock` can be mutated through a
+//! mutable reference, but can only be read within a closure, with
+//! [`Keyable::with_value`]; other methods that read them fail with a
+//! [`FieldErrorKind::ScopedContents`] error.
+//!
+//! # Generic types
+//!
+//! The derive supports type, const and lifetime parameters, and where
+//! clauses. Type parameters that are used by fields must be `Keyable`, as
+//! must any associated types they use, such as `T::Item`.
+//!
+//! # Borrowed types
+//!
+//! Types that borrow their data, such as the output of a zero-copy parser,
+//! can be keyable too. Values are identified by their type without its
+//! lifetimes, and a path created by the [`keypath!`][] macro ties the
+//! lifetimes of its value type to those of its root, so it can't be used to
+//! extend a borrow. Paths that are created at runtime, such as with
+//! [`KeyPath::parse`], have nothing to tie them together, so they, and APIs
+//! that return [`Any`], require `'static` types.
+//!
+//! ```
+//! use keypath::{Keyable, KeyPath, keypath};
+//!
+//! #[derive(Keyable)]
+//! struct View<'a> {
+//!     name: &'a str,
+//!     tags: Vec<&'a str>,
+//! }
+//!
+//! fn name<'a>() -> KeyPath<View<'a>, &'a str> {
+//!     keypath!(View.name)
+//! }
+//!
+//! let source = String::from("coco eli nico");
+//! let view = View { name: &source[..4], tags: source.split(' ').collect() };
+//! assert_eq!(view[&name()], "coco");
+//! assert_eq!(keypath!(View.tags[2]).get(&view).unwrap(), &"nico");
+//! ```
+//!
+//! # Field attributes
+//!
+//! The derive accepts `#[keypath(...)]` attributes on fields:
+//!
+//! - `skip`: the field is not part of any keypath, and its type need not
+//!   implement `Keyable`.
+//! - `rename = "name"`: the field is called `name` in keypaths, both in the
+//!   [`keypath!`][] macro and when parsing paths at runtime.
+//! - `opaque`: the field is a leaf; paths can end at it, but not traverse
+//!   into it. Its type can be any type, whether or not it implements
+//!   `Keyable`.
+//! - `flatten`: the fields of the field's type are reached as if they were
+//!   fields of the containing type, and the field itself is not part of any
+//!   keypath. The type's own fields take precedence over those of the
+//!   flattened field.
+//!
+//!   Only one field of a type (or of an enum variant) can be flattened: the
+//!   [`keypath!`][] macro finds the flattened fields by dereferencing the
+//!   type's mirror to that of the flattened field, and a type can only
+//!   dereference to one other. The flattened type can itself flatten one of
+//!   its fields, so fields can be flattened through several levels.
+//!
+//! A struct with a single field can be marked `#[keypath(transparent)]`,
+//! which flattens that field; this is useful for newtypes.
+//!
+//! Missing values of derived types that implement `Default` can be inserted
+//! by [`FallibleKeyPath::get_mut_or_insert_default`]. This is detected
+//! automatically, except for types with type parameters, which must be
+//! marked `#[keypath(default)]`; they are then only `Keyable` when they are
+//! also `Default`.
+//!
+//! ```
+//! use keypath::{Keyable, KeyPath, keypath};
+//!
+//! // a type from some other crate
+//! #[derive(Debug, PartialEq)]
+//! struct Instant(u64);
+//!
+//! #[derive(Keyable)]
+//! struct Event {
+//!     #[keypath(rename = "title")]
+//!     name: String,
+//!     #[keypath(opaque)]
+//!     start: Instant,
+//!     #[keypath(skip)]
+//!     cache: std::cell::Cell<u32>,
+//! }
+//!
+//! let event = Event { name: "lunch".into(), start: Instant(12), cache: Default::default() };
+//! assert_eq!(event[&keypath!(Event.title)], "lunch");
+//! assert_eq!(event[&keypath!(Event.start)], Instant(12));
+//! assert!(KeyPath::<Event, String>::parse("name").is_err());
+//! ```
+//!
+//! # Re-exporting
+//!
+//! Generated code refers to this crate as `::keypath`. If it is re-exported
+//! by another crate, or renamed in `Cargo.toml`, the path can be given to the
+//! derive with `#[keypath(crate = "path::to::keypath")]` on the type, and to
+//! the [`keypath!`][] macro with a leading `crate = path::to::keypath;`. A
+//! crate that re-exports the macro can wrap it, so that its users don't need
+//! to depend on this crate directly:
+//!
+//! ```
+//! # mod platform { pub mod keypath { pub use ::keypath::*; } }
+//! #[macro_export]
+//! macro_rules! keypath {
+//!     ($($path:tt)*) => {
+//!         $crate::platform::keypath::keypath!(crate = $crate::platform::keypath; $($path)*)
+//!     };
+//! }
+//! # use platform::keypath::Keyable;
+//! # #[derive(Keyable)]
+//! # #[keypath(crate = "platform::keypath")]
+//! # struct Person { name: String }
+//! # fn main() {
+//! # let person = Person { name: "coco".into() };
+//! # assert_eq!(person[&keypath!(Person.name)], "coco");
+//! # }
 //! ```
 
+mod collections;
 mod error;
+mod fallible;
 mod impls;
 pub mod internals;
+mod parse;
+mod partial;
+mod wildcard;
 
-pub use error::{FieldError, FieldErrorKind};
+pub use collections::{IndexKey, KeyableMap, KeyableSequence};
+pub use error::{FieldError, FieldErrorKind, ParseError};
+pub use fallible::FallibleKeyPath;
 pub use keypath_proc_macros::{keypath, Keyable};
+pub use partial::{AnyKeyPath, PartialKeyPath};
+pub use wildcard::WildcardKeyPath;
 
 use std::any::Any;
 use std::borrow::Cow;
 use std::marker::PhantomData;
 
 /// A non-fallible keypath.
-pub struct KeyPath<Root: ?Sized, Value: 'static> {
+///
+/// The [`keypath!`] macro only creates a `KeyPath` when the path is made up
+/// entirely of fields; paths that include indices, enum variants, or optionals
+/// may fail at runtime, and create a [`FallibleKeyPath`] instead.
+///
+/// A keypath is invariant in both of its types, so that the lifetimes of a
+/// borrowed value type can't be changed independently of the root's.
+pub struct KeyPath<Root: ?Sized, Value> {
     partial: PartialKeyPath<Root>,
-    _value: PhantomData<Value>,
-}
-
-// we don't really use this yet
-#[doc(hidden)]
-/// A keypath for a known route, but which doesn't know the destination type.
-#[derive(Debug)]
-pub struct PartialKeyPath<Root: ?Sized> {
-    fields: Cow<'static, [internals::PathComponent]>,
-    _root: PhantomData<Root>,
+    _value: PhantomData<fn(Value) -> Value>,
 }
 
-impl<Root, Value> KeyPath<Root, Value> {
+impl<Root: Keyable, Value> KeyPath<Root, Value> {
     /// Create a new typed `KeyPath` from the provided fields.
     ///
     /// This method does not ensure the path is valid; it is intended
     /// to be called after a path has been type-checked, presumably in the
-    /// context of a proc_macro.
+    /// context of a proc_macro. The value type is inferred from the closure,
+    /// which traverses the root's mirror to the mirror of the final type; this
+    /// also ties any lifetimes in the value type to those of the root.
+    ///
+    /// The closure is only type-checked, and never called, so the mirror
+    /// methods it uses need not be `const`.
+    ///
+    /// # Safety
+    ///
+    /// Values are downcast to `Value` without comparing lifetimes, so if the
+    /// value at `fields` is a `Value`, it must be a `Value` with the lifetimes
+    /// that the root's mirror gives it. A closure that traverses the mirror, as
+    /// generated by the macro, ensures this; it always holds if `Value` is
+    /// `'static`.
     #[doc(hidden)]
-    pub const fn __conjure_from_abyss(fields: &'static [internals::PathComponent]) -> Self {
+    pub const unsafe fn __conjure_from_abyss<F>(
+        value: F,
+        fields: &'static [internals::PathComponent],
+    ) -> Self
+    where
+        F: FnOnce(Root::Mirror) -> PhantomData<Value>,
+    {
+        internals::forget_mirror_closure(value);
         KeyPath {
             partial: PartialKeyPath {
                 fields: Cow::Borrowed(fields),
+                value: internals::TypeInfo::of::<Value>(),
                 _root: PhantomData,
             },
             _value: PhantomData,
         }
     }
+}
 
+impl<Root: Keyable> KeyPath<Root, Root> {
+    /// The empty path, from a value to itself.
+    pub(crate) fn identity() -> Self {
+        KeyPath {
+            partial: PartialKeyPath {
+                fields: Cow::Borrowed(&[]),
+                value: internals::TypeInfo::of::<Root>(),
+                _root: PhantomData,
+            },
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<Root, Value> KeyPath<Root, Value> {
     /// Create a new `KeyPath` by combining two routes.
     ///
     /// The final type of the first route must be the first type of the second
@@ -130,12 +329,70 @@ impl<Root, Value> KeyPath<Root, Value> {
         partial
             .fields
             .to_mut()
-            .extend(other.partial.fields.iter().clone());
+            .extend(other.partial.fields.iter().cloned());
+        partial.value = other.partial.value;
         KeyPath {
             partial,
             _value: other._value,
         }
     }
+
+    /// Create a new `FallibleKeyPath` by appending a fallible route to this one.
+    ///
+    /// The final type of this route must be the first type of the second route.
+    pub fn append_fallible<T>(
+        &self,
+        other: &FallibleKeyPath<Value, T>,
+    ) -> FallibleKeyPath<Root, T> {
+        FallibleKeyPath::from(KeyPath {
+            partial: self.partial.clone(),
+            _value: PhantomData,
+        })
+        .append(other)
+    }
+}
+
+impl<Root: Keyable + 'static, Value: 'static> KeyPath<Root, Value> {
+    /// Parse a `KeyPath` from a string, checking it against the root type.
+    ///
+    /// The syntax is the same as for the [`keypath!`] macro, without the root
+    /// type. The path must be made up only of fields, and must resolve to a
+    /// value of type `Value`; paths that can fail at runtime should be parsed
+    /// with [`FallibleKeyPath::parse`].
+    ///
+    /// Since nothing ties the lifetimes of a parsed path's value type to
+    /// those of its root, both types must be `'static`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use keypath::{Keyable, KeyPath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Person {
+    ///     name: String,
+    ///     size: (u8, u8),
+    /// }
+    ///
+    /// let mut person = Person { name: "coco".into(), size: (45, 12) };
+    ///
+    /// let width = KeyPath::<Person, u8>::parse("size.1").unwrap();
+    /// assert_eq!(person[&width], 12);
+    ///
+    /// assert!(KeyPath::<Person, String>::parse("size.1").is_err());
+    /// assert!(KeyPath::<Person, String>::parse("nmae").is_err());
+    /// ```
+    pub fn parse(path: &str) -> Result<Self, ParseError> {
+        let partial = PartialKeyPath::parse(path)?;
+        if partial.is_fallible() {
+            return Err(ParseError::Fallible);
+        }
+        error::check_value_type::<Value>(partial.value)?;
+        Ok(KeyPath {
+            partial,
+            _value: PhantomData,
+        })
+    }
 }
 
 /// A trait for types that can be indexed with keypaths.
@@ -144,22 +401,134 @@ pub trait Keyable: internals::RawKeyable {
     ///
     /// This is the worst part of the code right now? We generate structs with magic
     /// names for each Keyable type.
+    ///
+    /// Mirrors carry no data, and must be zero-sized.
     type Mirror;
 
+    /// An instance of this type's mirror.
+    ///
+    /// This is a const, rather than only a method, so that the mirrors of
+    /// compound types, such as tuples and generic structs, can be built from
+    /// those of their fields in const contexts; this is what lets keypaths
+    /// into generic types be `const`.
+    ///
+    /// Hand-written impls for types without fields can use
+    /// [`Leaf`](internals::Leaf), as in `const MIRROR: Leaf<Self> = Leaf::new();`.
+    const MIRROR: Self::Mirror;
+
     /// Return an instance of this type's mirror.
-    fn mirror() -> Self::Mirror;
+    fn mirror() -> Self::Mirror {
+        Self::MIRROR
+    }
+
+    /// Typed accessors for the keypaths into this type, starting from `Root`.
+    ///
+    /// For derived structs, this is a generated struct with an accessor for
+    /// each field, which derefs to the keypath of the struct itself. For other
+    /// types, it is just that keypath; hand-written impls can implement this
+    /// and [`keypaths_from`](Keyable::keypaths_from) with
+    /// [`keypaths_are_path!`].
+    type KeyPaths<Root>;
+
+    /// Return the typed accessors for the keypaths that continue `path`.
+    fn keypaths_from<Root>(path: KeyPath<Root, Self>) -> Self::KeyPaths<Root>
+    where
+        Self: Sized;
+
+    /// Return the typed accessors for the keypaths into this type.
+    ///
+    /// This is an alternative to the [`keypath!`] macro for structs, where
+    /// each field of a derived struct is a field of its accessor, and the
+    /// accessor of a field derefs to the field's [`KeyPath`]. Fields use
+    /// their renamed names; skipped and flattened fields have no accessors,
+    /// and opaque fields have only their keypath.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use keypath::{Keyable, KeyPath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Person {
+    ///     name: String,
+    ///     size: Size,
+    /// }
+    ///
+    /// #[derive(Keyable)]
+    /// struct Size {
+    ///     heft: u8,
+    /// }
+    ///
+    /// let mut person = Person { name: "coco".into(), size: Size { heft: 7 } };
+    /// let paths = Person::keypaths();
+    /// person[&paths.size.heft] = 9;
+    /// assert_eq!(person[&paths.name], "coco");
+    ///
+    /// // the accessor of a struct field derefs to the field's keypath, which
+    /// // composes with other keypaths as usual.
+    /// let size: &KeyPath<Person, Size> = &paths.size;
+    /// let heft = size.append(&Size::keypaths().heft);
+    /// assert_eq!(person[&heft], 9);
+    /// ```
+    fn keypaths() -> Self::KeyPaths<Self>
+    where
+        Self: Sized,
+    {
+        Self::keypaths_from(KeyPath::identity())
+    }
+
+    /// Return a default value for this type, if one is known.
+    ///
+    /// This is used when inserting missing values during traversal, such as
+    /// in [`FallibleKeyPath::get_mut_or_insert_default`]. Derived impls return
+    /// `Some` if the type implements `Default`; for generic types, only if
+    /// they are marked `#[keypath(default)]`.
+    #[doc(hidden)]
+    fn default_value() -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Check that a path is valid for this type, without needing an instance.
+    ///
+    /// This checks the structure of the path only: it does not know whether
+    /// an index is in range or an `Option` is `Some`. On success, returns the
+    /// type of the value the path resolves to.
+    ///
+    /// The default implementation treats the type as a leaf, with no fields.
+    fn type_at_path(path: &[internals::PathComponent]) -> Result<internals::TypeInfo, FieldError>
+    where
+        Self: Sized,
+    {
+        match path.split_first() {
+            None => Ok(internals::TypeInfo::of::<Self>()),
+            Some((head, rest)) => {
+                Err(FieldErrorKind::InvalidField(head.clone()).into_error_for::<Self>(rest.len()))
+            }
+        }
+    }
 
     //TODO: this is a bit of a mess, and I don't know what methods we will want
-    //or need. Having partial keypaths or keypaths that are failable seems reasonable,
-    //but I don't know what the types are going to look like yet.
+    //or need. Failable paths are handled by `FallibleKeyPath`; partial keypaths
+    //still seem reasonable, but I don't know what the types are going to look like yet.
     /// Attempt to traverse a series of `PathComponent`s, returning an `&dyn Any`
     /// if successful.
+    ///
+    /// Only `'static` types can be [`Any`], so this requires a `'static` root.
     fn try_any_at_path(
         &self,
         path: impl AsRef<[internals::PathComponent]>,
-    ) -> Result<&dyn Any, FieldError> {
-        self.get_field(path.as_ref())
-            .map(internals::RawKeyable::as_any)
+    ) -> Result<&dyn Any, FieldError>
+    where
+        Self: 'static,
+    {
+        let path = path.as_ref();
+        self.get_field(path)
+            // SAFETY: every value reachable from a 'static root is 'static
+            .map(|value| unsafe { value.assume_static() }.as_any())
+            .map_err(|e| e.with_path::<Self>(path))
     }
 
     /// Attempt to traverse a series of `PathComponent`s, returning an `&mut dyn Any`
@@ -167,25 +536,82 @@ pub trait Keyable: internals::RawKeyable {
     fn try_any_at_path_mut(
         &mut self,
         path: impl AsRef<[internals::PathComponent]>,
-    ) -> Result<&mut dyn Any, FieldError> {
-        self.get_field_mut(path.as_ref())
-            .map(internals::RawKeyable::as_any_mut)
+    ) -> Result<&mut dyn Any, FieldError>
+    where
+        Self: 'static,
+    {
+        let path = path.as_ref();
+        self.get_field_mut(path)
+            // SAFETY: every value reachable from a 'static root is 'static
+            .map(|value| unsafe { value.assume_static_mut() }.as_any_mut())
+            .map_err(|e| e.with_path::<Self>(path))
+    }
+
+    /// Attempt to traverse a series of `PathComponent`s, calling `f` with an
+    /// `&dyn Any` if successful, and returning its result.
+    ///
+    /// Unlike [`try_any_at_path`](Keyable::try_any_at_path), this can reach
+    /// the contents of `RefCell`s, `Mutex`es and `RwLock`s, which are borrowed
+    /// or locked while `f` is called.
+    ///
+    /// ```
+    /// use std::cell::RefCell;
+    /// use keypath::{Keyable, keypath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Document {
+    ///     cache: RefCell<Cache>,
+    /// }
+    ///
+    /// #[derive(Keyable)]
+    /// struct Cache {
+    ///     hits: u32,
+    /// }
+    ///
+    /// let doc = Document { cache: RefCell::new(Cache { hits: 3 }) };
+    /// let hits = keypath!(Document.cache.hits);
+    /// assert!(doc.try_any_at_path(&hits).is_err());
+    ///
+    /// let hits = doc.with_any_at_path(&hits, |hits| *hits.downcast_ref::<u32>().unwrap());
+    /// assert_eq!(hits.unwrap(), 3);
+    /// ```
+    fn with_any_at_path<R>(
+        &self,
+        path: impl AsRef<[internals::PathComponent]>,
+        f: impl FnOnce(&dyn Any) -> R,
+    ) -> Result<R, FieldError>
+    where
+        Self: Sized + 'static,
+    {
+        let path = path.as_ref();
+        let mut f = Some(f);
+        let mut result = None;
+        (self as &dyn internals::RawKeyable)
+            .with_field(path, &mut |value| {
+                // SAFETY: every value reachable from a 'static root is 'static
+                let value = unsafe { value.assume_static() }.as_any();
+                result = f.take().map(|f| f(value));
+            })
+            .map_err(|e| e.with_path::<Self>(path))?;
+        Ok(result.expect("the closure is called when traversal succeeds"))
     }
 
     //NOTE: these two methods are intended in cases where the keypath has not been
-    //validated, but currently we don't really support creating invalid keypaths.
+    //validated; if the value is not a `T`, they return a `TypeMismatch` error.
     #[doc(hidden)]
     fn try_item_at_path<T>(&self, path: &KeyPath<Self, T>) -> Result<&T, FieldError> {
-        self.try_any_at_path(path)
-            ////FIXME: no unwrap here, some new more expresesive error type instead
-            .map(|t| t.downcast_ref().unwrap())
+        self.get_field(path.as_ref())
+            // SAFETY: the value type of a `KeyPath` has the root's lifetimes
+            .and_then(|value| unsafe { value.downcast_ref() })
+            .map_err(|e| e.with_path::<Self>(path.as_ref()))
     }
 
     #[doc(hidden)]
     fn try_item_at_path_mut<T>(&mut self, path: &KeyPath<Self, T>) -> Result<&mut T, FieldError> {
-        self.try_any_at_path_mut(path)
-            //FIXME: no unwrap here, some new more expresesive error type instead
-            .map(|t| t.downcast_mut().unwrap())
+        self.get_field_mut(path.as_ref())
+            // SAFETY: the value type of a `KeyPath` has the root's lifetimes
+            .and_then(|value| unsafe { value.downcast_mut() })
+            .map_err(|e| e.with_path::<Self>(path.as_ref()))
     }
 
     /// Get a reference to the value at the provided path.
@@ -194,13 +620,8 @@ pub trait Keyable: internals::RawKeyable {
     /// syntax instead.
     ///
     /// Assuming the path was constructed with the [`keypath!`] macro, this
-    /// method will not fail.
-    ///
-    ///
-    /// # Panics
-    ///
-    /// I lied, it will fail if you provide an index into a collection and
-    /// that item does not exist.
+    /// method will not fail; paths that can fail at runtime, such as those
+    /// that index into a collection, are represented by [`FallibleKeyPath`].
     fn item_at_path<T>(&self, path: &KeyPath<Self, T>) -> &T {
         self.try_item_at_path(path).unwrap()
     }
@@ -211,29 +632,180 @@ pub trait Keyable: internals::RawKeyable {
     /// syntax instead.
     ///
     /// Assuming the path was constructed with the [`keypath!`] macro, this
-    /// method will not fail.
+    /// method will not fail; paths that can fail at runtime, such as those
+    /// that index into a collection, are represented by [`FallibleKeyPath`].
+    fn item_at_path_mut<T>(&mut self, path: &KeyPath<Self, T>) -> &mut T {
+        self.try_item_at_path_mut(path).unwrap()
+    }
+
+    /// Call `f` with a reference to the value at `path`, returning its result.
     ///
+    /// Unlike [`FallibleKeyPath::get`] and `[index]` syntax, this can also
+    /// read values that can only be borrowed within a scope, such as the
+    /// contents of a `RefCell` or `Mutex`, which are borrowed or locked while
+    /// `f` is called.
     ///
-    /// # Panics
+    /// ```
+    /// use std::sync::Mutex;
+    /// use keypath::{Keyable, keypath};
     ///
-    /// I lied, it will fail if you provide an index into a collection and
-    /// that item does not exist.
-    fn item_at_path_mut<T>(&mut self, path: &KeyPath<Self, T>) -> &mut T {
-        self.try_item_at_path_mut(path).unwrap()
+    /// #[derive(Keyable)]
+    /// struct Player {
+    ///     stats: Mutex<Stats>,
+    /// }
+    ///
+    /// #[derive(Keyable)]
+    /// struct Stats {
+    ///     scores: Vec<u32>,
+    /// }
+    ///
+    /// let player = Player { stats: Mutex::new(Stats { scores: vec![3, 5] }) };
+    /// let total = player.with_value(&keypath!(Player.stats.scores), |scores| scores.iter().sum::<u32>());
+    /// assert_eq!(total.unwrap(), 8);
+    /// let first = player.with_value(&keypath!(Player.stats.scores[0]), |score| *score * 2);
+    /// assert_eq!(first.unwrap(), 6);
+    /// ```
+    fn with_value<T, R>(
+        &self,
+        path: &impl TypedKeyPath<Self, T>,
+        f: impl FnOnce(&T) -> R,
+    ) -> Result<R, FieldError>
+    where
+        Self: Sized,
+    {
+        let path = path.as_ref();
+        let mut f = Some(f);
+        let mut result = None;
+        (self as &dyn internals::RawKeyable)
+            .with_field(path, &mut |value| {
+                // SAFETY: the value type of a typed keypath has the root's lifetimes
+                result =
+                    Some(unsafe { value.downcast_ref() }.map(|value| (f.take().unwrap())(value)));
+            })
+            .and_then(|()| result.expect("the closure is called when traversal succeeds"))
+            .map_err(|e| e.with_path::<Self>(path))
+    }
+
+    /// Call `f` with a mutable reference to the value at `path`, returning its
+    /// result.
+    ///
+    /// ```
+    /// use keypath::{Keyable, keypath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Counter {
+    ///     counts: Vec<u32>,
+    /// }
+    ///
+    /// let mut counter = Counter { counts: vec![0, 4] };
+    /// let count = counter.modify(&keypath!(Counter.counts[1]), |count| {
+    ///     *count += 1;
+    ///     *count
+    /// });
+    /// assert_eq!(count.unwrap(), 5);
+    /// assert!(counter.modify(&keypath!(Counter.counts[2]), |count| *count += 1).is_err());
+    /// ```
+    fn modify<T, R>(
+        &mut self,
+        path: &impl TypedKeyPath<Self, T>,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Result<R, FieldError>
+    where
+        Self: Sized,
+    {
+        let path = path.as_ref();
+        self.get_field_mut(path)
+            // SAFETY: the value type of a typed keypath has the root's lifetimes
+            .and_then(|value| unsafe { value.downcast_mut() })
+            .map(f)
+            .map_err(|e| e.with_path::<Self>(path))
     }
 }
 
-impl<Root: ?Sized, Value: 'static> AsRef<[internals::PathComponent]> for KeyPath<Root, Value> {
+impl<Root: ?Sized, Value> AsRef<[internals::PathComponent]> for KeyPath<Root, Value> {
     fn as_ref(&self) -> &[internals::PathComponent] {
         self.partial.fields.as_ref()
     }
 }
 
-impl<R: ?Sized> Clone for PartialKeyPath<R> {
-    fn clone(&self) -> Self {
-        PartialKeyPath {
-            fields: self.fields.clone(),
-            _root: PhantomData,
+/// Implement the std traits for a typed keypath, which wraps a `PartialKeyPath`.
+///
+/// These are implemented manually, so that they don't require the root and
+/// value types to implement them.
+macro_rules! typed_keypath_impls {
+    ($name:ident) => {
+        impl<Root: ?Sized, Value> Clone for $name<Root, Value> {
+            fn clone(&self) -> Self {
+                $name {
+                    partial: self.partial.clone(),
+                    _value: PhantomData,
+                }
+            }
         }
-    }
+
+        impl<Root: ?Sized, Value> std::fmt::Display for $name<Root, Value> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.partial.fmt(f)
+            }
+        }
+
+        impl<Root: ?Sized, Value> std::fmt::Debug for $name<Root, Value> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.debug_tuple(stringify!($name))
+                    .field(&format_args!("{}", self.partial))
+                    .finish()
+            }
+        }
+
+        impl<Root: ?Sized, Value> PartialEq for $name<Root, Value> {
+            fn eq(&self, other: &Self) -> bool {
+                self.partial == other.partial
+            }
+        }
+
+        impl<Root: ?Sized, Value> Eq for $name<Root, Value> {}
+
+        impl<Root: ?Sized, Value> std::hash::Hash for $name<Root, Value> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.partial.hash(state)
+            }
+        }
+
+        impl<Root: ?Sized, Value> PartialOrd for $name<Root, Value> {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl<Root: ?Sized, Value> Ord for $name<Root, Value> {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.partial.cmp(&other.partial)
+            }
+        }
+    };
+}
+
+typed_keypath_impls!(KeyPath);
+typed_keypath_impls!(FallibleKeyPath);
+typed_keypath_impls!(WildcardKeyPath);
+
+/// A keypath from `Root` to a `Value`: either a [`KeyPath`] or a
+/// [`FallibleKeyPath`].
+///
+/// This is used by methods that accept either kind of path, such as
+/// [`Keyable::modify`]. It can't be implemented outside of this crate, since
+/// those methods rely on the value type having been checked.
+pub trait TypedKeyPath<Root: ?Sized, Value>:
+    sealed::Sealed + AsRef<[internals::PathComponent]>
+{
+}
+
+impl<Root: ?Sized, Value> TypedKeyPath<Root, Value> for KeyPath<Root, Value> {}
+impl<Root: ?Sized, Value> TypedKeyPath<Root, Value> for FallibleKeyPath<Root, Value> {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl<Root: ?Sized, Value> Sealed for super::KeyPath<Root, Value> {}
+    impl<Root: ?Sized, Value> Sealed for super::FallibleKeyPath<Root, Value> {}
 }
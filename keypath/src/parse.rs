@@ -0,0 +1,395 @@
+//! Parsing keypaths from strings.
+//!
+//! The syntax is the same as that accepted by the [`keypath!`] macro, without
+//! the leading root type: `friends[0].name`, `shapes[1]::Circle.radius`,
+//! `pet?.name`, `servers["primary"].host` or `members[*].name`.
+//!
+//! Indices must be literals, in any form that the macro accepts: integers,
+//! which may be negative, and may have a radix prefix or a type suffix such as
+//! `5_u8`; strings and byte strings, which may be raw; characters; bytes; and
+//! `true` or `false`. Other keys, such as enums, are expressions in the macro,
+//! and can't be parsed.
+//!
+//! [`keypath!`]: crate::keypath
+
+use std::convert::TryFrom;
+
+use super::error::ParseError;
+use super::internals::PathComponent;
+use super::IndexKey;
+
+/// Parse a string into a sequence of path components.
+///
+/// This checks syntax only; the components are not checked against any type.
+pub(crate) fn parse_path(path: &str) -> Result<Vec<PathComponent>, ParseError> {
+    Parser { src: path, pos: 0 }.parse()
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Vec<PathComponent>, ParseError> {
+        let mut components = Vec::new();
+
+        // the first field does not need a leading '.'
+        if let Some(c) = self.peek() {
+            if c == '_' || c.is_alphanumeric() {
+                self.field(&mut components)?;
+            }
+        }
+
+        while let Some(c) = self.peek() {
+            match c {
+                '.' => {
+                    self.bump();
+                    self.field(&mut components)?;
+                }
+                '[' => {
+                    self.bump();
+                    components.push(self.index()?);
+                    self.expect(']', "expected ']'")?;
+                }
+                ':' => {
+                    self.bump();
+                    self.expect(':', "expected '::'")?;
+                    let name = self.ident()?;
+                    components.push(PathComponent::variant(name.to_owned()));
+                }
+                '?' => {
+                    self.bump();
+                    components.push(PathComponent::Optional);
+                }
+                _ => return Err(self.error("expected '.', '[', '::' or '?'")),
+            }
+        }
+        Ok(components)
+    }
+
+    /// A named or unnamed field, after the '.'.
+    ///
+    /// As in the macro, `0.1` is two unnamed fields.
+    fn field(&mut self, components: &mut Vec<PathComponent>) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                let idx = self.integer()?;
+                components.push(PathComponent::unnamed(idx));
+            }
+            Some(_) => {
+                let name = self.ident()?;
+                components.push(PathComponent::named(name.to_owned()));
+            }
+            None => return Err(self.error("expected a field")),
+        }
+        Ok(())
+    }
+
+    /// The contents of an index, between the brackets.
+    fn index(&mut self) -> Result<PathComponent, ParseError> {
+        let rest = self.rest();
+        match self.peek() {
+            Some('*') => {
+                self.bump();
+                Ok(PathComponent::Wildcard)
+            }
+            Some(c) if c.is_ascii_digit() => self.integer_key(false),
+            Some('-') => {
+                self.bump();
+                match self.peek() {
+                    Some(c) if c.is_ascii_digit() => self.integer_key(true),
+                    _ => Err(self.error("expected an integer")),
+                }
+            }
+            Some('"') => self.string().map(PathComponent::index_str),
+            Some('r') if rest.starts_with("r\"") || rest.starts_with("r#") => {
+                self.raw_string().map(PathComponent::index_str)
+            }
+            Some('\'') => self.char_literal().map(|c| c.to_path_component()),
+            Some('b') if rest.starts_with("b\"") || rest.starts_with("br") => {
+                let start = self.pos;
+                self.bump();
+                let string = match self.peek() {
+                    Some('"') => self.string()?,
+                    _ => self.raw_string()?,
+                };
+                match string.is_ascii() {
+                    true => Ok(PathComponent::index_str(string)),
+                    false => Err(ParseError::Syntax {
+                        position: start,
+                        message: "byte string keys must be ASCII",
+                    }),
+                }
+            }
+            Some('b') if rest.starts_with("b'") => {
+                let start = self.pos;
+                self.bump();
+                match self.char_literal()? {
+                    c if c.is_ascii() => Ok((c as u8).to_path_component()),
+                    _ => Err(ParseError::Syntax {
+                        position: start,
+                        message: "byte literals must be ASCII",
+                    }),
+                }
+            }
+            Some(_) if self.keyword("true") => Ok(true.to_path_component()),
+            Some(_) if self.keyword("false") => Ok(false.to_path_component()),
+            _ => Err(self.error("expected a literal or '*'")),
+        }
+    }
+
+    /// Consume `word` if it is the next identifier.
+    fn keyword(&mut self, word: &str) -> bool {
+        let rest = self.rest();
+        let is_word = rest.starts_with(word)
+            && !matches!(rest[word.len()..].chars().next(), Some(c) if c == '_' || c.is_alphanumeric());
+        if is_word {
+            self.pos += word.len();
+        }
+        is_word
+    }
+
+    fn ident(&mut self) -> Result<&str, ParseError> {
+        // raw identifiers are stored without their prefix
+        if self.rest().starts_with("r#") {
+            self.pos += 2;
+        }
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c == '_' || c.is_alphabetic() => self.bump(),
+            _ => return Err(self.error("expected an identifier")),
+        }
+        while let Some(c) = self.peek() {
+            if c == '_' || c.is_alphanumeric() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        Ok(&self.src[start..self.pos])
+    }
+
+    fn integer(&mut self) -> Result<usize, ParseError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        self.src[start..self.pos]
+            .parse()
+            .map_err(|_| ParseError::Syntax {
+                position: start,
+                message: "integer is too large",
+            })
+    }
+
+    /// An integer index, after any '-'.
+    ///
+    /// As in the macro, this may have a radix prefix, such as `0xff`, and a
+    /// type suffix, such as `5_u8`, which makes it a key of that type.
+    /// Negative integers without a suffix are `i128` keys.
+    fn integer_key(&mut self, negative: bool) -> Result<PathComponent, ParseError> {
+        let start = self.pos - negative as usize;
+        let radix = match self.rest().get(..2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.pos += 2;
+        }
+        let digits_start = self.pos;
+        while matches!(self.peek(), Some(c) if c == '_' || c.is_digit(radix)) {
+            self.bump();
+        }
+        let digits = self.src[digits_start..self.pos].replace('_', "");
+        let suffix_start = self.pos;
+        while matches!(self.peek(), Some(c) if c == '_' || c.is_alphanumeric()) {
+            self.bump();
+        }
+        let error = |message| ParseError::Syntax {
+            position: start,
+            message,
+        };
+        let value =
+            u128::from_str_radix(&digits, radix).map_err(|_| error("invalid integer literal"))?;
+
+        macro_rules! typed {
+            ($suffix:expr, $($int:ident),*) => {
+                match $suffix {
+                    $(stringify!($int) => integer_as::<$int>(value, negative),)*
+                    _ => return Err(error("invalid integer suffix")),
+                }
+            };
+        }
+        let suffix = &self.src[suffix_start..self.pos];
+        let component = match suffix {
+            "" if negative => integer_as::<i128>(value, true),
+            "" => usize::try_from(value).ok().map(PathComponent::index_int),
+            _ => typed!(suffix, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize),
+        };
+        component.ok_or_else(|| error("integer is too large"))
+    }
+
+    /// A character literal, such as `'a'` or `'\n'`.
+    fn char_literal(&mut self) -> Result<char, ParseError> {
+        let start = self.pos;
+        self.bump();
+        let c = match self.peek() {
+            Some('\\') => {
+                self.bump();
+                self.escape()?
+            }
+            Some(c) if c != '\'' => {
+                self.bump();
+                c
+            }
+            _ => return Err(self.error("expected a character")),
+        };
+        match self.peek() {
+            Some('\'') => {
+                self.bump();
+                Ok(c)
+            }
+            _ => Err(ParseError::Syntax {
+                position: start,
+                message: "unterminated character literal",
+            }),
+        }
+    }
+
+    /// A string literal, with the same escapes as in Rust.
+    fn string(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        self.bump();
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(ParseError::Syntax {
+                        position: start,
+                        message: "unterminated string literal",
+                    })
+                }
+                Some('"') => {
+                    self.bump();
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.bump();
+                    result.push(self.escape()?);
+                }
+                Some(c) => {
+                    self.bump();
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let start = self.pos - 1;
+        let invalid = ParseError::Syntax {
+            position: start,
+            message: "invalid escape in string literal",
+        };
+        let c = self.peek().ok_or_else(|| invalid.clone())?;
+        self.bump();
+        match c {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            '\\' | '\'' | '"' => Ok(c),
+            'x' => {
+                let hex = self.rest().get(..2).ok_or_else(|| invalid.clone())?;
+                let value = u8::from_str_radix(hex, 16).map_err(|_| invalid.clone())?;
+                self.pos += 2;
+                if value > 0x7f {
+                    return Err(invalid);
+                }
+                Ok(value as char)
+            }
+            'u' => {
+                let rest = self.rest();
+                let end = rest.find('}').ok_or_else(|| invalid.clone())?;
+                let hex = rest[..end]
+                    .strip_prefix('{')
+                    .ok_or_else(|| invalid.clone())?;
+                let value = u32::from_str_radix(hex, 16).map_err(|_| invalid.clone())?;
+                self.pos += end + 1;
+                std::char::from_u32(value).ok_or(invalid)
+            }
+            _ => Err(invalid),
+        }
+    }
+
+    /// A raw string literal, such as `r"key"` or `r#"key"#`.
+    fn raw_string(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        self.bump();
+        let hashes = self.rest().len() - self.rest().trim_start_matches('#').len();
+        self.pos += hashes;
+        self.expect('"', "expected '\"'")?;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        match self.rest().find(&terminator) {
+            Some(end) => {
+                let result = self.rest()[..end].to_owned();
+                self.pos += end + terminator.len();
+                Ok(result)
+            }
+            None => Err(ParseError::Syntax {
+                position: start,
+                message: "unterminated string literal",
+            }),
+        }
+    }
+
+    fn expect(&mut self, c: char, message: &'static str) -> Result<(), ParseError> {
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError::Syntax {
+            position: self.pos,
+            message,
+        }
+    }
+}
+
+/// The component for the integer `value`, or `-value` if `negative`, as a
+/// key of type `T`, if it is in range.
+fn integer_as<T>(value: u128, negative: bool) -> Option<PathComponent>
+where
+    T: TryFrom<u128> + TryFrom<i128> + IndexKey,
+{
+    let int = match negative {
+        true => T::try_from(i128::try_from(value).ok()?.checked_neg()?).ok()?,
+        false => T::try_from(value).ok()?,
+    };
+    Some(int.to_path_component())
+}
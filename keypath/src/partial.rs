@@ -0,0 +1,387 @@
+//! Type-erased keypaths.
+
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use super::internals::{PathComponent, TypeInfo};
+use super::{error, parse, FallibleKeyPath, FieldError, KeyPath, Keyable, ParseError};
+
+/// A keypath for a known root type, which doesn't know the destination type.
+///
+/// Any [`KeyPath`] or [`FallibleKeyPath`] can be converted into a
+/// `PartialKeyPath`, which is useful for storing paths to values of different
+/// types together; the original path can be recovered with [`downcast`] or
+/// [`downcast_fallible`].
+///
+/// # Examples
+///
+/// ```
+/// use keypath::{Keyable, KeyPath, PartialKeyPath, keypath};
+///
+/// #[derive(Keyable)]
+/// struct Person {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// let columns: Vec<PartialKeyPath<Person>> = vec![
+///     keypath!(Person.name).into(),
+///     keypath!(Person.age).into(),
+/// ];
+///
+/// let person = Person { name: "coco".into(), age: 7 };
+/// let age: KeyPath<Person, u8> = columns[1].downcast().unwrap();
+/// assert_eq!(person[&age], 7);
+///
+/// // the wrong type fails
+/// assert!(columns[0].downcast::<u8>().is_none());
+///
+/// // values can also be accessed without downcasting the path
+/// let name = columns[0].get(&person).unwrap();
+/// assert_eq!(name.downcast_ref::<String>().unwrap(), "coco");
+/// ```
+///
+/// [`downcast`]: PartialKeyPath::downcast
+/// [`downcast_fallible`]: PartialKeyPath::downcast_fallible
+pub struct PartialKeyPath<Root: ?Sized> {
+    pub(crate) fields: Cow<'static, [PathComponent]>,
+    pub(crate) value: TypeInfo,
+    pub(crate) _root: PhantomData<fn(&Root) -> &Root>,
+}
+
+/// A keypath that knows neither its root nor its destination type.
+///
+/// Any keypath can be converted into an `AnyKeyPath`, and it can be downcast
+/// back to a typed keypath, if the types match.
+///
+/// # Examples
+///
+/// ```
+/// use keypath::{AnyKeyPath, Keyable, KeyPath, keypath};
+///
+/// #[derive(Keyable)]
+/// struct Person {
+///     name: String,
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Pet {
+///     name: String,
+/// }
+///
+/// let paths: Vec<AnyKeyPath> = vec![keypath!(Person.name).into(), keypath!(Pet.name).into()];
+///
+/// assert!(paths[0].downcast::<Person, String>().is_some());
+/// assert!(paths[1].downcast::<Person, String>().is_none());
+/// assert!(paths[1].downcast_partial::<Pet>().is_some());
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnyKeyPath {
+    fields: Cow<'static, [PathComponent]>,
+    root: TypeInfo,
+    value: TypeInfo,
+}
+
+impl<Root: ?Sized> PartialKeyPath<Root> {
+    /// The `TypeId` of the value this path resolves to.
+    pub fn value_type_id(&self) -> TypeId {
+        self.value.type_id()
+    }
+
+    /// The name of the type of the value this path resolves to.
+    pub fn value_type_name(&self) -> &'static str {
+        self.value.type_name()
+    }
+
+    /// Returns `true` if this path can fail at runtime, such as if it
+    /// indexes into a collection.
+    ///
+    /// Such a path can only be downcast to a [`FallibleKeyPath`].
+    pub fn is_fallible(&self) -> bool {
+        self.fields.iter().any(PathComponent::is_fallible)
+    }
+
+    /// Attempt to convert this into a [`KeyPath`] with the given value type.
+    ///
+    /// Returns `None` if the value type does not match, or if the path
+    /// [is fallible]. Since nothing ties the lifetimes of the value type to
+    /// those of the root, both types must be `'static`.
+    ///
+    /// [is fallible]: PartialKeyPath::is_fallible
+    pub fn downcast<Value: 'static>(&self) -> Option<KeyPath<Root, Value>>
+    where
+        Root: 'static,
+    {
+        if self.is_fallible() || !self.value.is::<Value>() {
+            return None;
+        }
+        Some(KeyPath {
+            partial: self.clone(),
+            _value: PhantomData,
+        })
+    }
+
+    /// Attempt to convert this into a [`FallibleKeyPath`] with the given
+    /// value type.
+    ///
+    /// Returns `None` if the value type does not match. As with [`downcast`],
+    /// both types must be `'static`.
+    ///
+    /// [`downcast`]: PartialKeyPath::downcast
+    pub fn downcast_fallible<Value: 'static>(&self) -> Option<FallibleKeyPath<Root, Value>>
+    where
+        Root: 'static,
+    {
+        if !self.value.is::<Value>() {
+            return None;
+        }
+        Some(FallibleKeyPath {
+            partial: self.clone(),
+            _value: PhantomData,
+        })
+    }
+}
+
+impl<Root: Keyable> PartialKeyPath<Root> {
+    /// Parse a path from a string, checking it against the root type.
+    ///
+    /// The syntax is the same as for the [`keypath!`] macro, without the root
+    /// type. If a component does not exist on the type it is applied to, the
+    /// returned error includes that component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use keypath::{Keyable, PartialKeyPath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Person {
+    ///     name: String,
+    ///     friends: Vec<Person>,
+    /// }
+    ///
+    /// let person = Person {
+    ///     name: "coco".into(),
+    ///     friends: vec![Person { name: "eli".into(), friends: Vec::new() }],
+    /// };
+    ///
+    /// let path = PartialKeyPath::<Person>::parse("friends[0].name").unwrap();
+    /// let name = person.try_any_at_path(&path).unwrap();
+    /// assert_eq!(name.downcast_ref::<String>().unwrap(), "eli");
+    ///
+    /// let err = PartialKeyPath::<Person>::parse("friends[0].nmae").err().unwrap();
+    /// assert!(err.to_string().contains(".nmae"));
+    /// ```
+    ///
+    /// [`keypath!`]: crate::keypath
+    pub fn parse(path: &str) -> Result<Self, ParseError> {
+        let fields = parse::parse_path(path)?;
+        let value = Root::type_at_path(&fields)
+            .map_err(|e| ParseError::InvalidPath(e.with_path::<Root>(&fields)))?;
+        Ok(PartialKeyPath {
+            fields: Cow::Owned(fields),
+            value,
+            _root: PhantomData,
+        })
+    }
+
+    /// Attempt to get a reference to the value at this path.
+    ///
+    /// Only `'static` types can be [`Any`], so this requires a `'static` root.
+    pub fn get<'a>(&self, root: &'a Root) -> Result<&'a dyn Any, FieldError>
+    where
+        Root: 'static,
+    {
+        root.try_any_at_path(self)
+    }
+
+    /// Attempt to get a mutable reference to the value at this path.
+    pub fn get_mut<'a>(&self, root: &'a mut Root) -> Result<&'a mut dyn Any, FieldError>
+    where
+        Root: 'static,
+    {
+        root.try_any_at_path_mut(self)
+    }
+}
+
+impl AnyKeyPath {
+    /// The `TypeId` of the root type of this path.
+    pub fn root_type_id(&self) -> TypeId {
+        self.root.type_id()
+    }
+
+    /// The name of the root type of this path.
+    pub fn root_type_name(&self) -> &'static str {
+        self.root.type_name()
+    }
+
+    /// The `TypeId` of the value this path resolves to.
+    pub fn value_type_id(&self) -> TypeId {
+        self.value.type_id()
+    }
+
+    /// The name of the type of the value this path resolves to.
+    pub fn value_type_name(&self) -> &'static str {
+        self.value.type_name()
+    }
+
+    /// Returns `true` if this path can fail at runtime, such as if it
+    /// indexes into a collection.
+    pub fn is_fallible(&self) -> bool {
+        self.fields.iter().any(PathComponent::is_fallible)
+    }
+
+    /// Attempt to convert this into a [`PartialKeyPath`] with the given root type.
+    ///
+    /// Returns `None` if the root type does not match.
+    pub fn downcast_partial<Root: ?Sized + 'static>(&self) -> Option<PartialKeyPath<Root>> {
+        if !self.root.is::<Root>() {
+            return None;
+        }
+        Some(PartialKeyPath {
+            fields: self.fields.clone(),
+            value: self.value,
+            _root: PhantomData,
+        })
+    }
+
+    /// Attempt to convert this into a [`KeyPath`] with the given root and
+    /// value types.
+    ///
+    /// Returns `None` if either type does not match, or if the path
+    /// [is fallible].
+    ///
+    /// [is fallible]: AnyKeyPath::is_fallible
+    pub fn downcast<Root: ?Sized + 'static, Value: 'static>(&self) -> Option<KeyPath<Root, Value>> {
+        self.downcast_partial()
+            .and_then(|partial| partial.downcast())
+    }
+
+    /// Attempt to convert this into a [`FallibleKeyPath`] with the given root
+    /// and value types.
+    ///
+    /// Returns `None` if either type does not match.
+    pub fn downcast_fallible<Root: ?Sized + 'static, Value: 'static>(
+        &self,
+    ) -> Option<FallibleKeyPath<Root, Value>> {
+        self.downcast_partial()
+            .and_then(|partial| partial.downcast_fallible())
+    }
+}
+
+impl<Root: ?Sized, Value> From<KeyPath<Root, Value>> for PartialKeyPath<Root> {
+    fn from(src: KeyPath<Root, Value>) -> Self {
+        src.partial
+    }
+}
+
+impl<Root: ?Sized, Value> From<FallibleKeyPath<Root, Value>> for PartialKeyPath<Root> {
+    fn from(src: FallibleKeyPath<Root, Value>) -> Self {
+        src.partial
+    }
+}
+
+impl<Root: ?Sized + 'static> From<PartialKeyPath<Root>> for AnyKeyPath {
+    fn from(src: PartialKeyPath<Root>) -> Self {
+        AnyKeyPath {
+            fields: src.fields,
+            root: TypeInfo::of::<Root>(),
+            value: src.value,
+        }
+    }
+}
+
+impl<Root: ?Sized + 'static, Value: 'static> From<KeyPath<Root, Value>> for AnyKeyPath {
+    fn from(src: KeyPath<Root, Value>) -> Self {
+        src.partial.into()
+    }
+}
+
+impl<Root: ?Sized + 'static, Value: 'static> From<FallibleKeyPath<Root, Value>> for AnyKeyPath {
+    fn from(src: FallibleKeyPath<Root, Value>) -> Self {
+        src.partial.into()
+    }
+}
+
+impl<Root: ?Sized> AsRef<[PathComponent]> for PartialKeyPath<Root> {
+    fn as_ref(&self) -> &[PathComponent] {
+        self.fields.as_ref()
+    }
+}
+
+impl AsRef<[PathComponent]> for AnyKeyPath {
+    fn as_ref(&self) -> &[PathComponent] {
+        self.fields.as_ref()
+    }
+}
+
+impl<R: ?Sized> Clone for PartialKeyPath<R> {
+    fn clone(&self) -> Self {
+        PartialKeyPath {
+            fields: self.fields.clone(),
+            value: self.value,
+            _root: PhantomData,
+        }
+    }
+}
+
+impl<Root: ?Sized> fmt::Display for PartialKeyPath<Root> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::write_path(f, std::any::type_name::<Root>(), &self.fields)
+    }
+}
+
+impl<Root: ?Sized> fmt::Debug for PartialKeyPath<Root> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PartialKeyPath")
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
+
+impl<Root: ?Sized> PartialEq for PartialKeyPath<Root> {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields && self.value == other.value
+    }
+}
+
+impl<Root: ?Sized> Eq for PartialKeyPath<Root> {}
+
+impl<Root: ?Sized> Hash for PartialKeyPath<Root> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fields.hash(state);
+        self.value.hash(state);
+    }
+}
+
+impl<Root: ?Sized> PartialOrd for PartialKeyPath<Root> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Root: ?Sized> Ord for PartialKeyPath<Root> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.fields
+            .cmp(&other.fields)
+            .then_with(|| self.value.cmp(&other.value))
+    }
+}
+
+impl fmt::Display for AnyKeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::write_path(f, self.root.type_name(), &self.fields)
+    }
+}
+
+impl fmt::Debug for AnyKeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("AnyKeyPath")
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
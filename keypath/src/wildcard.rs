@@ -0,0 +1,320 @@
+//! Keypaths that match many values.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use super::internals::{self, PathComponent, RawKeyable, TypeInfo};
+use super::{error, FieldError, FieldErrorKind, Keyable, ParseError, PartialKeyPath};
+
+/// A keypath that matches every item of one or more collections.
+///
+/// This is created by the [`keypath!`] macro when a path includes a wildcard,
+/// `[*]`, in place of an index, such as `Team.members[*].name`. Each wildcard
+/// matches every item of a sequence, or every value of a map.
+///
+/// Items that the rest of the path doesn't resolve on, such as a `None`
+/// after a `?`, are not matches. Neither are values inside a `RefCell`,
+/// `Mutex` or `RwLock`, except through the methods that take a mutable root.
+/// Those methods return an error if they pass through a value that can't be
+/// borrowed mutably, such as a shared `Rc`. The values of a `HashMap` are matched in an arbitrary order.
+///
+/// # Examples
+///
+/// ```
+/// use keypath::{Keyable, WildcardKeyPath, keypath, internals::PathComponent};
+///
+/// #[derive(Keyable)]
+/// struct Team {
+///     members: Vec<Person>,
+/// }
+///
+/// #[derive(Keyable)]
+/// struct Person {
+///     name: String,
+///     pet: Option<String>,
+/// }
+///
+/// let mut team = Team {
+///     members: vec![
+///         Person { name: "coco".into(), pet: None },
+///         Person { name: "eli".into(), pet: Some("jojo".into()) },
+///     ],
+/// };
+///
+/// let names: WildcardKeyPath<Team, String> = keypath!(Team.members[*].name);
+/// assert_eq!(names.iter(&team).collect::<Vec<_>>(), ["coco", "eli"]);
+///
+/// names.for_each_mut(&mut team, |_, name| name.make_ascii_uppercase()).unwrap();
+/// assert_eq!(team.members[1].name, "ELI");
+///
+/// let pets = keypath!(Team.members[*].pet?);
+/// let (keys, pet) = pets.matches(&team).next().unwrap();
+/// assert_eq!(keys, [PathComponent::IndexInt(1)]);
+/// assert_eq!(pet, "jojo");
+/// ```
+///
+/// [`keypath!`]: crate::keypath
+pub struct WildcardKeyPath<Root: ?Sized, Value> {
+    pub(crate) partial: PartialKeyPath<Root>,
+    pub(crate) _value: PhantomData<fn(Value) -> Value>,
+}
+
+impl<Root: Keyable, Value> WildcardKeyPath<Root, Value> {
+    /// Create a new typed `WildcardKeyPath` from the provided fields.
+    ///
+    /// As with [`FallibleKeyPath`](crate::FallibleKeyPath), this does not
+    /// ensure the path is valid; the value type is inferred from a closure over
+    /// the root's mirror, which is never called.
+    ///
+    /// # Safety
+    ///
+    /// The same as for `KeyPath::__conjure_from_abyss`.
+    #[doc(hidden)]
+    pub const unsafe fn __conjure_from_abyss<F>(value: F, fields: &'static [PathComponent]) -> Self
+    where
+        F: FnOnce(Root::Mirror) -> PhantomData<Value>,
+    {
+        internals::forget_mirror_closure(value);
+        WildcardKeyPath {
+            partial: PartialKeyPath {
+                fields: Cow::Borrowed(fields),
+                value: TypeInfo::of::<Value>(),
+                _root: PhantomData,
+            },
+            _value: PhantomData,
+        }
+    }
+
+    /// Create a new typed `WildcardKeyPath` from fields that are only known
+    /// at runtime, such as when the path also contains an index variable.
+    ///
+    /// # Safety
+    ///
+    /// The same as for `KeyPath::__conjure_from_abyss`.
+    #[doc(hidden)]
+    pub unsafe fn __conjure_from_abyss_owned<F>(value: F, fields: Vec<PathComponent>) -> Self
+    where
+        F: FnOnce(Root::Mirror) -> PhantomData<Value>,
+    {
+        internals::forget_mirror_closure(value);
+        WildcardKeyPath {
+            partial: PartialKeyPath {
+                fields: Cow::Owned(fields),
+                value: TypeInfo::of::<Value>(),
+                _root: PhantomData,
+            },
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<Root: Keyable + 'static, Value: 'static> WildcardKeyPath<Root, Value> {
+    /// Parse a `WildcardKeyPath` from a string, checking it against the root
+    /// type.
+    ///
+    /// The syntax is the same as for [`FallibleKeyPath::parse`], with
+    /// wildcards, `[*]`, in place of any indices. As with
+    /// [`KeyPath::parse`](crate::KeyPath::parse), both types must be
+    /// `'static`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use keypath::{Keyable, WildcardKeyPath};
+    ///
+    /// #[derive(Keyable)]
+    /// struct Team {
+    ///     members: Vec<String>,
+    /// }
+    ///
+    /// let team = Team { members: vec!["coco".into(), "eli".into()] };
+    /// let names = WildcardKeyPath::<Team, String>::parse("members[*]").unwrap();
+    /// assert_eq!(names.iter(&team).collect::<Vec<_>>(), ["coco", "eli"]);
+    /// assert_eq!(names.to_string(), "Team.members[*]");
+    /// ```
+    ///
+    /// [`FallibleKeyPath::parse`]: crate::FallibleKeyPath::parse
+    pub fn parse(path: &str) -> Result<Self, ParseError> {
+        let partial = PartialKeyPath::parse(path)?;
+        error::check_value_type::<Value>(partial.value)?;
+        Ok(WildcardKeyPath {
+            partial,
+            _value: PhantomData,
+        })
+    }
+}
+
+impl<Root: Keyable, Value> WildcardKeyPath<Root, Value> {
+    /// Return an iterator over the values that this path matches.
+    pub fn iter<'a>(&self, root: &'a Root) -> impl Iterator<Item = &'a Value>
+    where
+        Value: 'a,
+    {
+        self.matches(root).map(|(_, value)| value)
+    }
+
+    /// Return an iterator over mutable references to the values that this
+    /// path matches.
+    ///
+    /// Returns an error if a value on the path can't be borrowed mutably, such
+    /// as the contents of a shared `Rc`.
+    pub fn iter_mut<'a>(
+        &self,
+        root: &'a mut Root,
+    ) -> Result<impl Iterator<Item = &'a mut Value>, FieldError>
+    where
+        Value: 'a,
+    {
+        Ok(self.matches_mut(root)?.map(|(_, value)| value))
+    }
+
+    /// Return an iterator over the values that this path matches, along with
+    /// the index or key that each wildcard matched to reach them.
+    ///
+    /// The keys are in the order of the wildcards in the path.
+    pub fn matches<'a>(
+        &self,
+        root: &'a Root,
+    ) -> impl Iterator<Item = (Vec<PathComponent>, &'a Value)>
+    where
+        Value: 'a,
+    {
+        let mut found = Vec::new();
+        collect(root, self.as_ref(), &mut Vec::new(), &mut found);
+        found.into_iter().filter_map(|(keys, value)| {
+            // SAFETY: the value type of a typed keypath has the root's lifetimes
+            let value = unsafe { value.downcast_ref() }.ok()?;
+            Some((keys, value))
+        })
+    }
+
+    /// Return an iterator over mutable references to the values that this
+    /// path matches, along with the index or key that each wildcard matched.
+    ///
+    /// Returns an error if a value on the path can't be borrowed mutably, as
+    /// for [`iter_mut`](WildcardKeyPath::iter_mut).
+    pub fn matches_mut<'a>(
+        &self,
+        root: &'a mut Root,
+    ) -> Result<impl Iterator<Item = (Vec<PathComponent>, &'a mut Value)>, FieldError>
+    where
+        Value: 'a,
+    {
+        let path = self.as_ref();
+        let mut found = Vec::new();
+        collect_mut(root, path, &mut Vec::new(), &mut found)
+            .map_err(|e| e.with_path::<Root>(path))?;
+        Ok(found.into_iter().filter_map(|(keys, value)| {
+            // SAFETY: as for `matches`
+            let value = unsafe { value.downcast_mut() }.ok()?;
+            Some((keys, value))
+        }))
+    }
+
+    /// Call `f` with each value that this path matches, along with the index
+    /// or key that each wildcard matched to reach it.
+    ///
+    /// Returns an error, without calling `f`, if a value on the path can't be
+    /// borrowed mutably, as for [`iter_mut`](WildcardKeyPath::iter_mut).
+    pub fn for_each_mut(
+        &self,
+        root: &mut Root,
+        mut f: impl FnMut(&[PathComponent], &mut Value),
+    ) -> Result<(), FieldError> {
+        for (keys, value) in self.matches_mut(root)? {
+            f(&keys, value);
+        }
+        Ok(())
+    }
+}
+
+/// Find the values at `path` from `node`, expanding each wildcard into every
+/// item of the collection it is applied to.
+///
+/// `keys` holds the components that the wildcards before `node` matched.
+fn collect<'a>(
+    node: &'a dyn RawKeyable,
+    path: &[PathComponent],
+    keys: &mut Vec<PathComponent>,
+    found: &mut Vec<(Vec<PathComponent>, &'a dyn RawKeyable)>,
+) {
+    let wildcard = match path.iter().position(|c| *c == PathComponent::Wildcard) {
+        Some(wildcard) => wildcard,
+        None => {
+            if let Ok(value) = node.get_field(path) {
+                found.push((keys.clone(), value));
+            }
+            return;
+        }
+    };
+    if let Ok(collection) = node.get_field(&path[..wildcard]) {
+        collection.for_each_item(&mut |key, item| {
+            keys.push(key);
+            collect(item, &path[wildcard + 1..], keys, found);
+            keys.pop();
+        });
+    }
+}
+
+/// The same as [`collect`], for mutable references.
+///
+/// Values that can't be borrowed mutably, such as the contents of a shared
+/// `Rc`, are errors rather than values that don't match.
+fn collect_mut<'a>(
+    node: &'a mut dyn RawKeyable,
+    path: &[PathComponent],
+    keys: &mut Vec<PathComponent>,
+    found: &mut Vec<(Vec<PathComponent>, &'a mut dyn RawKeyable)>,
+) -> Result<(), FieldError> {
+    let wildcard = match path.iter().position(|c| *c == PathComponent::Wildcard) {
+        Some(wildcard) => wildcard,
+        None => {
+            match node.get_field_mut(path) {
+                Ok(value) => found.push((keys.clone(), value)),
+                Err(err) if is_borrow_error(&err) => return Err(err),
+                Err(_) => (),
+            }
+            return Ok(());
+        }
+    };
+    let collection = match node.get_field_mut(&path[..wildcard]) {
+        Ok(collection) => collection,
+        Err(err) if is_borrow_error(&err) => {
+            // the depth is relative to the subpath; fix it up
+            return Err(FieldError {
+                depth: err.depth + path.len() - wildcard,
+                ..err
+            });
+        }
+        Err(_) => return Ok(()),
+    };
+    let mut result = Ok(());
+    collection
+        .for_each_item_mut(&mut |key, item| {
+            if result.is_ok() {
+                keys.push(key);
+                result = collect_mut(item, &path[wildcard + 1..], keys, found);
+                keys.pop();
+            }
+        })
+        .map_err(|err| FieldError {
+            depth: err.depth + path.len() - wildcard - 1,
+            ..err
+        })?;
+    result
+}
+
+/// Returns `true` if the error is because a value couldn't be borrowed,
+/// rather than because the path doesn't resolve on it.
+fn is_borrow_error(err: &FieldError) -> bool {
+    matches!(
+        err.kind,
+        FieldErrorKind::SharedPointer | FieldErrorKind::Poisoned
+    )
+}
+
+impl<Root: ?Sized, Value> AsRef<[PathComponent]> for WildcardKeyPath<Root, Value> {
+    fn as_ref(&self) -> &[PathComponent] {
+        self.partial.fields.as_ref()
+    }
+}
@@ -0,0 +1,114 @@
+//! typed keypath accessors for derived structs
+
+use std::collections::HashMap;
+
+use keypath::{keypath, KeyPath, Keyable};
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    size: Size,
+    #[keypath(rename = "nick")]
+    nickname: Option<String>,
+    #[keypath(opaque)]
+    notes: HashMap<String, String>,
+    #[keypath(skip)]
+    #[allow(dead_code)]
+    secret: std::rc::Rc<str>,
+    friends: Vec<Person>,
+}
+
+#[derive(Keyable)]
+struct Size {
+    heft: u8,
+    shape: Shape,
+}
+
+#[derive(Keyable)]
+struct Shape(u16, #[keypath(skip)] (), u32);
+
+#[derive(Keyable)]
+struct Pair<'a, T> {
+    left: T,
+    right: &'a str,
+}
+
+fn coco() -> Person {
+    Person {
+        name: "coco".into(),
+        size: Size {
+            heft: 7,
+            shape: Shape(1, (), 2),
+        },
+        nickname: None,
+        notes: HashMap::new(),
+        secret: "shh".into(),
+        friends: Vec::new(),
+    }
+}
+
+#[test]
+fn nested_fields() {
+    let mut person = coco();
+    let paths = Person::keypaths();
+    assert_eq!(person[&paths.name], "coco");
+    assert_eq!(person[&paths.size.heft], 7);
+    assert_eq!(person[&paths.size.shape.2], 2);
+
+    person[&paths.size.shape.0] = 5;
+    assert_eq!(person.size.shape.0, 5);
+    assert_eq!(paths.size.shape.0, keypath!(Person.size.shape.0));
+    assert_eq!(paths.size.heft, keypath!(Person.size.heft));
+}
+
+#[test]
+fn struct_fields_deref_to_their_path() {
+    let person = coco();
+    let paths = Person::keypaths();
+    let size: &KeyPath<Person, Size> = &paths.size;
+    assert_eq!(size, &keypath!(Person.size));
+    assert_eq!(person[&*paths.size].heft, 7);
+    assert_eq!(*Person::keypaths(), keypath!(Person));
+}
+
+#[test]
+fn attributes() {
+    let mut person = coco();
+    let paths = Person::keypaths();
+    let notes: &KeyPath<Person, HashMap<String, String>> = &paths.notes;
+    person[notes].insert("likes".into(), "fish".into());
+    assert_eq!(person.notes["likes"], "fish");
+
+    assert_eq!(paths.nick, keypath!(Person.nick));
+    person.nickname = Some("co".into());
+    assert_eq!(person[&paths.nick].as_deref(), Some("co"));
+}
+
+#[test]
+fn composition() {
+    let mut person = coco();
+    person.friends.push(coco());
+
+    let heft = Person::keypaths().size.heft.clone();
+    let friend_heft = keypath!(Person.friends[0]).append(&heft.into());
+    assert_eq!(friend_heft.get(&person).unwrap(), &7);
+
+    let size = Person::keypaths().size;
+    let shape = size.append(&Size::keypaths().shape.0);
+    assert_eq!(person[&shape], 1);
+}
+
+#[test]
+fn generics_and_lifetimes() {
+    let source = String::from("eli");
+    let pair = Pair {
+        left: Size {
+            heft: 3,
+            shape: Shape(0, (), 0),
+        },
+        right: &source,
+    };
+    let paths = Pair::<Size>::keypaths();
+    assert_eq!(pair[&paths.left.heft], 3);
+    assert_eq!(pair[&paths.right], "eli");
+}
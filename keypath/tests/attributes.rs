@@ -0,0 +1,208 @@
+//! `#[keypath(...)]` field attributes
+
+use keypath::internals::PathComponent;
+use keypath::{keypath, FallibleKeyPath, KeyPath, Keyable};
+
+/// Stands in for a type from another crate, which does not implement `Keyable`.
+#[derive(Debug, PartialEq)]
+struct Timestamp(u64);
+
+#[derive(Keyable)]
+struct Event {
+    #[keypath(rename = "title")]
+    name: String,
+    #[keypath(opaque)]
+    at: Timestamp,
+    #[keypath(skip)]
+    cache: std::cell::Cell<u32>,
+    #[keypath(opaque)]
+    tags: Option<Timestamp>,
+    #[keypath(rename = "kind")]
+    r#type: Kind,
+}
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Kind {
+    Meeting {
+        #[keypath(opaque)]
+        room: Timestamp,
+        #[keypath(skip)]
+        notes: std::rc::Rc<str>,
+    },
+    Reminder(#[keypath(skip)] std::rc::Rc<str>, u8),
+}
+
+fn event() -> Event {
+    Event {
+        name: "lunch".into(),
+        at: Timestamp(1200),
+        cache: Default::default(),
+        tags: None,
+        r#type: Kind::Reminder("soon".into(), 5),
+    }
+}
+
+#[test]
+fn rename() {
+    let mut event = event();
+    assert_eq!(event[&keypath!(Event.title)], "lunch");
+    event[&keypath!(Event.title)] = "dinner".into();
+    assert_eq!(event.name, "dinner");
+
+    let path = KeyPath::<Event, String>::parse("title").unwrap();
+    assert_eq!(event[&path], "dinner");
+    assert!(KeyPath::<Event, String>::parse("name").is_err());
+    assert!(keypath!(Event.kind::Reminder.1).get(&event).is_ok());
+}
+
+#[test]
+fn opaque() {
+    let mut event = event();
+    let at: KeyPath<Event, Timestamp> = keypath!(Event.at);
+    assert_eq!(event[&at], Timestamp(1200));
+    event[&at].0 += 1;
+    assert_eq!(event.at, Timestamp(1201));
+
+    let parsed = KeyPath::<Event, Timestamp>::parse("at").unwrap();
+    assert_eq!(event[&parsed], Timestamp(1201));
+    assert!(KeyPath::<Event, u64>::parse("at.0").is_err());
+    assert!(event
+        .try_any_at_path(&[PathComponent::named("at"), PathComponent::unnamed(0)])
+        .is_err());
+
+    assert_eq!(event[&keypath!(Event.tags)], None);
+
+    event.r#type = Kind::Meeting {
+        room: Timestamp(4),
+        notes: "".into(),
+    };
+    let room: FallibleKeyPath<Event, Timestamp> = keypath!(Event.kind::Meeting.room);
+    assert_eq!(room.get(&event).unwrap(), &Timestamp(4));
+}
+
+#[test]
+fn skip() {
+    let event = event();
+    event.cache.set(1);
+    assert!(event
+        .try_any_at_path(&[PathComponent::named("cache")])
+        .is_err());
+    assert!(FallibleKeyPath::<Event, std::rc::Rc<str>>::parse("kind::Meeting.notes").is_err());
+
+    let level = keypath!(Event.kind::Reminder.1);
+    assert_eq!(level.get(&event).unwrap(), &5);
+    assert!(FallibleKeyPath::<Event, std::rc::Rc<str>>::parse("kind::Reminder.0").is_err());
+}
+
+#[derive(Keyable)]
+struct Config {
+    name: String,
+    #[keypath(flatten)]
+    network: Network,
+}
+
+#[derive(Keyable)]
+struct Network {
+    timeout: Seconds,
+    retries: u8,
+    name: String,
+}
+
+#[derive(Keyable)]
+#[keypath(transparent)]
+struct Seconds(Duration);
+
+#[derive(Keyable)]
+struct Duration {
+    secs: u64,
+}
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Source {
+    Remote {
+        #[keypath(flatten)]
+        network: Network,
+    },
+    Local(String),
+}
+
+fn config() -> Config {
+    Config {
+        name: "main".into(),
+        network: Network {
+            timeout: Seconds(Duration { secs: 30 }),
+            retries: 3,
+            name: "eth0".into(),
+        },
+    }
+}
+
+#[test]
+fn flatten() {
+    let mut config = config();
+    assert_eq!(config[&keypath!(Config.retries)], 3);
+    config[&keypath!(Config.retries)] = 4;
+    assert_eq!(config.network.retries, 4);
+
+    // the container's own fields come first
+    assert_eq!(config[&keypath!(Config.name)], "main");
+
+    let path = KeyPath::<Config, u8>::parse("retries").unwrap();
+    assert_eq!(config[&path], 4);
+    assert!(KeyPath::<Config, u8>::parse("network.retries").is_err());
+
+    let err = config
+        .try_any_at_path(&[PathComponent::named("nope")])
+        .unwrap_err();
+    assert_eq!(err.to_string(), "no field 'nope' on Config at Config.nope");
+
+    let source = Source::Remote {
+        network: config.network,
+    };
+    let retries = keypath!((Source)::Remote.retries);
+    assert_eq!(retries.get(&source).unwrap(), &4);
+    let parsed = FallibleKeyPath::<Source, u8>::parse("::Remote.retries").unwrap();
+    assert_eq!(parsed.get(&source).unwrap(), &4);
+}
+
+#[test]
+fn transparent() {
+    let mut config = config();
+    let secs: KeyPath<Config, u64> = keypath!(Config.timeout.secs);
+    assert_eq!(config[&secs], 30);
+    config[&secs] = 60;
+    assert_eq!(config.network.timeout.0.secs, 60);
+
+    let timeout = KeyPath::<Config, Seconds>::parse("timeout").unwrap();
+    assert_eq!(config[&timeout].0.secs, 60);
+    assert!(KeyPath::<Config, u64>::parse("timeout.0.secs").is_err());
+    assert!(KeyPath::<Config, u64>::parse("timeout.secs").is_ok());
+}
+
+/// Only one field can be flattened, but flattened types can flatten a field
+/// of their own.
+#[derive(Keyable)]
+struct Deployment {
+    #[keypath(flatten)]
+    config: Config,
+    replicas: u8,
+}
+
+#[test]
+fn nested_flatten() {
+    let mut deployment = Deployment {
+        config: config(),
+        replicas: 2,
+    };
+    let retries: KeyPath<Deployment, u8> = keypath!(Deployment.retries);
+    deployment[&retries] = 5;
+    assert_eq!(deployment.config.network.retries, 5);
+    assert_eq!(deployment[&keypath!(Deployment.name)], "main");
+    assert_eq!(deployment[&keypath!(Deployment.timeout.secs)], 30);
+    assert_eq!(deployment[&keypath!(Deployment.replicas)], 2);
+
+    let parsed = KeyPath::<Deployment, u64>::parse("timeout.secs").unwrap();
+    assert_eq!(deployment[&parsed], 30);
+}
@@ -0,0 +1,103 @@
+//! keypaths over types that borrow their data
+
+// the derive and the macro don't need crates to allow unsafe code
+#![deny(unsafe_code)]
+
+use std::borrow::Cow;
+
+use keypath::{keypath, FallibleKeyPath, KeyPath, Keyable, PartialKeyPath};
+
+#[derive(Keyable)]
+struct Item {
+    id: u32,
+}
+
+#[derive(Keyable)]
+struct View<'a> {
+    name: &'a str,
+    items: &'a [Item],
+    header: Header<'a>,
+    #[keypath(opaque)]
+    title: Cow<'a, str>,
+}
+
+#[derive(Keyable)]
+struct Header<'a> {
+    tags: Vec<&'a str>,
+    parent: Option<&'a str>,
+    label: Cow<'a, str>,
+}
+
+fn view<'a>(source: &'a str, items: &'a [Item]) -> View<'a> {
+    View {
+        name: &source[..4],
+        items,
+        header: Header {
+            tags: source.split(' ').collect(),
+            parent: None,
+            label: Cow::Borrowed(&source[..2]),
+        },
+        title: Cow::Borrowed(&source[5..]),
+    }
+}
+
+fn name<'a>() -> KeyPath<View<'a>, &'a str> {
+    keypath!(View.name)
+}
+
+#[test]
+fn borrowed_fields() {
+    let source = String::from("coco eli");
+    let items = [Item { id: 1 }, Item { id: 2 }];
+    let view = view(&source, &items);
+
+    assert_eq!(view[&name()], "coco");
+    assert_eq!(view[&keypath!(View.items)].len(), 2);
+    assert_eq!(view[&keypath!(View.title)], "eli");
+
+    let tag = keypath!(View.header.tags[1]);
+    assert_eq!(tag.get(&view).unwrap(), &"eli");
+    let i = 0;
+    assert_eq!(keypath!(View.header.tags[i]).get(&view).unwrap(), &"coco");
+    assert!(keypath!(View.header.parent?).get(&view).is_err());
+}
+
+#[test]
+fn borrowed_mutation() {
+    let source = String::from("coco eli");
+    let other = String::from("nico");
+    let mut view = view(&source, &[]);
+
+    view[&name()] = &other;
+    assert_eq!(view.name, "nico");
+
+    let label = keypath!(View.header.label);
+    assert_eq!(view[&label], "co");
+    view[&label].to_mut().push('!');
+    assert_eq!(view.header.label, "co!");
+    view[&label] = Cow::Borrowed(&other);
+    assert!(matches!(view.header.label, Cow::Borrowed("nico")));
+
+    let parent: FallibleKeyPath<View, &str> = keypath!(View.header.parent?);
+    assert!(parent.get_mut(&mut view).is_err());
+    view.header.parent = Some(&source);
+    *parent.get_mut(&mut view).unwrap() = &other[1..];
+    assert_eq!(view.header.parent, Some("ico"));
+}
+
+#[test]
+fn static_roots() {
+    // runtime-typed paths need the root and value to be 'static, which a
+    // borrowing type is when it borrows static data.
+    let view: View<'static> = view("coco eli", &[]);
+
+    let path = KeyPath::<View<'static>, &'static str>::parse("header.tags").err();
+    assert!(path.is_some());
+    let path = FallibleKeyPath::<View<'static>, &'static str>::parse("header.tags[0]").unwrap();
+    assert_eq!(path.get(&view).unwrap(), &"coco");
+
+    let partial: PartialKeyPath<View<'static>> = name().into();
+    let name = partial.get(&view).unwrap();
+    assert_eq!(name.downcast_ref::<&str>(), Some(&"coco"));
+    assert!(partial.downcast::<&str>().is_some());
+}
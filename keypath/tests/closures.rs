@@ -0,0 +1,110 @@
+//! closure-scoped access to values
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use keypath::{keypath, FieldErrorKind, Keyable};
+
+#[derive(Keyable)]
+struct Scoreboard {
+    title: String,
+    counts: HashMap<String, u32>,
+    history: RefCell<Vec<u32>>,
+    totals: RwLock<Totals>,
+}
+
+#[derive(Keyable)]
+struct Totals {
+    games: u32,
+}
+
+fn scoreboard() -> Scoreboard {
+    let mut counts = HashMap::new();
+    counts.insert("coco".to_string(), 2);
+    Scoreboard {
+        title: "league".into(),
+        counts,
+        history: RefCell::new(vec![3, 1]),
+        totals: RwLock::new(Totals { games: 7 }),
+    }
+}
+
+#[test]
+fn with_value() {
+    let board = scoreboard();
+    let len = board.with_value(&keypath!(Scoreboard.title), String::len);
+    assert_eq!(len.unwrap(), 6);
+
+    let coco = board.with_value(&keypath!(Scoreboard.counts["coco"]), |count| count + 1);
+    assert_eq!(coco.unwrap(), 3);
+    let err = board
+        .with_value(&keypath!(Scoreboard.counts["eli"]), |_| ())
+        .unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::MissinngKey(_)));
+    assert_eq!(
+        err.to_string(),
+        "no key \"eli\" in HashMap<String, u32> at Scoreboard.counts[\"eli\"]"
+    );
+}
+
+#[test]
+fn with_value_through_cells() {
+    let board = scoreboard();
+    let last = board.with_value(&keypath!(Scoreboard.history[1]), |last| *last);
+    assert_eq!(last.unwrap(), 1);
+    let games = board.with_value(&keypath!(Scoreboard.totals.games), |games| *games);
+    assert_eq!(games.unwrap(), 7);
+
+    let _guard = board.history.borrow_mut();
+    // a path that ends at the cell doesn't borrow it
+    assert!(board
+        .with_value(&keypath!(Scoreboard.history), |_| ())
+        .is_ok());
+    let err = board
+        .with_value(&keypath!(Scoreboard.history[0]), |_| ())
+        .unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::AlreadyBorrowed));
+}
+
+#[test]
+fn modify() {
+    let mut board = scoreboard();
+    let games = board.modify(&keypath!(Scoreboard.totals.games), |games| {
+        *games += 1;
+        *games
+    });
+    assert_eq!(games.unwrap(), 8);
+
+    board
+        .modify(&keypath!(Scoreboard.history), |history| {
+            history.get_mut().push(4)
+        })
+        .unwrap();
+    assert_eq!(*board.history.borrow(), [3, 1, 4]);
+
+    let err = board
+        .modify(&keypath!(Scoreboard.history[3]), |last| *last = 0)
+        .unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::IndexOutOfRange { index: 3, len: 3 }
+    ));
+}
+
+#[derive(Keyable)]
+struct Borrowed<'a> {
+    names: Vec<&'a str>,
+}
+
+#[test]
+fn borrowed_roots() {
+    let source = String::from("coco eli");
+    let mut view = Borrowed {
+        names: source.split(' ').collect(),
+    };
+    let first = keypath!(Borrowed.names[0]);
+    assert_eq!(view.with_value(&first, |name| name.len()).unwrap(), 4);
+    view.modify(&first, |name| *name = &source[5..]).unwrap();
+    assert_eq!(view.names, ["eli", "eli"]);
+}
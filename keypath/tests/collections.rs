@@ -1,8 +1,10 @@
-use keypath::{keypath, Keyable};
-use std::collections::HashMap;
+use keypath::{keypath, FallibleKeyPath, FieldErrorKind, Keyable};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::Arc;
 
 // keys that reference collections cannot currently be const
-//const JOJO: keypath::KeyPath<DemoStruct, String> = keypath!(DemoStruct.friend_lists["play"][0].name);
+//const JOJO: keypath::FallibleKeyPath<DemoStruct, String> = keypath!(DemoStruct.friend_lists["play"][0].name);
 
 #[derive(Keyable)]
 struct DemoStruct {
@@ -49,8 +51,224 @@ fn nested_keypath() {
     let mut demo = DemoStruct { friend_lists };
     let jojo_name = keypath!(DemoStruct.friend_lists["play"][0].name);
 
-    assert_eq!(demo[&jojo_name], "jojo");
-    demo[&jojo_name] = "Brad".into();
-    assert_eq!(demo[&jojo_name], "Brad");
+    assert_eq!(jojo_name.get(&demo).unwrap(), "jojo");
+    *jojo_name.get_mut(&mut demo).unwrap() = "Brad".into();
+    assert_eq!(jojo_name.get(&demo).unwrap(), "Brad");
     assert_eq!(demo.friend_lists["play"][0].name, "Brad");
 }
+
+#[test]
+fn missing_items() {
+    let mut friend_lists = HashMap::new();
+    friend_lists.insert("work".to_string(), Vec::new());
+    let demo = DemoStruct { friend_lists };
+
+    let missing_key = keypath!(DemoStruct.friend_lists["play"]);
+    assert!(matches!(
+        missing_key.get(&demo).err().unwrap().kind(),
+        FieldErrorKind::MissinngKey(key) if key == "play"
+    ));
+
+    let missing_idx = keypath!(DemoStruct.friend_lists["work"][0].name);
+    assert!(matches!(
+        missing_idx.get(&demo).err().unwrap().kind(),
+        FieldErrorKind::IndexOutOfRange { index: 0, len: 0 }
+    ));
+}
+
+#[test]
+fn runtime_indices() {
+    let people = vec![
+        Person {
+            name: "coco".to_string(),
+            magnitude: 42.0,
+            size: Size {
+                big: true,
+                heft: 200,
+            },
+        },
+        Person {
+            name: "jojo".to_string(),
+            magnitude: 69.0,
+            size: Size {
+                big: false,
+                heft: 20,
+            },
+        },
+    ];
+    let mut friend_lists = HashMap::new();
+    friend_lists.insert("play".to_string(), people);
+    let mut demo = DemoStruct { friend_lists };
+
+    let key = String::from("play");
+    let mut names = Vec::new();
+    for i in 0..2 {
+        let path = keypath!(DemoStruct.friend_lists[key][{ i }].name);
+        names.push(path.get(&demo).unwrap().clone());
+    }
+    assert_eq!(names, ["coco", "jojo"]);
+
+    let key: &str = "play";
+    let idx = 1;
+    let heft = keypath!(DemoStruct.friend_lists[key][idx].size.heft);
+    *heft.get_mut(&mut demo).unwrap() += 1;
+    assert_eq!(demo.friend_lists["play"][1].size.heft, 21);
+
+    let missing = keypath!(DemoStruct.friend_lists[key][idx + 1]);
+    assert!(missing.get(&demo).is_err());
+}
+
+#[test]
+fn literal_keys() {
+    let keys = [
+        "a\"b",
+        "tab\t",
+        "x",
+        r"back\slash",
+        "caf\u{e9}",
+        "bytes",
+        "multi\nline",
+    ];
+    let mut friend_lists = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        let person = Person {
+            name: i.to_string(),
+            magnitude: 0.0,
+            size: Size {
+                big: false,
+                heft: 0,
+            },
+        };
+        friend_lists.insert(key.to_string(), vec![person]);
+    }
+    let demo = DemoStruct { friend_lists };
+
+    let paths = [
+        keypath!(DemoStruct.friend_lists["a\"b"][0].name),
+        keypath!(DemoStruct.friend_lists["tab\t"][0usize].name),
+        keypath!(DemoStruct.friend_lists[r#"x"#][0x0].name),
+        keypath!(DemoStruct.friend_lists[r"back\slash"][0].name),
+        keypath!(DemoStruct.friend_lists["caf\u{e9}"][0].name),
+        keypath!(DemoStruct.friend_lists[b"bytes"][0].name),
+        keypath!(DemoStruct.friend_lists["multi\x0aline"][0].name),
+    ];
+    for (i, path) in paths.iter().enumerate() {
+        assert_eq!(path.get(&demo).unwrap(), &i.to_string());
+    }
+}
+
+#[derive(Keyable)]
+struct Palette {
+    swatches: BTreeMap<String, [f32; 4]>,
+    history: VecDeque<Size>,
+    frozen: Box<[String]>,
+}
+
+fn palette() -> Palette {
+    let mut swatches = BTreeMap::new();
+    swatches.insert("sky".to_string(), [0.5, 0.7, 1.0, 1.0]);
+    let mut history = VecDeque::new();
+    history.push_back(Size { big: true, heft: 1 });
+    history.push_front(Size {
+        big: false,
+        heft: 2,
+    });
+    Palette {
+        swatches,
+        history,
+        frozen: vec!["coco".to_string(), "jojo".to_string()].into_boxed_slice(),
+    }
+}
+
+#[test]
+fn other_collections() {
+    let mut palette = palette();
+
+    let alpha = keypath!(Palette.swatches["sky"][3]);
+    assert_eq!(alpha.get(&palette).unwrap(), &1.0);
+    *alpha.get_mut(&mut palette).unwrap() = 0.5;
+    assert_eq!(palette.swatches["sky"], [0.5, 0.7, 1.0, 0.5]);
+    assert!(matches!(
+        keypath!(Palette.swatches["sea"][0]).get(&palette).err().unwrap().kind(),
+        FieldErrorKind::MissinngKey(key) if key == "sea"
+    ));
+
+    assert_eq!(keypath!(Palette.history[0].heft).get(&palette).unwrap(), &2);
+    *keypath!(Palette.history[1].big)
+        .get_mut(&mut palette)
+        .unwrap() = false;
+    assert!(!palette.history[1].big);
+
+    let jojo = keypath!(Palette.frozen[1]);
+    jojo.get_mut(&mut palette).unwrap().push('!');
+    assert_eq!(&*palette.frozen[1], "jojo!");
+    assert!(matches!(
+        keypath!(Palette.frozen[2])
+            .get(&palette)
+            .err()
+            .unwrap()
+            .kind(),
+        FieldErrorKind::IndexOutOfRange { index: 2, len: 2 }
+    ));
+}
+
+#[derive(Keyable)]
+struct Shared {
+    local: Rc<[Size]>,
+    synced: Arc<[String]>,
+}
+
+#[test]
+fn shared_slices() {
+    let mut shared = Shared {
+        local: vec![Size { big: true, heft: 1 }].into(),
+        synced: vec!["coco".to_string()].into(),
+    };
+    let heft = keypath!(Shared.local[0].heft);
+    assert_eq!(heft.get(&shared).unwrap(), &1);
+    *heft.get_mut(&mut shared).unwrap() = 3;
+    assert_eq!(shared.local[0].heft, 3);
+    assert!(keypath!(Shared.synced[1]).get(&shared).is_err());
+
+    let names = keypath!(Shared.synced[*]);
+    names
+        .for_each_mut(&mut shared, |_, name| name.push('!'))
+        .unwrap();
+    assert_eq!(&*shared.synced[0], "coco!");
+
+    let other = Arc::clone(&shared.synced);
+    let err = keypath!(Shared.synced[0])
+        .get_mut(&mut shared)
+        .err()
+        .unwrap();
+    assert!(matches!(err.kind(), FieldErrorKind::SharedPointer));
+    assert_eq!(keypath!(Shared.synced[0]).get(&shared).unwrap(), "coco!");
+
+    assert_eq!(names.iter(&shared).count(), 1);
+    let err = names
+        .for_each_mut(&mut shared, |_, _| panic!())
+        .unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::SharedPointer));
+    assert_eq!(err.failed_index(), Some(1));
+    drop(other);
+}
+
+#[test]
+fn other_collections_at_runtime() {
+    let palette = palette();
+
+    let key = "sky";
+    let channel = 4;
+    let path = keypath!(Palette.swatches[key][channel]);
+    assert!(matches!(
+        path.get(&palette).err().unwrap().kind(),
+        FieldErrorKind::IndexOutOfRange { index: 4, len: 4 }
+    ));
+
+    let parsed = FallibleKeyPath::<Palette, f32>::parse("swatches[\"sky\"][1]").unwrap();
+    assert_eq!(parsed.get(&palette).unwrap(), &0.7);
+    let parsed = FallibleKeyPath::<Palette, u8>::parse("history[1].heft").unwrap();
+    assert_eq!(parsed.get(&palette).unwrap(), &1);
+    let parsed = FallibleKeyPath::<Palette, String>::parse("frozen[0]").unwrap();
+    assert_eq!(parsed.get(&palette).unwrap(), "coco");
+}
@@ -0,0 +1,56 @@
+//! generated code that reaches the crate through a re-export
+
+mod platform {
+    pub use keypath as paths;
+
+    /// A facade's version of the `keypath!` macro.
+    #[macro_export]
+    macro_rules! platform_keypath {
+        ($($path:tt)*) => {
+            $crate::platform::paths::keypath!(crate = $crate::platform::paths; $($path)*)
+        };
+    }
+}
+
+use platform::paths::{keypath, FallibleKeyPath, KeyPath, Keyable};
+
+#[derive(Keyable)]
+#[keypath(crate = "platform::paths")]
+struct Person {
+    name: String,
+    pets: Vec<Pet>,
+}
+
+#[derive(Keyable)]
+#[keypath(crate = "crate::platform::paths")]
+#[allow(dead_code)]
+enum Pet {
+    Dog { name: String },
+    Fish,
+}
+
+fn coco() -> Person {
+    Person {
+        name: "coco".into(),
+        pets: vec![Pet::Fish, Pet::Dog { name: "eli".into() }],
+    }
+}
+
+#[test]
+fn derive_crate_path() {
+    let person = coco();
+    let name: KeyPath<Person, String> = keypath!(crate = platform::paths; Person.name);
+    assert_eq!(person[&name], "coco");
+    assert_eq!(KeyPath::<Person, String>::parse("name").unwrap(), name);
+}
+
+#[test]
+fn macro_crate_path() {
+    let person = coco();
+    let dog: FallibleKeyPath<Person, String> = platform_keypath!(Person.pets[1]::Dog.name);
+    assert_eq!(dog.get(&person).unwrap(), "eli");
+
+    let i = 0;
+    let fish = platform_keypath!(Person.pets[i]::Dog.name);
+    assert!(fish.get(&person).is_err());
+}
@@ -0,0 +1,165 @@
+//! user-defined collections, indexed through `KeyableSequence` and `KeyableMap`
+
+use keypath::{keypath, FallibleKeyPath, FieldErrorKind, Keyable, KeyableMap, KeyableSequence};
+
+/// A vector that stores up to `N` items inline.
+#[derive(Keyable)]
+#[keypath(sequence)]
+struct InlineVec<T, const N: usize> {
+    items: [Option<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Default for InlineVec<T, N> {
+    fn default() -> Self {
+        InlineVec {
+            items: std::array::from_fn(|_| None),
+            len: 0,
+        }
+    }
+}
+
+impl<T, const N: usize> InlineVec<T, N> {
+    fn push(&mut self, item: T) {
+        self.items[self.len] = Some(item);
+        self.len += 1;
+    }
+}
+
+impl<T: Keyable, const N: usize> KeyableSequence for InlineVec<T, N> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index)?.as_ref()
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.items.get_mut(index)?.as_mut()
+    }
+
+    fn items_mut(&mut self) -> Box<dyn Iterator<Item = &mut T> + '_> {
+        Box::new(self.items.iter_mut().flatten())
+    }
+}
+
+/// A map whose keys are interned.
+#[derive(Keyable)]
+#[keypath(map)]
+struct Interned<T> {
+    keys: Vec<&'static str>,
+    values: Vec<T>,
+}
+
+impl<T: Keyable> KeyableMap for Interned<T> {
+    type Key = str;
+    type Value = T;
+
+    fn get_by_key(&self, key: &str) -> Option<&T> {
+        let idx = self.keys.iter().position(|k| *k == key)?;
+        self.values.get(idx)
+    }
+
+    fn get_by_key_mut(&mut self, key: &str) -> Option<&mut T> {
+        let idx = self.keys.iter().position(|k| *k == key)?;
+        self.values.get_mut(idx)
+    }
+
+    fn entries(&self) -> Box<dyn Iterator<Item = (&str, &T)> + '_> {
+        Box::new(self.keys.iter().copied().zip(&self.values))
+    }
+
+    fn entries_mut(&mut self) -> Box<dyn Iterator<Item = (&str, &mut T)> + '_> {
+        Box::new(self.keys.iter().copied().zip(&mut self.values))
+    }
+}
+
+#[derive(Keyable, Default)]
+struct Layer {
+    opacity: u8,
+}
+
+#[derive(Keyable)]
+struct Doc {
+    layers: InlineVec<Layer, 4>,
+    styles: Interned<Layer>,
+    spare: Option<InlineVec<Layer, 2>>,
+}
+
+fn doc() -> Doc {
+    let mut layers = InlineVec::default();
+    layers.push(Layer { opacity: 1 });
+    layers.push(Layer { opacity: 2 });
+    Doc {
+        layers,
+        styles: Interned {
+            keys: vec!["dim", "bright"],
+            values: vec![Layer { opacity: 3 }, Layer { opacity: 4 }],
+        },
+        spare: None,
+    }
+}
+
+#[test]
+fn sequences() {
+    let mut doc = doc();
+    let second = keypath!(Doc.layers[1].opacity);
+    assert_eq!(second.get(&doc).unwrap(), &2);
+    *second.get_mut(&mut doc).unwrap() = 5;
+    assert_eq!(doc.layers.items[1].as_ref().unwrap().opacity, 5);
+
+    let err = keypath!(Doc.layers[3]).get(&doc).err().unwrap();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::IndexOutOfRange { index: 3, len: 2 }
+    ));
+
+    let i = 0;
+    assert_eq!(keypath!(Doc.layers[i].opacity).get(&doc).unwrap(), &1);
+}
+
+#[test]
+fn maps() {
+    let mut doc = doc();
+    let bright = keypath!(Doc.styles["bright"].opacity);
+    assert_eq!(bright.get(&doc).unwrap(), &4);
+    *bright.get_mut(&mut doc).unwrap() = 6;
+    assert_eq!(doc.styles.values[1].opacity, 6);
+
+    let key = "missing";
+    let err = keypath!(Doc.styles[key]).get(&doc).err().unwrap();
+    assert!(matches!(err.kind(), FieldErrorKind::MissinngKey(_)));
+}
+
+#[test]
+fn wildcards() {
+    let mut doc = doc();
+    let layers = keypath!(Doc.layers[*].opacity);
+    assert_eq!(layers.iter(&doc).collect::<Vec<_>>(), [&1, &2]);
+    layers
+        .for_each_mut(&mut doc, |_, opacity| *opacity *= 10)
+        .unwrap();
+    assert_eq!(layers.iter(&doc).collect::<Vec<_>>(), [&10, &20]);
+
+    let styles = keypath!(Doc.styles[*].opacity);
+    let keys = styles
+        .matches(&doc)
+        .map(|(keys, _)| keys[0].to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, ["[\"dim\"]", "[\"bright\"]"]);
+}
+
+#[test]
+fn parsing_and_defaults() {
+    let mut doc = doc();
+    let parsed = FallibleKeyPath::<Doc, u8>::parse(r#"styles["dim"].opacity"#).unwrap();
+    assert_eq!(parsed, keypath!(Doc.styles["dim"].opacity));
+    assert!(FallibleKeyPath::<Doc, u8>::parse("layers.len").is_err());
+
+    // the sequence is `Default`, so it can be inserted
+    let spare = keypath!(Doc.spare?);
+    assert_eq!(spare.get_mut_or_insert_default(&mut doc).unwrap().len(), 0);
+}
@@ -1,5 +1,7 @@
 //! test deriving on various struct types
 
+#![allow(dead_code)]
+
 use keypath::Keyable;
 
 #[derive(Keyable)]
@@ -28,3 +30,56 @@ struct MultiFieldStruct {
     b: i64,
     c: String,
 }
+
+#[derive(Keyable)]
+enum UnitEnum {
+    A,
+    B,
+}
+
+#[derive(Keyable)]
+enum MixedEnum {
+    Unit,
+    Tuple(bool, i64),
+    Named { a: bool, b: String },
+    EmptyTuple(),
+    EmptyNamed {},
+}
+
+#[derive(Keyable)]
+struct RecursiveStruct {
+    children: Vec<RecursiveStruct>,
+    parent: Option<Vec<Self>>,
+}
+
+// variants with the same names as their field types, and as the enum itself
+#[derive(Keyable)]
+#[allow(clippy::enum_variant_names)]
+enum ShadowingEnum {
+    String(String),
+    MixedEnum(MixedEnum),
+    ShadowingEnum(Vec<ShadowingEnum>),
+}
+
+#[test]
+fn local_types() {
+    #[derive(Keyable)]
+    struct Local {
+        inner: Option<Vec<Local>>,
+        value: u8,
+    }
+
+    let local = Local {
+        inner: Some(vec![Local {
+            inner: None,
+            value: 5,
+        }]),
+        value: 1,
+    };
+    let path = keypath::keypath!(Local.inner?[0].value);
+    assert_eq!(path.get(&local).unwrap(), &5);
+
+    let nested = ShadowingEnum::ShadowingEnum(vec![ShadowingEnum::String("coco".into())]);
+    let path = keypath::keypath!((ShadowingEnum)::ShadowingEnum.0[0]::String.0);
+    assert_eq!(path.get(&nested).unwrap(), "coco");
+}
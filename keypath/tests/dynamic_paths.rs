@@ -0,0 +1,131 @@
+//! paths built at runtime, without the keypath! macro
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use keypath::internals::PathComponent;
+use keypath::{FallibleKeyPath, FieldErrorKind, Keyable};
+
+#[derive(Keyable)]
+struct Config {
+    servers: HashMap<String, Server>,
+    mode: Mode,
+}
+
+#[derive(Keyable)]
+struct Server {
+    host: String,
+    ports: Vec<u16>,
+}
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Mode {
+    Debug { level: u8 },
+    Release,
+}
+
+fn config() -> Config {
+    let mut servers = HashMap::new();
+    servers.insert(
+        "primary".to_string(),
+        Server {
+            host: "localhost".into(),
+            ports: vec![80, 443],
+        },
+    );
+    Config {
+        servers,
+        mode: Mode::Debug { level: 2 },
+    }
+}
+
+#[test]
+fn owned_components() {
+    let mut config = config();
+    // pretend these came from a config file
+    let names = [
+        "servers".to_string(),
+        "primary".to_string(),
+        "ports".to_string(),
+    ];
+
+    let path = vec![
+        PathComponent::named(names[0].clone()),
+        PathComponent::index_str(names[1].clone()),
+        PathComponent::named(names[2].clone()),
+        PathComponent::index_int(1),
+    ];
+
+    let port = config.try_any_at_path(&path).unwrap();
+    assert_eq!(port.downcast_ref::<u16>(), Some(&443));
+
+    let port = config.try_any_at_path_mut(&path).unwrap();
+    *port.downcast_mut::<u16>().unwrap() = 8443;
+    assert_eq!(config.servers["primary"].ports[1], 8443);
+
+    let variant = String::from("Debug");
+    let path = vec![
+        PathComponent::named("mode"),
+        PathComponent::variant(variant),
+        PathComponent::named(String::from("level")),
+    ];
+    let level = config.try_any_at_path(&path).unwrap();
+    assert_eq!(level.downcast_ref::<u8>(), Some(&2));
+}
+
+#[test]
+fn invalid_owned_components() {
+    let config = config();
+    let path = vec![
+        PathComponent::named("servers"),
+        PathComponent::index_str(String::from("primary")),
+        PathComponent::named(String::from("hots")),
+    ];
+    let err = config.try_any_at_path(&path).err().unwrap();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::InvalidField(PathComponent::Named(name)) if name == "hots"
+    ));
+
+    let path = vec![
+        PathComponent::named("mode"),
+        PathComponent::variant(String::from("Release")),
+    ];
+    let err = config.try_any_at_path(&path).err().unwrap();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::VariantMismatch {
+            expected: "Release",
+            found: "Debug",
+        }
+    ));
+}
+
+#[test]
+fn mistyped_path() {
+    let mut config = config();
+    // an unchecked path, whose value type is wrong
+    // SAFETY: the value type is 'static, so the mismatch is caught
+    let path = unsafe {
+        FallibleKeyPath::<Config, u8>::__conjure_from_abyss_owned(
+            |_| PhantomData,
+            vec![
+                PathComponent::named("servers"),
+                PathComponent::index_str("primary"),
+                PathComponent::named("host"),
+            ],
+        )
+    };
+    let err = path.get(&config).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::TypeMismatch {
+            expected: "u8",
+            found: "alloc::string::String",
+        }
+    ));
+    assert_eq!(err.type_name(), "alloc::string::String");
+    assert!(path.get_mut(&mut config).is_err());
+    assert!(path.get_mut_or_insert_default(&mut config).is_err());
+}
@@ -0,0 +1,86 @@
+use keypath::{keypath, FieldErrorKind, Keyable};
+
+#[derive(Keyable)]
+enum Shape {
+    Circle { radius: f64 },
+    Rect(f64, f64),
+    Empty,
+}
+
+#[derive(Keyable, Debug, PartialEq)]
+enum Color {
+    Red,
+    Green,
+}
+
+#[derive(Keyable)]
+struct Drawing {
+    shape: Shape,
+    color: Color,
+}
+
+#[derive(Keyable)]
+enum Wrapper<T> {
+    Some(T),
+    Nothing,
+}
+
+#[test]
+fn variant_keypath() {
+    let mut shape = Shape::Circle { radius: 4.0 };
+    let radius = keypath!((Shape)::Circle.radius);
+    assert_eq!(radius.get(&shape).unwrap(), &4.0);
+    *radius.get_mut(&mut shape).unwrap() = 8.0;
+    assert!(matches!(shape, Shape::Circle { radius } if radius == 8.0));
+
+    let mut shape = Shape::Rect(1.0, 2.0);
+    let height = keypath!((Shape)::Rect.1);
+    assert_eq!(height.get(&shape).unwrap(), &2.0);
+    *height.get_mut(&mut shape).unwrap() = 5.0;
+    assert!(matches!(shape, Shape::Rect(_, h) if h == 5.0));
+}
+
+#[test]
+fn variant_mismatch() {
+    let shape = Shape::Rect(1.0, 2.0);
+    let radius = keypath!((Shape)::Circle.radius);
+    let err = radius.get(&shape).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::VariantMismatch {
+            expected: "Circle",
+            found: "Rect"
+        }
+    ));
+
+    let err = radius.get(&Shape::Empty).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::VariantMismatch { found: "Empty", .. }
+    ));
+}
+
+#[test]
+fn nested_variant() {
+    let mut drawing = Drawing {
+        shape: Shape::Circle { radius: 1.0 },
+        color: Color::Red,
+    };
+
+    let radius = keypath!(Drawing.shape::Circle.radius);
+    let color = keypath!(Drawing.color);
+    assert_eq!(radius.get(&drawing).unwrap(), &1.0);
+    assert_eq!(drawing[&color], Color::Red);
+    drawing[&color] = Color::Green;
+    assert_eq!(drawing.color, Color::Green);
+}
+
+#[test]
+fn generic_enum() {
+    let mut wrapper = Wrapper::Some(5u8);
+    let inner = keypath!((Wrapper<u8>)::Some.0);
+    assert_eq!(inner.get(&wrapper).unwrap(), &5);
+    *inner.get_mut(&mut wrapper).unwrap() = 10;
+    assert!(matches!(wrapper, Wrapper::Some(10)));
+    assert!(inner.get(&Wrapper::Nothing).is_err());
+}
@@ -0,0 +1,100 @@
+//! type-erased keypaths, and downcasting them
+
+use keypath::{keypath, AnyKeyPath, FallibleKeyPath, KeyPath, Keyable, PartialKeyPath};
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    age: u8,
+    friends: Vec<String>,
+}
+
+#[derive(Keyable)]
+struct Pet {
+    name: String,
+}
+
+const AGE: KeyPath<Person, u8> = keypath!(Person.age);
+
+fn coco() -> Person {
+    Person {
+        name: "coco".into(),
+        age: 7,
+        friends: vec!["eli".into()],
+    }
+}
+
+#[test]
+fn partial_downcast() {
+    let mut person = coco();
+    let columns: Vec<PartialKeyPath<Person>> = vec![
+        keypath!(Person.name).into(),
+        AGE.into(),
+        keypath!(Person.friends[0]).into(),
+    ];
+
+    let name = columns[0].downcast::<String>().unwrap();
+    assert_eq!(person[&name], "coco");
+    assert!(columns[0].downcast::<u8>().is_none());
+
+    let age = columns[1].downcast::<u8>().unwrap();
+    person[&age] = 8;
+    assert_eq!(person.age, 8);
+
+    // fallible paths can only be downcast to fallible keypaths
+    assert!(columns[2].is_fallible());
+    assert!(columns[2].downcast::<String>().is_none());
+    let friend = columns[2].downcast_fallible::<String>().unwrap();
+    assert_eq!(friend.get(&person).unwrap(), "eli");
+
+    // and infallible paths can be downcast to either
+    let age = columns[1].downcast_fallible::<u8>().unwrap();
+    assert_eq!(age.get(&person).unwrap(), &8);
+}
+
+#[test]
+fn partial_access() {
+    let mut person = coco();
+    let age = PartialKeyPath::from(AGE);
+    assert_eq!(age.value_type_name(), "u8");
+    *age.get_mut(&mut person)
+        .unwrap()
+        .downcast_mut::<u8>()
+        .unwrap() = 9;
+    assert_eq!(age.get(&person).unwrap().downcast_ref::<u8>(), Some(&9));
+}
+
+#[test]
+fn any_downcast() {
+    let person = coco();
+    let paths: Vec<AnyKeyPath> = vec![
+        keypath!(Person.name).into(),
+        keypath!(Pet.name).into(),
+        keypath!(Person.friends[0]).into(),
+        PartialKeyPath::from(AGE).into(),
+    ];
+
+    assert!(paths[0].downcast::<Person, String>().is_some());
+    assert!(paths[0].downcast::<Pet, String>().is_none());
+    assert!(paths[1].downcast::<Pet, String>().is_some());
+    assert!(paths[1].downcast_partial::<Person>().is_none());
+
+    let friend: FallibleKeyPath<Person, String> = paths[2].downcast_fallible().unwrap();
+    assert_eq!(friend.get(&person).unwrap(), "eli");
+    assert!(paths[2].downcast::<Person, String>().is_none());
+
+    let age: KeyPath<Person, u8> = paths[3].downcast().unwrap();
+    assert_eq!(person[&age], 7);
+    assert!(paths[3].root_type_name().ends_with("Person"));
+}
+
+#[test]
+fn appended_value_type() {
+    let person = coco();
+    let friends = FallibleKeyPath::from(keypath!(Person.friends));
+    let first = friends.append(&keypath!(Vec<String>[0]));
+    let partial = PartialKeyPath::from(first);
+    assert!(partial.downcast_fallible::<Vec<String>>().is_none());
+    let first = partial.downcast_fallible::<String>().unwrap();
+    assert_eq!(first.get(&person).unwrap(), "eli");
+}
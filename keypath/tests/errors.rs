@@ -0,0 +1,92 @@
+//! the content and formatting of traversal errors
+
+use std::collections::HashMap;
+
+use keypath::{keypath, FieldErrorKind, Keyable};
+
+#[derive(Keyable, Debug)]
+struct Person {
+    size: Size,
+    friends: Vec<Person>,
+    pets: HashMap<String, Pet>,
+    pet: Option<Pet>,
+}
+
+#[derive(Keyable, Debug)]
+struct Size {
+    height: u8,
+}
+
+#[derive(Keyable, Debug)]
+#[allow(dead_code)]
+enum Pet {
+    Cat { lives: u8 },
+    Dog(String),
+}
+
+fn coco() -> Person {
+    Person {
+        size: Size { height: 45 },
+        friends: vec![Person {
+            size: Size { height: 12 },
+            friends: Vec::new(),
+            pets: HashMap::new(),
+            pet: None,
+        }],
+        pets: HashMap::new(),
+        pet: Some(Pet::Dog("jojo".into())),
+    }
+}
+
+#[test]
+fn full_path() {
+    let person = coco();
+    let path = keypath!(Person.friends[0].friends[3].size.height);
+    let err = path.get(&person).unwrap_err();
+    assert_eq!(err.path().len(), path.as_ref().len());
+    assert_eq!(err.failed_index(), Some(3));
+    assert_eq!(err.depth(), 2);
+    assert!(err.root_type_name().unwrap().ends_with("Person"));
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::IndexOutOfRange { index: 3, len: 0 }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "index 3 is out of range for Vec<Person> of length 0 \
+         at Person.friends[0].friends[3].size.height"
+    );
+}
+
+#[test]
+fn display() {
+    let mut person = coco();
+    let err = keypath!(Person.pets["rex"]).get(&person).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "no key \"rex\" in HashMap<String, Pet> at Person.pets[\"rex\"]"
+    );
+
+    let err = keypath!(Person.pet?::Cat.lives).get(&person).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected Pet::Cat, found Pet::Dog at Person.pet?::Cat.lives"
+    );
+    assert_eq!(err.failed_index(), Some(2));
+
+    let err = keypath!(Person.friends[0].pet?)
+        .get_mut(&mut person)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Option<Pet> is None at Person.friends[0].pet?"
+    );
+}
+
+#[test]
+fn std_error() {
+    let person = coco();
+    let err: Box<dyn std::error::Error> =
+        Box::new(keypath!(Person.friends[1]).get(&person).unwrap_err());
+    assert!(err.to_string().starts_with("index 1 is out of range"));
+}
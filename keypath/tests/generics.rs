@@ -1,3 +1,5 @@
+use std::marker::PhantomData;
+
 use keypath::{keypath, Keyable};
 
 #[derive(Keyable)]
@@ -23,3 +25,121 @@ fn simple_keypath() {
     person[&value_path] = false;
     assert!(!person.value);
 }
+
+#[derive(Keyable)]
+struct Borrowed<'a> {
+    name: &'a str,
+    scores: [u8; 3],
+}
+
+#[test]
+fn lifetimes() {
+    let mut borrowed = Borrowed {
+        name: "coco",
+        scores: [1, 2, 3],
+    };
+    assert_eq!(borrowed[&keypath!(Borrowed.name)], "coco");
+    borrowed[&keypath!(Borrowed.name)] = "eli";
+    assert_eq!(borrowed.name, "eli");
+    assert_eq!(keypath!(Borrowed.scores[2]).get(&borrowed).unwrap(), &3);
+}
+
+#[derive(Keyable)]
+struct Buffer<T, const N: usize> {
+    items: [T; N],
+    pairs: [(T, u8); 2],
+}
+
+#[test]
+fn const_generics() {
+    let mut buffer = Buffer {
+        items: [1u16, 2, 3, 4],
+        pairs: [(5, 6), (7, 8)],
+    };
+    let third = keypath!(Buffer<u16, 4>.items[2]);
+    assert_eq!(third.get(&buffer).unwrap(), &3);
+    // literal indices are checked against the length at compile time
+    let fifth = 4;
+    assert!(keypath!(Buffer<u16, 4>.items[fifth]).get(&buffer).is_err());
+    *keypath!(Buffer<u16, 4>.pairs[1].0)
+        .get_mut(&mut buffer)
+        .unwrap() = 9;
+    assert_eq!(buffer.pairs[1], (9, 8));
+}
+
+trait Unit {
+    type Value;
+}
+
+struct Meters;
+
+impl Unit for Meters {
+    type Value = f64;
+}
+
+#[derive(Keyable)]
+struct Measure<U: Unit, I: Iterator>
+where
+    U::Value: Copy,
+{
+    value: U::Value,
+    next: Option<<I as Iterator>::Item>,
+    #[keypath(skip)]
+    _unit: PhantomData<U>,
+}
+
+#[test]
+fn associated_types() {
+    let measure = Measure::<Meters, std::vec::IntoIter<String>> {
+        value: 2.5,
+        next: Some("x".into()),
+        _unit: PhantomData,
+    };
+    type M = Measure<Meters, std::vec::IntoIter<String>>;
+    assert_eq!(measure[&keypath!(M.value)], 2.5);
+    assert_eq!(keypath!(M.next?).get(&measure).unwrap(), "x");
+}
+
+#[derive(Keyable)]
+struct Tree<T, U = u8>
+where
+    T: Clone,
+{
+    value: (T, U),
+    children: Vec<Self>,
+}
+
+#[test]
+fn where_clauses_and_tuples() {
+    let tree = Tree {
+        value: (true, 1u8),
+        children: vec![Tree {
+            value: (false, 2),
+            children: Vec::new(),
+        }],
+    };
+    let path = keypath!(Tree<bool>.children[0].value.1);
+    assert_eq!(path.get(&tree).unwrap(), &2);
+    assert!(tree[&keypath!(Tree<bool, u8>.value.0)]);
+}
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Either<'a, L, R: Default, const N: usize> {
+    Left([L; N]),
+    Right { value: R, label: &'a str },
+    Neither,
+}
+
+#[test]
+fn generic_enums() {
+    type E = Either<'static, u8, String, 2>;
+    let left: E = Either::Left([4, 5]);
+    assert_eq!(keypath!((E)::Left.0[1]).get(&left).unwrap(), &5);
+    let right: E = Either::Right {
+        value: "r".into(),
+        label: "label",
+    };
+    assert_eq!(keypath!((E)::Right.value).get(&right).unwrap(), "r");
+    assert!(keypath!((E)::Right.label).get(&left).is_err());
+}
@@ -0,0 +1,217 @@
+//! maps with integer, enum and newtype keys
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use keypath::internals::PathComponent;
+use keypath::{keypath, FallibleKeyPath, FieldErrorKind, IndexKey, Keyable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Region {
+    North,
+    South,
+}
+
+impl IndexKey for Region {
+    type Lookup = Region;
+
+    fn to_path_component(&self) -> PathComponent {
+        PathComponent::key(*self)
+    }
+
+    fn from_path_component(component: &PathComponent) -> Option<Cow<'_, Region>> {
+        component.key_ref().map(Cow::Borrowed)
+    }
+}
+
+/// A key whose values all print the same.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Hidden(u8);
+
+impl fmt::Debug for Hidden {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Hidden")
+    }
+}
+
+/// A newtype key, which is written as the integer it wraps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct UserId(u32);
+
+impl IndexKey for UserId {
+    type Lookup = UserId;
+
+    fn to_path_component(&self) -> PathComponent {
+        self.0.to_path_component()
+    }
+
+    fn from_path_component(component: &PathComponent) -> Option<Cow<'_, UserId>> {
+        let id = u32::from_path_component(component)?;
+        Some(Cow::Owned(UserId(*id)))
+    }
+}
+
+#[derive(Keyable)]
+struct Bank {
+    accounts: HashMap<u32, Account>,
+    offsets: BTreeMap<i64, u8>,
+    regions: BTreeMap<Region, Vec<u32>>,
+    owners: HashMap<UserId, String>,
+    grades: HashMap<char, u8>,
+}
+
+#[derive(Keyable)]
+struct Account {
+    balance: i64,
+}
+
+fn bank() -> Bank {
+    let mut accounts = HashMap::new();
+    accounts.insert(5, Account { balance: 10 });
+    accounts.insert(7, Account { balance: -3 });
+    let mut offsets = BTreeMap::new();
+    offsets.insert(-1, 1);
+    offsets.insert(2, 2);
+    let mut regions = BTreeMap::new();
+    regions.insert(Region::North, vec![5]);
+    regions.insert(Region::South, vec![7]);
+    let mut owners = HashMap::new();
+    owners.insert(UserId(5), "coco".to_string());
+    let mut grades = HashMap::new();
+    grades.insert('a', 90);
+    Bank {
+        accounts,
+        offsets,
+        regions,
+        owners,
+        grades,
+    }
+}
+
+#[test]
+fn integer_keys() {
+    let mut bank = bank();
+    let balance = keypath!(Bank.accounts[5].balance);
+    assert_eq!(balance.get(&bank).unwrap(), &10);
+    *balance.get_mut(&mut bank).unwrap() += 1;
+    assert_eq!(bank.accounts[&5].balance, 11);
+
+    let id = 7;
+    assert_eq!(keypath!(Bank.accounts[id].balance).get(&bank).unwrap(), &-3);
+
+    let err = keypath!(Bank.accounts[9]).get(&bank).err().unwrap();
+    assert!(matches!(
+        err.kind(),
+        FieldErrorKind::MissingTypedKey(PathComponent::IndexInt(9))
+    ));
+    assert_eq!(
+        err.to_string(),
+        "no key 9 in HashMap<u32, Account> at Bank.accounts[9]"
+    );
+}
+
+#[test]
+fn negative_keys() {
+    let bank = bank();
+    let before = keypath!(Bank.offsets[-1]);
+    assert_eq!(before.get(&bank).unwrap(), &1);
+    assert_eq!(keypath!(Bank.offsets[2_i64]).get(&bank).unwrap(), &2);
+    assert_eq!(before.to_string(), "Bank.offsets[-1]");
+
+    let parsed = FallibleKeyPath::<Bank, u8>::parse("offsets[-1]").unwrap();
+    assert_eq!(parsed, before);
+    assert_eq!(parsed.get(&bank).unwrap(), &1);
+}
+
+#[test]
+fn enum_and_newtype_keys() {
+    let mut bank = bank();
+    let south = keypath!(Bank.regions[Region::South][0]);
+    assert_eq!(south.get(&bank).unwrap(), &7);
+    assert_eq!(south.to_string(), "Bank.regions[South][0]");
+
+    let owner = keypath!(Bank.owners[UserId(5)]);
+    owner.get_mut(&mut bank).unwrap().push('!');
+    assert_eq!(bank.owners[&UserId(5)], "coco!");
+    // the newtype is written as its integer
+    assert_eq!(
+        owner,
+        FallibleKeyPath::<Bank, String>::parse("owners[5]").unwrap()
+    );
+
+    let grade = keypath!(Bank.grades['a']);
+    assert_eq!(grade.get(&bank).unwrap(), &90);
+    assert!(keypath!(Bank.grades['b']).get(&bank).is_err());
+}
+
+#[test]
+fn runtime_components() {
+    let bank = bank();
+    let path = [
+        PathComponent::named("regions"),
+        PathComponent::key(Region::North),
+    ];
+    let accounts = bank.try_any_at_path(&path).unwrap();
+    assert_eq!(accounts.downcast_ref::<Vec<u32>>().unwrap(), &[5]);
+
+    // keys of the wrong type are not keys of the map
+    let path = [PathComponent::named("regions"), PathComponent::key(5_u32)];
+    let err = bank.try_any_at_path(&path).unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::InvalidField(_)));
+    let path = [
+        PathComponent::named("accounts"),
+        PathComponent::index_str("5"),
+    ];
+    assert!(bank.try_any_at_path(&path).is_err());
+
+    assert_eq!(
+        PathComponent::key(Region::North),
+        PathComponent::key(Region::North)
+    );
+    assert_ne!(
+        PathComponent::key(Region::North),
+        PathComponent::key(Region::South)
+    );
+    assert_ne!(PathComponent::key(5_u32), PathComponent::key(5_u64));
+    assert!(PathComponent::key(Region::North) < PathComponent::key(Region::South));
+
+    // keys of the same type are ordered by their `Ord` impl, consistently
+    // with `Eq`, even if they print the same
+    let (a, b) = (PathComponent::key(Hidden(1)), PathComponent::key(Hidden(2)));
+    assert_eq!(a.to_string(), b.to_string());
+    assert_eq!(a.cmp(&b), Ordering::Less);
+    assert_eq!(b.cmp(&a), Ordering::Greater);
+    let paths: BTreeSet<_> = vec![b.clone(), a.clone(), b].into_iter().collect();
+    assert_eq!(
+        paths.into_iter().collect::<Vec<_>>(),
+        [a.clone(), PathComponent::key(Hidden(2))]
+    );
+}
+
+#[test]
+fn wildcards() {
+    let mut bank = bank();
+    let balances = keypath!(Bank.accounts[*].balance);
+    let mut found = balances
+        .matches(&bank)
+        .map(|(keys, balance)| (keys[0].clone(), *balance))
+        .collect::<Vec<_>>();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            (PathComponent::IndexInt(5), 10),
+            (PathComponent::IndexInt(7), -3)
+        ]
+    );
+
+    keypath!(Bank.regions[*][*])
+        .for_each_mut(&mut bank, |keys, id| {
+            assert!(keys[0].key_ref::<Region>().is_some());
+            *id += 100;
+        })
+        .unwrap();
+    assert_eq!(bank.regions[&Region::South], [107]);
+}
@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use keypath::{keypath, FallibleKeyPath, FieldErrorKind, KeyPath, Keyable};
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    pet: Option<Pet>,
+    nickname: Option<String>,
+    shelter: Option<Shelter>,
+}
+
+#[derive(Keyable, Default)]
+struct Pet {
+    name: String,
+    vet: Option<Vet>,
+}
+
+#[derive(Keyable, Default)]
+struct Vet {
+    name: String,
+}
+
+// no Default impl
+#[derive(Keyable)]
+struct Shelter {
+    name: String,
+}
+
+#[derive(Keyable)]
+struct Kennel {
+    boxed: Box<Option<Pet>>,
+    shared: Rc<Option<Pet>>,
+}
+
+fn coco() -> Person {
+    Person {
+        name: "coco".into(),
+        pet: None,
+        nickname: Some("co".into()),
+        shelter: None,
+    }
+}
+
+#[test]
+fn optional_chaining() {
+    let mut person = coco();
+    let nickname: FallibleKeyPath<Person, String> = keypath!(Person.nickname?);
+    assert_eq!(nickname.get(&person).unwrap(), "co");
+    *nickname.get_mut(&mut person).unwrap() = "cc".into();
+    assert_eq!(person.nickname.as_deref(), Some("cc"));
+
+    let pet_name = keypath!(Person.pet?.name);
+    let err = pet_name.get(&person).unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::NoneValue));
+    assert!(pet_name.get_mut(&mut person).is_err());
+
+    person.pet = Some(Pet {
+        name: "jojo".into(),
+        vet: None,
+    });
+    assert_eq!(pet_name.get(&person).unwrap(), "jojo");
+}
+
+#[test]
+fn non_fallible_option() {
+    let mut person = coco();
+    let nickname: KeyPath<Person, Option<String>> = keypath!(Person.nickname);
+    assert_eq!(person[&nickname].as_deref(), Some("co"));
+    person[&nickname] = None;
+    assert!(person.nickname.is_none());
+}
+
+#[test]
+fn insert_default() {
+    let mut person = coco();
+    let vet_name = keypath!(Person.pet?.vet?.name);
+    assert!(vet_name.get(&person).is_err());
+
+    *vet_name.get_mut_or_insert_default(&mut person).unwrap() = "dr. nico".into();
+    assert_eq!(
+        person.pet.as_ref().unwrap().vet.as_ref().unwrap().name,
+        "dr. nico"
+    );
+    assert_eq!(vet_name.get(&person).unwrap(), "dr. nico");
+
+    // existing values are not replaced
+    let pet_name = keypath!(Person.pet?.name);
+    *pet_name.get_mut(&mut person).unwrap() = "jojo".into();
+    vet_name.get_mut_or_insert_default(&mut person).unwrap();
+    assert_eq!(pet_name.get(&person).unwrap(), "jojo");
+}
+
+#[test]
+fn insert_default_requires_default() {
+    let mut person = coco();
+    let shelter_name = keypath!(Person.shelter?.name);
+    let err = shelter_name
+        .get_mut_or_insert_default(&mut person)
+        .unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::NoneValue));
+    assert!(person.shelter.is_none());
+}
+
+#[test]
+fn append_fallible() {
+    let person = Person {
+        pet: Some(Pet {
+            name: "jojo".into(),
+            vet: Some(Vet {
+                name: "dr. nico".into(),
+            }),
+        }),
+        ..coco()
+    };
+    let pet = keypath!(Person.pet?);
+    let vet_name = FallibleKeyPath::from(keypath!(Pet.vet)).append(&keypath!(Option<Vet>?.name));
+    assert_eq!(pet.append(&vet_name).get(&person).unwrap(), "dr. nico");
+}
+
+#[derive(Keyable, Default)]
+#[keypath(default)]
+struct Tagged<T> {
+    tag: T,
+    labels: Vec<String>,
+}
+
+#[derive(Keyable)]
+struct Catalog {
+    entry: Option<Tagged<u32>>,
+    named: Option<Tagged<String>>,
+}
+
+#[test]
+fn insert_default_generic() {
+    let mut catalog = Catalog {
+        entry: None,
+        named: None,
+    };
+    *keypath!(Catalog.entry?.tag)
+        .get_mut_or_insert_default(&mut catalog)
+        .unwrap() = 7;
+    assert_eq!(catalog.entry.as_ref().unwrap().tag, 7);
+
+    keypath!(Catalog.named?.labels)
+        .get_mut_or_insert_default(&mut catalog)
+        .unwrap()
+        .push("new".into());
+    assert_eq!(catalog.named.as_ref().unwrap().labels, ["new"]);
+    assert_eq!(catalog.named.as_ref().unwrap().tag, "");
+}
+
+#[test]
+fn insert_default_behind_pointers() {
+    let mut kennel = Kennel {
+        boxed: Box::new(None),
+        shared: Rc::new(None),
+    };
+    *keypath!(Kennel.boxed?.name)
+        .get_mut_or_insert_default(&mut kennel)
+        .unwrap() = "jojo".into();
+    assert_eq!(kennel.boxed.as_ref().as_ref().unwrap().name, "jojo");
+
+    let shared = Rc::clone(&kennel.shared);
+    let err = keypath!(Kennel.shared?.name)
+        .get_mut_or_insert_default(&mut kennel)
+        .unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::SharedPointer));
+    drop(shared);
+    keypath!(Kennel.shared?.name)
+        .get_mut_or_insert_default(&mut kennel)
+        .unwrap()
+        .push('!');
+    assert_eq!(kennel.shared.as_ref().as_ref().unwrap().name, "!");
+}
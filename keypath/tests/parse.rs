@@ -0,0 +1,270 @@
+//! keypaths parsed from strings at runtime
+
+use std::collections::HashMap;
+
+use keypath::internals::PathComponent;
+use keypath::{
+    keypath, FallibleKeyPath, FieldErrorKind, KeyPath, Keyable, ParseError, PartialKeyPath,
+    WildcardKeyPath,
+};
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    friends: Vec<Person>,
+    pet: Option<Pet>,
+    size: Size,
+    tags: HashMap<String, String>,
+}
+
+#[derive(Keyable)]
+struct Pet {
+    name: String,
+    kind: Kind,
+}
+
+#[derive(Keyable)]
+struct Size((u8, u8), u16);
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Kind {
+    Cat { lives: u8 },
+    Dog(String),
+    Fish,
+}
+
+fn coco() -> Person {
+    let mut tags = HashMap::new();
+    tags.insert("a \"b\"".to_string(), "quoted".to_string());
+    Person {
+        name: "coco".into(),
+        friends: vec![Person {
+            name: "eli".into(),
+            friends: Vec::new(),
+            pet: None,
+            size: Size((1, 2), 3),
+            tags: HashMap::new(),
+        }],
+        pet: Some(Pet {
+            name: "jojo".into(),
+            kind: Kind::Dog("beagle".into()),
+        }),
+        size: Size((45, 12), 101),
+        tags,
+    }
+}
+
+#[test]
+fn parse_fields() {
+    let person = coco();
+    let name = KeyPath::<Person, String>::parse("name").unwrap();
+    assert_eq!(person[&name], "coco");
+    let name = KeyPath::<Person, String>::parse(".name").unwrap();
+    assert_eq!(person[&name], "coco");
+
+    let width = KeyPath::<Person, u8>::parse("size.0.1").unwrap();
+    assert_eq!(person[&width], 12);
+    let height = KeyPath::<Size, u8>::parse("0.0").unwrap();
+    assert_eq!(person.size[&height], 45);
+
+    let root = KeyPath::<Person, Person>::parse("").unwrap();
+    assert_eq!(person[&root].name, "coco");
+}
+
+#[test]
+fn parse_fallible() {
+    let person = coco();
+    let name = FallibleKeyPath::<Person, String>::parse("friends[0].name").unwrap();
+    assert_eq!(name.get(&person).unwrap(), "eli");
+
+    let breed = FallibleKeyPath::<Person, String>::parse("pet?.kind::Dog.0").unwrap();
+    assert_eq!(breed.get(&person).unwrap(), "beagle");
+
+    let lives = FallibleKeyPath::<Person, u8>::parse("pet?.kind::Cat.lives").unwrap();
+    assert!(lives.get(&person).is_err());
+
+    let tag = FallibleKeyPath::<Person, String>::parse(r#"tags["a \"b\""]"#).unwrap();
+    assert_eq!(tag.get(&person).unwrap(), "quoted");
+    let tag = FallibleKeyPath::<Person, String>::parse(r##"tags[r#"a "b""#]"##).unwrap();
+    assert_eq!(tag.get(&person).unwrap(), "quoted");
+
+    let fish = FallibleKeyPath::<Person, Kind>::parse("pet?.kind::Fish").unwrap();
+    assert!(fish.get(&person).is_err());
+}
+
+#[test]
+fn parse_partial() {
+    let person = coco();
+    let path = PartialKeyPath::<Person>::parse("friends[0].size.1").unwrap();
+    let value = person.try_any_at_path(&path).unwrap();
+    assert_eq!(value.downcast_ref::<u16>(), Some(&3));
+}
+
+#[test]
+fn invalid_components() {
+    let err = PartialKeyPath::<Person>::parse("friends[0].pet?.nmae")
+        .err()
+        .unwrap();
+    match &err {
+        ParseError::InvalidPath(err) => {
+            assert!(matches!(
+                err.kind(),
+                FieldErrorKind::InvalidField(PathComponent::Named(name)) if name == "nmae"
+            ));
+            assert!(err.type_name().ends_with("Pet"));
+            assert_eq!(err.depth(), 0);
+        }
+        other => panic!("unexpected error {:?}", other),
+    }
+    assert_eq!(
+        err.to_string(),
+        "invalid keypath: no field 'nmae' on Pet at Person.friends[0].pet?.nmae"
+    );
+
+    // components that don't exist on the type they are applied to
+    assert!(PartialKeyPath::<Person>::parse("name[0]").is_err());
+    assert!(PartialKeyPath::<Person>::parse("friends.name").is_err());
+    assert!(PartialKeyPath::<Person>::parse(r#"friends["a"]"#).is_err());
+    assert!(PartialKeyPath::<Person>::parse("tags[0]").is_err());
+    assert!(PartialKeyPath::<Person>::parse("pet.name").is_err());
+    assert!(PartialKeyPath::<Person>::parse("size.2").is_err());
+    assert!(PartialKeyPath::<Person>::parse("pet?.kind::Bird").is_err());
+    assert!(PartialKeyPath::<Person>::parse("pet?.kind::Dog.1").is_err());
+}
+
+#[test]
+fn wrong_types() {
+    let err = KeyPath::<Person, u8>::parse("name").err().unwrap();
+    assert!(matches!(
+        err,
+        ParseError::WrongType {
+            expected: "u8",
+            found: "alloc::string::String",
+        }
+    ));
+
+    let err = KeyPath::<Person, String>::parse("friends[0].name")
+        .err()
+        .unwrap();
+    assert!(matches!(err, ParseError::Fallible));
+    assert!(FallibleKeyPath::<Person, u8>::parse("friends[0].name").is_err());
+}
+
+#[test]
+fn syntax_errors() {
+    let position = |path| match PartialKeyPath::<Person>::parse(path).err().unwrap() {
+        ParseError::Syntax { position, .. } => position,
+        other => panic!("unexpected error {:?}", other),
+    };
+
+    assert_eq!(position("name."), 5);
+    assert_eq!(position("name..size"), 5);
+    assert_eq!(position("friends[]"), 8);
+    assert_eq!(position("friends[i]"), 8);
+    assert_eq!(position("friends[0"), 9);
+    assert_eq!(position("pet?:kind"), 5);
+    assert_eq!(position("name size"), 4);
+    assert_eq!(position(r#"tags["abc]"#), 5);
+    assert_eq!(position(r#"tags["\q"]"#), 6);
+    assert_eq!(position("friends[99999999999999999999999]"), 8);
+}
+
+#[derive(Keyable)]
+struct Keys {
+    chars: HashMap<char, u8>,
+    bytes: HashMap<u8, u8>,
+    signed: HashMap<i64, u8>,
+    large: HashMap<u128, u8>,
+    flags: HashMap<bool, u8>,
+    names: HashMap<String, Vec<u8>>,
+}
+
+fn keys() -> Keys {
+    let mut names = HashMap::new();
+    names.insert("coco".to_string(), vec![1, 2]);
+    Keys {
+        chars: vec![('a', 1), ('\n', 2)].into_iter().collect(),
+        bytes: vec![(b'a', 3)].into_iter().collect(),
+        signed: vec![(-5, 4), (255, 5)].into_iter().collect(),
+        large: vec![(u128::MAX, 6)].into_iter().collect(),
+        flags: vec![(true, 7)].into_iter().collect(),
+        names,
+    }
+}
+
+#[test]
+fn parse_literal_keys() {
+    let keys = keys();
+    let get = |path: &str| {
+        FallibleKeyPath::<Keys, u8>::parse(path)
+            .unwrap()
+            .get(&keys)
+            .copied()
+    };
+    assert_eq!(get("chars['a']").unwrap(), 1);
+    assert_eq!(get(r"chars['\n']").unwrap(), 2);
+    assert_eq!(get("bytes[b'a']").unwrap(), 3);
+    assert_eq!(get("bytes[97_u8]").unwrap(), 3);
+    assert_eq!(get("signed[-5]").unwrap(), 4);
+    assert_eq!(get("signed[-5_i64]").unwrap(), 4);
+    assert_eq!(get("signed[0xff]").unwrap(), 5);
+    assert_eq!(get("signed[0b1111_1111_i64]").unwrap(), 5);
+    assert_eq!(
+        get("large[340282366920938463463374607431768211455_u128]").unwrap(),
+        6
+    );
+    assert_eq!(get("flags[true]").unwrap(), 7);
+    assert!(get("flags[false]").is_err());
+    assert_eq!(get(r#"names[b"coco"][1]"#).unwrap(), 2);
+    assert_eq!(get(r##"names[br#"coco"#][0]"##).unwrap(), 1);
+
+    let position = |path| match PartialKeyPath::<Keys>::parse(path).err().unwrap() {
+        ParseError::Syntax { position, .. } => position,
+        other => panic!("unexpected error {:?}", other),
+    };
+    assert_eq!(position("bytes[256_u8]"), 6);
+    assert_eq!(position("bytes[5_u7]"), 6);
+    assert_eq!(position("signed[-5_u64]"), 7);
+    assert_eq!(position("chars['ab']"), 6);
+    assert_eq!(position("names[b\"é\"]"), 6);
+    assert_eq!(position("flags[truth]"), 6);
+}
+
+#[test]
+fn parse_wildcards() {
+    let keys = keys();
+    let names = WildcardKeyPath::<Keys, u8>::parse("names[*][*]").unwrap();
+    assert_eq!(names.iter(&keys).collect::<Vec<_>>(), [&1, &2]);
+    assert!(WildcardKeyPath::<Keys, u8>::parse("names[*]").is_err());
+    assert!(WildcardKeyPath::<Keys, u8>::parse("flags[*].0").is_err());
+
+    let err = FallibleKeyPath::<Keys, u8>::parse("names[*][0]")
+        .err()
+        .unwrap();
+    assert!(matches!(err, ParseError::Wildcard));
+}
+
+#[test]
+fn display_round_trips() {
+    let paths: Vec<PartialKeyPath<Keys>> = vec![
+        keypath!(Keys.chars['a']).into(),
+        keypath!(Keys.chars['\'']).into(),
+        keypath!(Keys.bytes[b'a']).into(),
+        keypath!(Keys.signed[-5]).into(),
+        keypath!(Keys.signed[5_i64]).into(),
+        keypath!(Keys.large[u128::MAX]).into(),
+        keypath!(Keys.flags[true]).into(),
+        keypath!(Keys.names["a \"b\"\n"][0]).into(),
+    ];
+    for path in paths {
+        let src: String = path.as_ref().iter().map(ToString::to_string).collect();
+        let parsed = PartialKeyPath::<Keys>::parse(&src).unwrap();
+        assert_eq!(parsed, path, "{}", src);
+    }
+
+    let names = keypath!(Keys.names[*][*]);
+    let src = names.to_string().replacen("Keys", "", 1);
+    let parsed = WildcardKeyPath::<Keys, u8>::parse(&src).unwrap();
+    assert_eq!(parsed.as_ref(), names.as_ref());
+}
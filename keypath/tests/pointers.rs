@@ -0,0 +1,184 @@
+//! paths through smart pointers and cells
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+
+use keypath::{keypath, FallibleKeyPath, FieldErrorKind, KeyPath, Keyable};
+
+#[derive(Keyable)]
+struct Tree {
+    root: Box<Node>,
+}
+
+#[derive(Keyable)]
+struct Node {
+    value: u32,
+    next: Option<Box<Node>>,
+}
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Expr {
+    Lit(u32),
+    Add(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Keyable, Clone)]
+struct Settings {
+    volume: u8,
+    names: Vec<String>,
+}
+
+#[derive(Keyable)]
+struct App {
+    shared: Rc<Settings>,
+    synced: Arc<Settings>,
+    defaults: Cow<'static, Settings>,
+    cache: RefCell<Settings>,
+    locked: Mutex<Settings>,
+    rw: RwLock<Settings>,
+}
+
+fn settings(volume: u8) -> Settings {
+    Settings {
+        volume,
+        names: vec!["coco".into()],
+    }
+}
+
+fn app() -> App {
+    App {
+        shared: Rc::new(settings(1)),
+        synced: Arc::new(settings(2)),
+        defaults: Cow::Owned(settings(3)),
+        cache: RefCell::new(settings(4)),
+        locked: Mutex::new(settings(5)),
+        rw: RwLock::new(settings(6)),
+    }
+}
+
+#[test]
+fn boxes() {
+    let mut tree = Tree {
+        root: Box::new(Node {
+            value: 1,
+            next: Some(Box::new(Node {
+                value: 2,
+                next: None,
+            })),
+        }),
+    };
+
+    let root = keypath!(Tree.root.value);
+    assert_eq!(tree[&root], 1);
+    tree[&root] = 3;
+    assert_eq!(tree.root.value, 3);
+
+    let next = keypath!(Tree.root.next?.value);
+    assert_eq!(next.get(&tree).unwrap(), &2);
+    *next.get_mut(&mut tree).unwrap() = 4;
+    assert_eq!(tree.root.next.as_ref().unwrap().value, 4);
+    assert!(keypath!(Tree.root.next?.next?.value).get(&tree).is_err());
+
+    let boxed: KeyPath<Tree, Box<Node>> = keypath!(Tree.root);
+    assert_eq!(tree[&boxed].value, 3);
+    let parsed = FallibleKeyPath::<Tree, u32>::parse("root.next?.value").unwrap();
+    assert_eq!(parsed, next);
+
+    let expr = Expr::Add(Box::new(Expr::Lit(1)), Box::new(Expr::Lit(2)));
+    let rhs = keypath!((Expr)::Add.1::Lit.0);
+    assert_eq!(rhs.get(&expr).unwrap(), &2);
+}
+
+#[test]
+fn shared_pointers() {
+    let mut app = app();
+    let volume = keypath!(App.shared.volume);
+    assert_eq!(app[&volume], 1);
+    app[&volume] = 7;
+    assert_eq!(app.shared.volume, 7);
+
+    let other = Rc::clone(&app.shared);
+    let err = app.try_any_at_path_mut(&volume).err().unwrap();
+    assert!(matches!(err.kind(), FieldErrorKind::SharedPointer));
+    assert_eq!(err.failed_index(), Some(1));
+    drop(other);
+
+    let synced = keypath!(App.synced.names[0]);
+    let _other = Arc::clone(&app.synced);
+    assert_eq!(synced.get(&app).unwrap(), "coco");
+    assert!(synced.get_mut(&mut app).is_err());
+
+    // a path that ends at the pointer replaces it, even when it is shared
+    let pointer: KeyPath<App, Arc<Settings>> = keypath!(App.synced);
+    app[&pointer] = Arc::new(settings(8));
+    assert_eq!(app.synced.volume, 8);
+}
+
+#[test]
+fn copy_on_write() {
+    static DEFAULTS: Settings = Settings {
+        volume: 9,
+        names: Vec::new(),
+    };
+    let mut app = app();
+    app.defaults = Cow::Borrowed(&DEFAULTS);
+
+    let volume = keypath!(App.defaults.volume);
+    assert_eq!(app[&volume], 9);
+    app[&volume] = 10;
+    assert!(matches!(app.defaults, Cow::Owned(_)));
+    assert_eq!(app.defaults.volume, 10);
+    assert_eq!(DEFAULTS.volume, 9);
+}
+
+#[test]
+fn cells() {
+    let mut app = app();
+    let cache = keypath!(App.cache.volume);
+    let locked = keypath!(App.locked.names[0]);
+    let rw = keypath!(App.rw.volume);
+
+    let err = app.try_any_at_path(&cache).err().unwrap();
+    assert!(matches!(err.kind(), FieldErrorKind::ScopedContents));
+    assert_eq!(
+        err.to_string(),
+        "the contents of RefCell<Settings> can only be borrowed within a closure at App.cache.volume"
+    );
+
+    let read = |path: &[keypath::internals::PathComponent]| {
+        app.with_any_at_path(path, |value| format!("{:?}", value.downcast_ref::<u8>()))
+            .unwrap()
+    };
+    assert_eq!(read(cache.as_ref()), "Some(4)");
+    assert_eq!(read(rw.as_ref()), "Some(6)");
+    let name = app.with_any_at_path(&locked, |name| name.downcast_ref::<String>().cloned());
+    assert_eq!(name.unwrap().unwrap(), "coco");
+
+    // exclusive access doesn't need to borrow
+    app[&cache] += 1;
+    *locked.get_mut(&mut app).unwrap() = "eli".into();
+    app[&rw] = 0;
+    assert_eq!(app.cache.borrow().volume, 5);
+    assert_eq!(app.locked.lock().unwrap().names[0], "eli");
+    assert_eq!(app.rw.read().unwrap().volume, 0);
+
+    let _borrow = app.cache.borrow_mut();
+    let err = app.with_any_at_path(&cache, |_| ()).unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::AlreadyBorrowed));
+    assert_eq!(err.failed_index(), Some(1));
+}
+
+#[test]
+fn missing_contents() {
+    let app = app();
+    let path = [
+        keypath::internals::PathComponent::named("cache"),
+        keypath::internals::PathComponent::named("loudness"),
+    ];
+    let err = app.with_any_at_path(path, |_| ()).unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::InvalidField(_)));
+    assert_eq!(err.failed_index(), Some(1));
+}
@@ -0,0 +1,150 @@
+//! the different kinds of type that can appear at the root of a keypath
+
+use keypath::{keypath, KeyPath, Keyable};
+
+mod model {
+    use keypath::Keyable;
+
+    #[derive(Keyable)]
+    pub struct Person {
+        pub name: String,
+        pub size: (u8, u8),
+        pub shape: Shape,
+    }
+
+    #[derive(Keyable)]
+    #[allow(dead_code)]
+    pub enum Shape {
+        Circle { radius: u8 },
+        Square(u8),
+    }
+}
+
+use model::{Person, Shape};
+
+#[derive(Keyable)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+const INNER: KeyPath<Wrapper<(u8, u8)>, u8> = keypath!(Wrapper<(u8, u8)>.inner.1);
+
+impl Person {
+    fn width() -> KeyPath<Self, u8> {
+        keypath!(Self.size.0)
+    }
+}
+
+fn coco() -> Person {
+    Person {
+        name: "coco".into(),
+        size: (45, 12),
+        shape: Shape::Circle { radius: 3 },
+    }
+}
+
+#[test]
+fn qualified_paths() {
+    let person = coco();
+    assert_eq!(person[&keypath!(model::Person.name)], "coco");
+    assert_eq!(person[&keypath!(crate::model::Person.size.1)], 12);
+    assert_eq!(person[&keypath!(self::Person.name)], "coco");
+
+    let radius = keypath!((model::Shape)::Circle.radius);
+    assert_eq!(radius.get(&person.shape).unwrap(), &3);
+    let side = keypath!((crate::model::Shape)::Square.0);
+    assert!(side.get(&person.shape).is_err());
+
+    // capitalized modules are never taken for enums
+    let label = keypath!(Legacy::Label.text);
+    let legacy = Legacy::Label { text: "old".into() };
+    assert_eq!(legacy[&label], "old");
+}
+
+#[test]
+fn self_type() {
+    let person = coco();
+    assert_eq!(person[&Person::width()], 45);
+}
+
+#[test]
+fn nested_generics() {
+    let wrapper = Wrapper {
+        inner: vec![1u8, 2, 3],
+    };
+    let second = keypath!(Wrapper<Vec<u8>>.inner[1]);
+    assert_eq!(second.get(&wrapper).unwrap(), &2);
+
+    let wrapper = Wrapper {
+        inner: Wrapper { inner: 5u8 },
+    };
+    assert_eq!(wrapper[&keypath!(Wrapper<Wrapper<u8>>.inner.inner)], 5);
+
+    let wrapper = Wrapper { inner: (1, 2) };
+    assert_eq!(wrapper[&INNER], 2);
+}
+
+#[test]
+fn tuples() {
+    let pair = (1u8, String::from("two"));
+    assert_eq!(pair.item_at_path(&keypath!((u8, String).1)), "two");
+
+    let people = vec![(0u16, coco())];
+    let name = keypath!(Vec<(u16, Person)>[0].1.name);
+    assert_eq!(name.get(&people).unwrap(), "coco");
+}
+
+/// A model with a record type, to test associated type roots.
+trait Registry {
+    type Record: Keyable;
+
+    fn record_name() -> KeyPath<Self::Record, String>;
+}
+
+struct People;
+
+impl Registry for People {
+    type Record = Person;
+
+    fn record_name() -> KeyPath<Self::Record, String> {
+        keypath!(Self::Record.name)
+    }
+}
+
+#[allow(non_snake_case)]
+mod Legacy {
+    use keypath::Keyable;
+
+    #[derive(Keyable)]
+    pub struct Label {
+        pub text: String,
+    }
+}
+
+#[allow(non_camel_case_types)]
+mod legacy {
+    use keypath::Keyable;
+
+    #[derive(Keyable)]
+    #[allow(dead_code)]
+    pub enum shape {
+        circle { radius: u8 },
+        square(u8),
+    }
+}
+
+#[test]
+fn explicit_roots() {
+    let person = coco();
+    assert_eq!(person[&People::record_name()], "coco");
+
+    // a variant follows a root in parentheses
+    let radius = keypath!((Shape)::Circle.radius);
+    assert_eq!(radius.get(&person.shape).unwrap(), &3);
+
+    // whatever its capitalization
+    let radius = keypath!((legacy::shape)::circle.radius);
+    let shape = legacy::shape::circle { radius: 4 };
+    assert_eq!(radius.get(&shape).unwrap(), &4);
+    assert!(keypath!((legacy::shape)::square.0).get(&shape).is_err());
+}
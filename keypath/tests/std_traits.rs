@@ -0,0 +1,133 @@
+//! keypaths as ordinary values: printing, comparing, hashing
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use keypath::{keypath, AnyKeyPath, FallibleKeyPath, KeyPath, Keyable, PartialKeyPath};
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    friends: Vec<Person>,
+    tags: HashMap<String, String>,
+    pet: Option<Pet>,
+    size: Size,
+}
+
+#[derive(Keyable)]
+struct Size(u8, u8);
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Pet {
+    Cat { name: String },
+    Dog(String),
+}
+
+#[derive(Keyable)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[test]
+fn display() {
+    assert_eq!(keypath!(Person.name).to_string(), "Person.name");
+    assert_eq!(keypath!(Person.size.1).to_string(), "Person.size.1");
+    assert_eq!(
+        keypath!(Person.friends[0].name).to_string(),
+        "Person.friends[0].name"
+    );
+    assert_eq!(
+        keypath!(Person.tags["a b"]).to_string(),
+        r#"Person.tags["a b"]"#
+    );
+    assert_eq!(
+        keypath!(Person.pet?::Cat.name).to_string(),
+        "Person.pet?::Cat.name"
+    );
+    assert_eq!(keypath!(Wrapper<u8>.inner).to_string(), "Wrapper<u8>.inner");
+
+    let partial = PartialKeyPath::from(keypath!(Person.name));
+    assert_eq!(partial.to_string(), "Person.name");
+    assert_eq!(AnyKeyPath::from(partial).to_string(), "Person.name");
+}
+
+#[test]
+fn debug() {
+    assert_eq!(
+        format!("{:?}", keypath!(Person.name)),
+        "KeyPath(Person.name)"
+    );
+    assert_eq!(
+        format!("{:?}", keypath!(Person.friends[0])),
+        "FallibleKeyPath(Person.friends[0])"
+    );
+    assert_eq!(
+        format!("{:?}", AnyKeyPath::from(keypath!(Person.name))),
+        "AnyKeyPath(Person.name)"
+    );
+}
+
+#[test]
+fn equality_and_hashing() {
+    let name = keypath!(Person.name);
+    let parsed = KeyPath::<Person, String>::parse("name").unwrap();
+    assert_eq!(name, parsed);
+    assert_ne!(
+        PartialKeyPath::from(name.clone()),
+        PartialKeyPath::from(keypath!(Person.size))
+    );
+
+    let mut dirty = HashSet::new();
+    dirty.insert(name.clone());
+    assert!(dirty.contains(&parsed));
+
+    let i = 1;
+    let mut cache: HashMap<FallibleKeyPath<Person, String>, usize> = HashMap::new();
+    cache.insert(keypath!(Person.friends[1].name), 5);
+    assert_eq!(cache.get(&keypath!(Person.friends[i].name)), Some(&5));
+    assert_eq!(cache.get(&keypath!(Person.friends[0].name)), None);
+
+    // erased paths also compare their value type
+    let a = AnyKeyPath::from(keypath!(Person.size.0));
+    let b = AnyKeyPath::from(FallibleKeyPath::from(keypath!(Person.size)));
+    assert_ne!(a, b);
+    assert_eq!(a, AnyKeyPath::from(keypath!(Person.size.0)));
+}
+
+#[test]
+fn ordering() {
+    let paths: BTreeSet<_> = vec![
+        keypath!(Person.friends[2]),
+        keypath!(Person.friends[0]),
+        keypath!(Person.friends[1]),
+    ]
+    .into_iter()
+    .collect();
+    let sorted = paths.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        sorted,
+        [
+            "Person.friends[0]",
+            "Person.friends[1]",
+            "Person.friends[2]"
+        ]
+    );
+}
+
+#[test]
+fn clone_into_closure() {
+    let person = Person {
+        name: "coco".into(),
+        friends: Vec::new(),
+        tags: HashMap::new(),
+        pet: None,
+        size: Size(1, 2),
+    };
+    let name = keypath!(Person.name);
+    let get_name = {
+        let name = name.clone();
+        move |p: &Person| p[&name].clone()
+    };
+    assert_eq!(get_name(&person), "coco");
+    assert_eq!(person[&name], "coco");
+}
@@ -0,0 +1,188 @@
+//! wildcard paths, which match every item of a collection
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use keypath::internals::PathComponent;
+use keypath::{keypath, FieldErrorKind, Keyable, WildcardKeyPath};
+
+#[derive(Keyable)]
+struct Team {
+    members: Vec<Person>,
+    roles: BTreeMap<String, Person>,
+    scores: HashMap<String, Vec<u32>>,
+    backup: Box<[Person; 1]>,
+    history: RefCell<Vec<u32>>,
+}
+
+#[derive(Keyable)]
+struct Person {
+    name: String,
+    pet: Option<Pet>,
+}
+
+#[derive(Keyable)]
+#[allow(dead_code)]
+enum Pet {
+    Dog { name: String },
+    Fish,
+}
+
+fn person(name: &str, pet: Option<Pet>) -> Person {
+    Person {
+        name: name.into(),
+        pet,
+    }
+}
+
+fn team() -> Team {
+    let mut roles = BTreeMap::new();
+    roles.insert("lead".to_string(), person("nico", None));
+    roles.insert("cook".to_string(), person("yaya", None));
+    let mut scores = HashMap::new();
+    scores.insert("coco".to_string(), vec![1, 2]);
+    Team {
+        members: vec![
+            person(
+                "coco",
+                Some(Pet::Dog {
+                    name: "jojo".into(),
+                }),
+            ),
+            person("eli", Some(Pet::Fish)),
+            person("nico", None),
+        ],
+        roles,
+        scores,
+        backup: Box::new([person("toto", None)]),
+        history: RefCell::new(vec![3, 4]),
+    }
+}
+
+#[test]
+fn sequences() {
+    let team = team();
+    let names: WildcardKeyPath<Team, String> = keypath!(Team.members[*].name);
+    assert_eq!(
+        names.iter(&team).collect::<Vec<_>>(),
+        ["coco", "eli", "nico"]
+    );
+    assert_eq!(names.to_string(), "Team.members[*].name");
+
+    let keys = names
+        .matches(&team)
+        .map(|(keys, _)| keys)
+        .collect::<Vec<_>>();
+    assert_eq!(keys[2], [PathComponent::IndexInt(2)]);
+
+    let backup = keypath!(Team.backup[*].name);
+    assert_eq!(backup.iter(&team).collect::<Vec<_>>(), ["toto"]);
+}
+
+#[test]
+fn maps() {
+    let team = team();
+    let roles = keypath!(Team.roles[*].name);
+    let found = roles
+        .matches(&team)
+        .map(|(keys, name)| (keys[0].to_string(), name.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [("[\"cook\"]".into(), "yaya"), ("[\"lead\"]".into(), "nico")]
+    );
+
+    let scores = keypath!(Team.scores[*][*]);
+    let (keys, score) = scores.matches(&team).last().unwrap();
+    assert_eq!(
+        keys,
+        [PathComponent::index_str("coco"), PathComponent::IndexInt(1)]
+    );
+    assert_eq!(score, &2);
+}
+
+#[test]
+fn unresolved_items_are_skipped() {
+    let team = team();
+    let dogs = keypath!(Team.members[*].pet?::Dog.name);
+    assert_eq!(dogs.iter(&team).collect::<Vec<_>>(), ["jojo"]);
+
+    let first = keypath!(Team.scores[*][0]);
+    assert_eq!(first.iter(&team).collect::<Vec<_>>(), [&1]);
+
+    let i = 5;
+    let missing = keypath!(Team.scores[*][i]);
+    assert_eq!(missing.iter(&team).count(), 0);
+}
+
+#[test]
+fn mutation() {
+    let mut team = team();
+    let names = keypath!(Team.members[*].name);
+    for name in names.iter_mut(&mut team).unwrap() {
+        name.push('!');
+    }
+    assert_eq!(team.members[1].name, "eli!");
+
+    let mut seen = Vec::new();
+    keypath!(Team.roles[*].name)
+        .for_each_mut(&mut team, |keys, name| {
+            seen.push(keys.to_vec());
+            name.make_ascii_uppercase();
+        })
+        .unwrap();
+    assert_eq!(
+        seen,
+        [
+            [PathComponent::index_str("cook")],
+            [PathComponent::index_str("lead")]
+        ]
+    );
+    assert_eq!(team.roles["lead"].name, "NICO");
+}
+
+#[test]
+fn cells_need_mutable_access() {
+    let mut team = team();
+    let history = keypath!(Team.history[*]);
+    assert_eq!(history.iter(&team).count(), 0);
+
+    history
+        .for_each_mut(&mut team, |_, entry| *entry *= 10)
+        .unwrap();
+    assert_eq!(*team.history.borrow(), [30, 40]);
+}
+
+#[derive(Keyable)]
+struct Kennel {
+    pets: Rc<Vec<Person>>,
+    owners: Vec<Rc<Person>>,
+}
+
+#[test]
+fn shared_pointers_are_errors() {
+    let mut kennel = Kennel {
+        pets: Rc::new(vec![person("jojo", None)]),
+        owners: vec![Rc::new(person("coco", None))],
+    };
+    let pets = keypath!(Kennel.pets[*].name);
+    let owners = keypath!(Kennel.owners[*].name);
+    pets.for_each_mut(&mut kennel, |_, name| name.push('!'))
+        .unwrap();
+    owners
+        .for_each_mut(&mut kennel, |_, name| name.push('!'))
+        .unwrap();
+    assert_eq!(kennel.pets[0].name, "jojo!");
+    assert_eq!(kennel.owners[0].name, "coco!");
+
+    let shared = (Rc::clone(&kennel.pets), Rc::clone(&kennel.owners[0]));
+    assert_eq!(pets.iter(&kennel).count(), 1);
+    let err = pets.for_each_mut(&mut kennel, |_, _| panic!()).unwrap_err();
+    assert!(matches!(err.kind(), FieldErrorKind::SharedPointer));
+    assert_eq!(err.failed_index(), Some(1));
+    let err = owners.iter_mut(&mut kennel).err().unwrap();
+    assert!(matches!(err.kind(), FieldErrorKind::SharedPointer));
+    assert_eq!(err.failed_index(), Some(2));
+    drop(shared);
+}